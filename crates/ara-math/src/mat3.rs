@@ -1,4 +1,4 @@
-use super::Vec2;
+use super::{Rect, Vec2};
 use std::ops::Mul;
 
 #[repr(C)]
@@ -158,6 +158,17 @@ impl Mat3 {
             + m[2] * (m[3] * m[7] - m[4] * m[6])
     }
 
+    /// How much lengths are scaled on average, the square root of how much areas are scaled
+    pub fn average_scale(&self) -> f32 {
+        self.det().abs().sqrt()
+    }
+
+    /// Checks if the edges of rects stay axis aligned, so `transform_rect` covers them exactly
+    pub fn is_axis_aligned(&self) -> bool {
+        let m = &self.data;
+        (m[1] == 0.0 && m[3] == 0.0) || (m[0] == 0.0 && m[4] == 0.0)
+    }
+
     pub fn is_identity(&self) -> bool {
         self == &Self::IDENTITY
     }
@@ -180,6 +191,39 @@ impl Mat3 {
     }
 }
 
+impl Mat3 {
+    /// Returns the axis aligned bounding box of the rect after applying this transform
+    pub fn transform_rect(&self, rect: &Rect<f32>) -> Rect<f32> {
+        let corners = [
+            *self * rect.top_left(),
+            *self * rect.top_right(),
+            *self * rect.bottom_left(),
+            *self * rect.bottom_right(),
+        ];
+
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in &corners[1..] {
+            min = min.min(corner);
+            max = max.max(corner);
+        }
+
+        Rect::from_corners(min, max)
+    }
+
+    /// Returns the matrix as three column vectors, each padded to four floats.
+    /// This matches the memory layout of a `mat3x3<f32>` in a WGSL uniform buffer.
+    #[rustfmt::skip]
+    pub fn to_cols_array_padded(&self) -> [[f32; 4]; 3] {
+        let m = &self.data;
+        [
+            [m[0], m[3], m[6], 0.0], // Column 0
+            [m[1], m[4], m[7], 0.0], // Column 1
+            [m[2], m[5], m[8], 0.0], // Column 2
+        ]
+    }
+}
+
 impl From<Mat3> for [[f32; 4]; 4] {
     #[rustfmt::skip]
     fn from(mat: Mat3) -> Self {
@@ -85,6 +85,42 @@ mod tests {
         fn is_identity() {
             assert!(mat3().is_identity())
         }
+
+        #[test]
+        fn is_axis_aligned() {
+            let mut m = mat3();
+            m.scale(2.0, -1.0).translate(5.0, 5.0);
+            assert!(m.is_axis_aligned());
+
+            assert!(!Mat3::from_rotation(0.3).is_axis_aligned());
+        }
+
+        #[test]
+        fn transform_rect() {
+            let rect = Rect::xywh(0.0, 0.0, 10.0, 20.0);
+
+            let mut m = mat3();
+            m.scale(2.0, 2.0).translate(5.0, 5.0);
+            assert_eq!(m.transform_rect(&rect), Rect::xywh(10.0, 10.0, 20.0, 40.0));
+
+            let r = Mat3::from_rotation(std::f32::consts::FRAC_PI_2).transform_rect(&rect);
+            assert!((r.min() - vec2(-20.0, 0.0)).magnitude() < 1e-4);
+            assert!((r.max() - vec2(0.0, 10.0)).magnitude() < 1e-4);
+        }
+
+        #[test]
+        fn padded_columns() {
+            let m = Mat3::from_translation(10.0, 20.0);
+
+            assert_eq!(
+                m.to_cols_array_padded(),
+                [
+                    [1.0, 0.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [10.0, 20.0, 1.0, 0.0]
+                ]
+            );
+        }
     }
     mod vec2 {
         use crate::traits::{One, Zero};
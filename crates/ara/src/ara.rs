@@ -14,6 +14,9 @@ pub mod scene;
 
 pub mod path;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
 pub use path::*;
 
 pub use ara_math as math;
@@ -24,9 +27,10 @@ pub use math::{mat3, vec2, Corners, Mat3, Rect, Size, Vec2};
 pub use paint::color::{Color, Rgba};
 pub use paint::DrawList;
 pub use paint::{
-    circle, quad, AraAtlas, AtlasKey, AtlasKeySource, AtlasTextureInfo, AtlasTextureInfoMap, Brush,
-    Circle, FillStyle, LineCap, LineJoin, Quad, StrokeStyle, Text, TextAlign, TextBaseline,
-    TextureAtlas,
+    circle, quad, stroke_to_path, AraAtlas, AtlasKey, AtlasKeySource, AtlasTextureInfo,
+    AtlasTextureInfoMap, Brush, Circle, FillStyle, Gradient, GradientKind, GradientStop, Image,
+    ImageId, LineCap, LineDash, LineJoin, Paint, PaintShader, Pattern, Quad, SpreadMode,
+    StrokeStyle, Text, TextAlign, TextBaseline, TextureAtlas,
 };
 
 pub use canvas::{
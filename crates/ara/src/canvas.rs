@@ -281,10 +281,26 @@ impl Canvas {
         self.list.stage_changes(self.current_state.clone());
     }
 
-    #[inline]
     pub fn draw_primitive(&mut self, prim: impl Into<Primitive>, brush: Brush) {
-        self.list
-            .add(GraphicsInstruction::brush(prim, brush.clone()));
+        let prim = prim.into();
+        let fill = &brush.fill_style;
+        let stroke = &brush.stroke_style;
+
+        // a draw has a single shader, fills and strokes painted differently are drawn separately
+        if !fill.color.is_transparent()
+            && !stroke.color.is_transparent()
+            && fill.shader != stroke.shader
+        {
+            self.list.add(GraphicsInstruction::brush(
+                prim.clone(),
+                brush.clone().no_stroke(),
+            ));
+            self.list
+                .add(GraphicsInstruction::brush(prim, brush.no_fill()));
+            return;
+        }
+
+        self.list.add(GraphicsInstruction::brush(prim, brush));
     }
 
     pub fn draw_path(&mut self, path: impl Into<Path>, brush: impl Into<PathBrush>) {
@@ -532,9 +548,13 @@ impl Canvas {
 
             for batch in batcher {
                 let render_texture = batch.renderer_texture.clone();
-                if let Some(renderable) =
+                let shader = batch
+                    .shader
+                    .map(|shader| (shader.clone(), staged.state.transform));
+                if let Some(mut renderable) =
                     self.build_renderable(&mut drawlist, batch, render_texture, staged.state)
                 {
+                    renderable.shader = shader;
                     self.cached_renderables.push(renderable);
                 }
             }
@@ -599,6 +619,7 @@ impl Canvas {
         Some(Renderable {
             clip_rect: canvas_state.clip_rect.clone(),
             mesh,
+            shader: None,
         })
     }
 }
@@ -4,7 +4,7 @@ use ara_math::Size;
 use futures::channel::oneshot::{self};
 use wgpu::{BufferAsyncError, PollType, TextureUsages};
 
-use crate::Context;
+use crate::{render::read_texture_async, Context};
 
 use super::Canvas;
 
@@ -85,66 +85,11 @@ impl Canvas {
     }
 }
 
-// FIXME: Alignment for copy buffer
 pub fn read_texels_async(
     gpu: &Context,
     src: &wgpu::Texture,
     read: impl FnOnce(Result<Vec<u8>, BufferAsyncError>) + Send + 'static,
 ) -> Result<()> {
-    let bytes_per_texel = src
-        .format()
-        .block_copy_size(
-            None, /* Sorry I wont read any depth or stencil textures */
-        )
-        .ok_or(anyhow::anyhow!("Invalid format unable to get texel size"))?;
-
-    let buffer_size = (src.width() * src.height() * bytes_per_texel) as u64;
-
-    let output_buffer = gpu.device.create_buffer(
-        &(wgpu::BufferDescriptor {
-            label: Some("Output Buffer"),
-            size: buffer_size,
-            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        }),
-    );
-
-    let mut encoder = gpu.create_command_encoder(Some("Command Encoder"));
-
-    encoder.copy_texture_to_buffer(
-        wgpu::TexelCopyTextureInfo {
-            texture: src,
-            mip_level: 0,
-            origin: wgpu::Origin3d::ZERO,
-            aspect: wgpu::TextureAspect::All,
-        },
-        wgpu::TexelCopyBufferInfo {
-            buffer: &output_buffer,
-            layout: wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(src.width() * bytes_per_texel),
-                rows_per_image: Some(src.height()),
-            },
-        },
-        wgpu::Extent3d {
-            width: src.width(),
-            height: src.height(),
-            depth_or_array_layers: 1,
-        },
-    );
-
-    // Submit the commands
-    gpu.queue.submit(Some(encoder.finish()));
-    let buffer_slice = output_buffer.slice(..);
-
-    buffer_slice.map_async(wgpu::MapMode::Read, {
-        let buffer = output_buffer.clone();
-        move |res| {
-            let data = buffer.slice(..).get_mapped_range();
-            let res = res.map(|_| data.to_vec());
-            read(res)
-        }
-    });
-
-    Ok(())
+    let size = Size::new(src.width(), src.height());
+    read_texture_async(gpu, src, size, read)
 }
@@ -36,6 +36,8 @@ pub async fn create_instance_with_wgpu_detection(
 pub struct ContextSpecification<'window> {
     pub power_preference: wgpu::PowerPreference,
     pub backends: wgpu::Backends,
+    /// only accept a software adapter (eg: llvmpipe, lavapipe, WARP)
+    pub force_fallback_adapter: bool,
     pub compatible_surface_target: Option<wgpu::SurfaceTarget<'window>>,
 }
 impl<'window> ContextSpecification<'window> {
@@ -98,7 +100,7 @@ impl Context {
             .request_adapter(
                 &(wgpu::RequestAdapterOptions {
                     power_preference: specs.power_preference,
-                    force_fallback_adapter: false,
+                    force_fallback_adapter: specs.force_fallback_adapter,
                     compatible_surface: compatible_surface.as_ref(),
                 }),
             )
@@ -3,10 +3,15 @@ pub mod brush;
 pub mod color;
 pub mod draw_list;
 pub mod geometry;
+pub mod gradient;
 pub mod graphics_instruction;
 pub mod image;
 pub mod mesh;
+pub mod paint_shader;
+pub mod pattern;
 pub mod primitives;
+pub mod rasterizer;
+pub mod stroke_outline;
 pub mod stroke_tessellate;
 pub mod text;
 pub mod texture;
@@ -18,10 +23,15 @@ pub use brush::*;
 pub use color::*;
 pub use draw_list::*;
 pub use geometry::*;
+pub use gradient::*;
 pub use graphics_instruction::*;
 pub use image::*;
 pub use mesh::*;
+pub use paint_shader::*;
+pub use pattern::*;
 pub use primitives::*;
+pub use rasterizer::*;
+pub use stroke_outline::*;
 pub use stroke_tessellate::*;
 pub use text::*;
 pub use texture::*;
@@ -1,13 +1,17 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    borrow::Cow,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
 
 use ara_math::{Corners, Rect};
 
 use crate::{
     path::{Contour, Point},
-    Canvas, PathBuilder, Polygon,
+    Canvas, FillRule, PathBuilder, Polygon,
 };
 
-use super::Color;
+use super::{Color, Gradient, Image, PaintShader, Pattern};
 
 /// Represents a brush used for drawing operations, which includes properties for fill style, stroke style, and anti-aliasing.
 #[derive(Debug, Clone, PartialEq)]
@@ -21,9 +25,7 @@ impl Default for Brush {
     /// Creates a default brush with transparent fill and stroke, and anti-aliasing disabled.
     fn default() -> Self {
         Self {
-            fill_style: FillStyle {
-                color: Color::TRANSPARENT,
-            },
+            fill_style: FillStyle::default(),
             stroke_style: StrokeStyle {
                 color: Color::TRANSPARENT,
                 ..Default::default()
@@ -36,7 +38,7 @@ impl Default for Brush {
 impl Brush {
     pub fn filled(fill_color: Color) -> Self {
         Self {
-            fill_style: FillStyle { color: fill_color },
+            fill_style: FillStyle::from(fill_color),
             ..Default::default()
         }
     }
@@ -66,7 +68,13 @@ impl Brush {
     ///
     /// * `color` - The new fill color to be applied.
     pub fn fill_color(mut self, color: Color) -> Self {
-        self.fill_style.color = color;
+        self.fill_style = self.fill_style.paint(color);
+        self
+    }
+
+    /// Sets the fill of the brush to a color, a gradient or a pattern
+    pub fn fill_paint(mut self, paint: impl Into<Paint>) -> Self {
+        self.fill_style = self.fill_style.paint(paint);
         self
     }
 
@@ -81,12 +89,12 @@ impl Brush {
     }
 
     pub fn no_fill(mut self) -> Self {
-        self.fill_style.color = Color::TRANSPARENT;
+        self.fill_style = self.fill_style.paint(Color::TRANSPARENT);
         self
     }
 
     pub fn no_stroke(mut self) -> Self {
-        self.stroke_style.color = Color::TRANSPARENT;
+        self.stroke_style = self.stroke_style.paint(Color::TRANSPARENT);
         self
     }
 
@@ -100,6 +108,16 @@ impl Brush {
         self
     }
 
+    /// Sets which areas of an overlapping or self intersecting path are filled.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill_rule` - The fill rule (e.g., non zero, even odd).
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_style.fill_rule = fill_rule;
+        self
+    }
+
     /// Gets the current stroke color of the brush.
     pub fn get_stroke_color(&self) -> Color {
         self.fill_style.color
@@ -111,7 +129,13 @@ impl Brush {
     ///
     /// * `color` - The new stroke color to be applied.
     pub fn stroke_color(mut self, color: Color) -> Self {
-        self.stroke_style.color = color;
+        self.stroke_style = self.stroke_style.paint(color);
+        self
+    }
+
+    /// Sets the stroke of the brush to a color, a gradient or a pattern
+    pub fn stroke_paint(mut self, paint: impl Into<Paint>) -> Self {
+        self.stroke_style = self.stroke_style.paint(paint);
         self
     }
 
@@ -120,7 +144,7 @@ impl Brush {
     /// # Arguments
     ///
     /// * `stroke_width` - The new stroke width to be applied.
-    pub fn line_width(mut self, line_width: u32) -> Self {
+    pub fn line_width(mut self, line_width: f32) -> Self {
         self.stroke_style.line_width = line_width;
         self
     }
@@ -145,6 +169,16 @@ impl Brush {
         self
     }
 
+    /// Sets how long a miter join can get before it is beveled or clipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `miter_limit` - The limit as a ratio of the miter length to half the line width.
+    pub fn miter_limit(mut self, miter_limit: f32) -> Self {
+        self.stroke_style.miter_limit = miter_limit;
+        self
+    }
+
     /// Sets the stroke line cap style for the brush.
     ///
     /// # Arguments
@@ -197,9 +231,65 @@ impl Brush {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// What a fill or a stroke is drawn with
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Paint {
+    Color(Color),
+    Gradient(Gradient),
+    Pattern(Pattern),
+}
+
+impl<T> From<T> for Paint
+where
+    T: Into<Color>,
+{
+    fn from(value: T) -> Self {
+        Self::Color(value.into())
+    }
+}
+
+impl From<Gradient> for Paint {
+    fn from(gradient: Gradient) -> Self {
+        Self::Gradient(gradient)
+    }
+}
+
+impl From<Pattern> for Paint {
+    fn from(pattern: Pattern) -> Self {
+        Self::Pattern(pattern)
+    }
+}
+
+impl From<Image> for Paint {
+    fn from(image: Image) -> Self {
+        Self::Pattern(image.into())
+    }
+}
+
+impl Paint {
+    /// Vertex color and shader of the paint, shaders are drawn with white vertices
+    /// which carry the antialiasing coverage
+    fn split(self) -> (Color, Option<Box<PaintShader>>) {
+        match self {
+            Paint::Color(color) => (color, None),
+            Paint::Gradient(gradient) => (
+                Color::WHITE,
+                Some(Box::new(PaintShader::Gradient(gradient))),
+            ),
+            Paint::Pattern(pattern) => {
+                (Color::WHITE, Some(Box::new(PaintShader::Pattern(pattern))))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FillStyle {
+    /// tints the shader when there is one
     pub color: Color,
+    pub shader: Option<Box<PaintShader>>,
+    /// areas of a path's contours which are filled, only used for paths
+    pub fill_rule: FillRule,
 }
 
 impl<T> From<T> for FillStyle
@@ -209,14 +299,24 @@ where
     fn from(value: T) -> Self {
         Self {
             color: value.into(),
+            shader: None,
+            fill_rule: FillRule::default(),
         }
     }
 }
 
+impl From<Gradient> for FillStyle {
+    fn from(gradient: Gradient) -> Self {
+        Self::default().paint(gradient)
+    }
+}
+
 impl Default for FillStyle {
     fn default() -> Self {
         Self {
             color: Color::TRANSPARENT,
+            shader: None,
+            fill_rule: FillRule::default(),
         }
     }
 }
@@ -226,11 +326,24 @@ impl FillStyle {
         self.color = color;
         self
     }
+
+    /// Replaces the color and the shader
+    pub fn paint(mut self, paint: impl Into<Paint>) -> Self {
+        (self.color, self.shader) = paint.into().split();
+        self
+    }
+
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LineJoin {
     Miter,
+    /// a miter cut off at the miter limit instead of falling back to a bevel
+    MiterClip,
     Bevel,
     Round,
 }
@@ -242,22 +355,129 @@ pub enum LineCap {
     Butt,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Lengths of the dashes and gaps a stroke alternates between,
+/// see `setLineDash` and `lineDashOffset` of a canvas
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineDash {
+    /// dash and gap lengths, repeated once when there is an odd number of them
+    pub segments: Vec<f32>,
+    /// distance into the pattern the stroke starts at
+    pub offset: f32,
+}
+
+impl LineDash {
+    pub fn new(segments: impl Into<Vec<f32>>) -> Self {
+        Self {
+            segments: segments.into(),
+            offset: 0.0,
+        }
+    }
+
+    pub fn offset(mut self, offset: f32) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Checks if the segments can be used as a dash, a canvas ignores them otherwise
+    pub fn is_valid(segments: &[f32]) -> bool {
+        segments
+            .iter()
+            .all(|segment| segment.is_finite() && *segment >= 0.0)
+    }
+
+    /// Lengths of alternating dashes and gaps,
+    /// none when the stroke is solid
+    pub(crate) fn pattern(&self) -> Option<Cow<'_, [f32]>> {
+        if !Self::is_valid(&self.segments) || !self.offset.is_finite() {
+            return None;
+        }
+        if self.segments.iter().sum::<f32>() <= 0.0 {
+            return None;
+        }
+
+        if self.segments.len() % 2 == 1 {
+            Some(Cow::Owned(self.segments.repeat(2)))
+        } else {
+            Some(Cow::Borrowed(&self.segments))
+        }
+    }
+}
+
+impl Eq for LineDash {}
+
+impl Hash for LineDash {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for segment in &self.segments {
+            segment.to_bits().hash(state);
+        }
+        self.offset.to_bits().hash(state);
+    }
+}
+
+impl From<Vec<f32>> for LineDash {
+    fn from(segments: Vec<f32>) -> Self {
+        Self::new(segments)
+    }
+}
+
+impl From<&[f32]> for LineDash {
+    fn from(segments: &[f32]) -> Self {
+        Self::new(segments)
+    }
+}
+
+impl<const N: usize> From<[f32; N]> for LineDash {
+    fn from(segments: [f32; N]) -> Self {
+        Self::new(segments)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct StrokeStyle {
+    /// tints the shader when there is one
     pub color: Color,
-    pub line_width: u32,
+    pub shader: Option<Box<PaintShader>>,
+    /// strokes thinner than a pixel are drawn a pixel wide and faded by how much of it they cover
+    pub line_width: f32,
     pub line_join: LineJoin,
+    /// longest miter as a ratio of its length to half the line width,
+    /// sharper corners are beveled or clipped depending on the join
+    pub miter_limit: f32,
     pub line_cap: LineCap,
+    /// solid when empty
+    pub line_dash: LineDash,
+    /// keeps the line width and the dashes in pixels instead of scaling them with the transform
+    pub non_scaling: bool,
     pub allow_overlap: bool,
 }
 
+impl Eq for StrokeStyle {}
+
+impl Hash for StrokeStyle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.color.hash(state);
+        self.shader.hash(state);
+        self.line_width.to_bits().hash(state);
+        self.line_join.hash(state);
+        self.miter_limit.to_bits().hash(state);
+        self.line_cap.hash(state);
+        self.line_dash.hash(state);
+        self.non_scaling.hash(state);
+        self.allow_overlap.hash(state);
+    }
+}
+
 impl Default for StrokeStyle {
     fn default() -> Self {
         Self {
             color: Color::WHITE,
-            line_width: 2,
+            shader: None,
+            line_width: 2.0,
             line_join: LineJoin::Miter,
+            miter_limit: 10.0,
             line_cap: LineCap::Butt,
+            line_dash: LineDash::default(),
+            non_scaling: false,
             allow_overlap: false,
         }
     }
@@ -274,21 +494,42 @@ impl StrokeStyle {
         self
     }
 
-    pub fn line_width(mut self, line_width: u32) -> Self {
+    /// Replaces the color and the shader
+    pub fn paint(mut self, paint: impl Into<Paint>) -> Self {
+        (self.color, self.shader) = paint.into().split();
+        self
+    }
+
+    pub fn line_width(mut self, line_width: f32) -> Self {
         self.line_width = line_width;
         self
     }
 
+    pub fn non_scaling(mut self, non_scaling: bool) -> Self {
+        self.non_scaling = non_scaling;
+        self
+    }
+
     pub fn line_join(mut self, line_join: LineJoin) -> Self {
         self.line_join = line_join;
         self
     }
 
+    pub fn miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
     pub fn line_cap(mut self, line_cap: LineCap) -> Self {
         self.line_cap = line_cap;
         self
     }
 
+    pub fn line_dash(mut self, line_dash: impl Into<LineDash>) -> Self {
+        self.line_dash = line_dash.into();
+        self
+    }
+
     pub fn default_join(mut self) -> Self {
         self.line_join = LineJoin::Miter;
         self
@@ -299,6 +540,11 @@ impl StrokeStyle {
         self
     }
 
+    pub fn miter_clip_join(mut self) -> Self {
+        self.line_join = LineJoin::MiterClip;
+        self
+    }
+
     pub fn bevel_join(mut self) -> Self {
         self.line_join = LineJoin::Bevel;
         self
@@ -498,7 +744,7 @@ mod tests {
 
         let mut brush = PathBrush::default();
 
-        let leg_paint = Brush::filled(Color::RED).line_width(10);
+        let leg_paint = Brush::filled(Color::RED).line_width(10.0);
 
         path.begin(vec2(0.0, 0.0));
         path.line_to(vec2(-20.0, 100.0));
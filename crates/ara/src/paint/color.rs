@@ -254,14 +254,14 @@ impl Rgba {
         if other.a >= 1.0 {
             other
         } else if other.a <= 0.0 {
-            return *self;
+            *self
         } else {
-            return Rgba {
+            Rgba {
                 r: self.r * (1.0 - other.a) + other.r * other.a,
                 g: self.g * (1.0 - other.a) + other.g * other.a,
                 b: self.b * (1.0 - other.a) + other.b * other.a,
                 a: self.a,
-            };
+            }
         }
     }
 }
@@ -1,4 +1,5 @@
 use core::f32;
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::ops::Range;
 
@@ -11,6 +12,7 @@ use super::{
 use crate::earcut::Earcut;
 use crate::math::{Rect, Vec2};
 use crate::paint::WHITE_UV;
+use crate::path::fill_regions;
 use crate::{get_path_bounds, Contour, PathEvent, PathEventsIter, PathGeometryBuilder};
 
 use std::ops::{Deref, DerefMut};
@@ -21,6 +23,8 @@ use crate::path::{PathBuilder, Point};
 struct ScratchPathBuilder {
     builder: PathBuilder,
     temp_path_data: Vec<Point>,
+    /// points of a contour before they are transformed, strokes are tessellated with them
+    local_path_data: Vec<Point>,
     earcut: Earcut<f32>,
 }
 
@@ -45,124 +49,182 @@ struct FillAndStrokeOptions<'a> {
     brush: AnyBrush<'a>,
     mesh: &'a mut Mesh,
     feathering: f32,
+    pixel_scale: f32,
     shape_type: ShapeType,
     textured: bool,
     build_mode: PathBuildMode,
 }
 
 impl ScratchPathBuilder {
-    fn _fill(
+    fn _fill_convex(
         mesh: &mut Mesh,
         path: &[Point],
-        earcut: &mut Earcut<f32>,
         brush: &Brush,
         feathering: f32,
         textured: bool,
-        shape_type: ShapeType,
     ) {
         if brush.fill_style.color.is_transparent() {
             return;
         }
 
-        let fill_style = &brush.fill_style;
         let stroke_color = brush.stroke_style.color;
+        fill_path_convex(
+            mesh,
+            path,
+            brush.fill_style.color,
+            textured,
+            feathering,
+            (!stroke_color.is_transparent()).then_some(stroke_color),
+            |_| {},
+        );
+    }
 
-        match shape_type {
-            ShapeType::Convex => {
-                fill_path_convex(
-                    mesh,
-                    path,
-                    fill_style.color,
-                    textured,
-                    feathering,
-                    (!stroke_color.is_transparent()).then_some(stroke_color),
-                    |_| {},
-                );
-            }
-            ShapeType::Concave => {
-                fill_path_concave(mesh, path, earcut, fill_style, feathering, |_| {});
-            }
+    /// Fills the contours together with the fill rule of the brush
+    fn _fill_concave<'a>(
+        mesh: &mut Mesh,
+        contours: impl IntoIterator<Item = &'a [Point]> + Clone,
+        earcut: &mut Earcut<f32>,
+        brush: &Brush,
+        feathering: f32,
+    ) {
+        let fill_style = &brush.fill_style;
+        if fill_style.color.is_transparent() {
+            return;
+        }
+
+        for region in fill_regions(contours, fill_style.fill_rule) {
+            fill_path_concave(
+                mesh,
+                &region.points,
+                &region.holes,
+                earcut,
+                fill_style,
+                feathering,
+                |_| {},
+            );
         }
     }
 
-    fn fill_and_stroke(
-        &mut self,
-        options: FillAndStrokeOptions,
-        map_points: Option<impl Fn(&mut [Point])>,
+    /// Tessellates the stroke of a contour. Unless the stroke is non scaling
+    /// it is tessellated before the transform, so its width and dashes scale with it
+    fn _stroke(
+        mesh: &mut Mesh,
+        local: &[Point],
+        transformed: &[Point],
+        brush: &Brush,
+        transform: Option<Mat3>,
+        pixel_scale: f32,
     ) {
+        let stroke_style = &brush.stroke_style;
+        match transform {
+            Some(transform) if !stroke_style.non_scaling => {
+                let start = mesh.vertices.len();
+                let scale = pixel_scale * transform.average_scale();
+                StrokeTessellator::add_to_mesh(mesh, local, stroke_style, scale);
+
+                for vertex in &mut mesh.vertices[start..] {
+                    let position = transform * Vec2::new(vertex.position[0], vertex.position[1]);
+                    vertex.position = [position.x, position.y];
+                }
+            }
+            _ => StrokeTessellator::add_to_mesh(mesh, transformed, stroke_style, pixel_scale),
+        }
+    }
+
+    fn fill_and_stroke(&mut self, options: FillAndStrokeOptions, transform: Option<Mat3>) {
         let FillAndStrokeOptions {
             brush,
             feathering,
+            pixel_scale,
             shape_type,
             build_mode,
             mesh,
             textured,
         } = options;
 
+        let transform = transform.filter(|transform| !transform.is_identity());
+
         let geometry: PathGeometryBuilder<_> =
             create_geometry_builder_for_path(self.builder.path_events(), &mut self.temp_path_data)
                 .with_auto_segments();
 
-        // Different handling based on build mode
-        match build_mode {
-            PathBuildMode::Single => {
-                let brush = match brush {
-                    AnyBrush::Brush(brush) => brush,
-                    AnyBrush::Path(path_brush) => &path_brush.get_or_default(&Contour::default()),
-                };
-
-                let feathering = if brush.antialias { feathering } else { 0.0 };
-
-                let range = expect_one_contour(geometry).1;
-
-                if let Some(map_points) = map_points {
-                    map_points(&mut self.temp_path_data[range.clone()]);
-                }
-
-                let path = &self.temp_path_data[range];
-
-                Self::_fill(
-                    mesh,
-                    path,
-                    &mut self.earcut,
-                    brush,
-                    feathering,
-                    textured,
-                    shape_type,
-                );
+        let contours: Vec<_> = match build_mode {
+            PathBuildMode::Single => vec![expect_one_contour(geometry)],
+            PathBuildMode::Full => geometry.collect(),
+        };
 
-                StrokeTessellator::add_to_mesh(mesh, path, &brush.stroke_style);
+        let brushes: Vec<Cow<Brush>> = contours
+            .iter()
+            .map(|(contour, _)| match brush {
+                AnyBrush::Brush(brush) => Cow::Borrowed(brush),
+                // a single contour is drawn with the default brush
+                AnyBrush::Path(path_brush) => Cow::Owned(match build_mode {
+                    PathBuildMode::Single => path_brush.get_or_default(&Contour::default()),
+                    PathBuildMode::Full => path_brush.get_or_default(contour),
+                }),
+            })
+            .collect();
+
+        let feathering = |brush: &Brush| if brush.antialias { feathering } else { 0.0 };
+
+        self.local_path_data.clear();
+        if let Some(transform) = transform {
+            self.local_path_data.extend_from_slice(&self.temp_path_data);
+            for point in self.temp_path_data.iter_mut() {
+                *point = transform * *point;
             }
-            PathBuildMode::Full => {
-                let geo_build = geometry.collect::<Vec<_>>();
-
-                for (contour, range) in geo_build {
-                    let brush = match brush {
-                        AnyBrush::Brush(brush) => brush,
-                        AnyBrush::Path(path_brush) => &path_brush.get_or_default(&contour),
-                    };
-
-                    let feathering = if brush.antialias { feathering } else { 0.0 };
-
-                    let points = &mut self.temp_path_data[range];
-
-                    if let Some(ref map_points) = map_points {
-                        map_points(points);
-                    }
+        }
 
-                    Self::_fill(
+        match shape_type {
+            ShapeType::Convex => {
+                for ((_, range), brush) in contours.iter().zip(&brushes) {
+                    Self::_fill_convex(
                         mesh,
-                        points,
-                        &mut self.earcut,
+                        &self.temp_path_data[range.clone()],
                         brush,
-                        feathering,
+                        feathering(brush),
                         textured,
-                        shape_type,
                     );
-                    StrokeTessellator::add_to_mesh(mesh, points, &brush.stroke_style);
+                }
+            }
+            ShapeType::Concave => {
+                // contours sharing a fill are filled together, so they can cut holes into each other
+                let same_fill = |a: &Brush, b: &Brush| {
+                    a.fill_style == b.fill_style && a.antialias == b.antialias
+                };
+
+                for (i, brush) in brushes.iter().enumerate() {
+                    if brushes[..i].iter().any(|other| same_fill(other, brush)) {
+                        continue;
+                    }
+
+                    let group = contours
+                        .iter()
+                        .zip(&brushes)
+                        .filter(|(_, other)| same_fill(other, brush))
+                        .map(|((_, range), _)| &self.temp_path_data[range.clone()]);
+
+                    Self::_fill_concave(mesh, group, &mut self.earcut, brush, feathering(brush));
                 }
             }
         }
+
+        for ((_, range), brush) in contours.iter().zip(&brushes) {
+            let local = if transform.is_some() {
+                &self.local_path_data[range.clone()]
+            } else {
+                &[]
+            };
+
+            Self::_stroke(
+                mesh,
+                local,
+                &self.temp_path_data[range.clone()],
+                brush,
+                transform,
+                pixel_scale,
+            );
+        }
     }
 
     #[inline(always)]
@@ -187,18 +249,35 @@ impl DerefMut for ScratchPathBuilder {
     }
 }
 
-#[derive(Default)]
 pub struct DrawList {
     pub(crate) feathering_px: f32,
+    pub(crate) pixel_scale: f32,
     pub(crate) mesh: Mesh,
     path: ScratchPathBuilder,
 }
 
+impl Default for DrawList {
+    fn default() -> Self {
+        Self {
+            feathering_px: 0.0,
+            pixel_scale: 1.0,
+            mesh: Default::default(),
+            path: Default::default(),
+        }
+    }
+}
+
 impl DrawList {
     pub fn feathering(&mut self, value: f32) {
         self.feathering_px = value;
     }
 
+    /// Pixels per unit of the space the vertices are added in,
+    /// non scaling strokes and strokes thinner than a pixel are measured with it
+    pub fn pixel_scale(&mut self, value: f32) {
+        self.pixel_scale = value;
+    }
+
     pub fn clear(&mut self) {
         self.mesh.clear();
         self.path.clear();
@@ -263,19 +342,12 @@ impl DrawList {
                 brush: AnyBrush::Brush(brush),
                 mesh: &mut self.mesh,
                 feathering: self.feathering_px,
+                pixel_scale: self.pixel_scale,
                 shape_type: ShapeType::Convex,
                 textured,
                 build_mode: PathBuildMode::Single,
             },
-            Some(|path: &mut [Point]| {
-                if let Some(transform) = transform {
-                    if !transform.is_identity() {
-                        for point in path.iter_mut() {
-                            *point = transform * *point;
-                        }
-                    }
-                }
-            }),
+            transform,
         );
     }
 
@@ -295,19 +367,12 @@ impl DrawList {
                 brush: AnyBrush::Brush(brush),
                 mesh: &mut self.mesh,
                 feathering: self.feathering_px,
+                pixel_scale: self.pixel_scale,
                 shape_type: ShapeType::Convex,
                 textured,
                 build_mode: PathBuildMode::Single,
             },
-            Some(|path: &mut [Point]| {
-                if let Some(transform) = transform {
-                    if !transform.is_identity() {
-                        for point in path.iter_mut() {
-                            *point = transform * *point;
-                        }
-                    }
-                }
-            }),
+            transform,
         );
     }
 
@@ -323,19 +388,12 @@ impl DrawList {
                 brush: AnyBrush::Path(brush),
                 mesh: &mut self.mesh,
                 feathering: self.feathering_px,
+                pixel_scale: self.pixel_scale,
                 shape_type: ShapeType::Concave,
                 textured: false,
                 build_mode: PathBuildMode::Full,
             },
-            Some(|path: &mut [Point]| {
-                if let Some(transform) = transform {
-                    if !transform.is_identity() {
-                        for point in path.iter_mut() {
-                            *point = transform * *point;
-                        }
-                    }
-                }
-            }),
+            transform,
         );
     }
 
@@ -434,9 +492,12 @@ fn is_path_closed(path: &[Vec2<f32>]) -> bool {
     }
 }
 
+/// Fills a ring and the holes cut into it, which start at the `holes` indices.
+/// The filled area has to be right of the edges, clockwise for the ring and counter clockwise for the holes
 pub fn fill_path_concave(
     mesh: &mut Mesh,
     path: &[Vec2<f32>],
+    holes: &[u32],
     earcut: &mut Earcut<f32>,
     fill_style: &FillStyle,
     feathering: f32,
@@ -445,7 +506,7 @@ pub fn fill_path_concave(
     let points_count = {
         let n = path.len() as u32;
 
-        if is_path_closed(path) {
+        if holes.is_empty() && is_path_closed(path) {
             n - 1
         } else {
             n
@@ -477,55 +538,65 @@ pub fn fill_path_concave(
         let mut temp_indices = <Vec<u32>>::new();
         earcut.earcut(
             path.iter().map(|p| [p.x, p.y]),
-            &[],
+            holes,
             &mut temp_indices,
             false,
         );
 
         for triangle in temp_indices.chunks_exact(3) {
-            let [i0, i1, i2] = [triangle[0], triangle[1], triangle[2]];
-
-            let v0 = idx_inner + ((points_count - 1 - i0) % points_count) * 2;
-            let v1 = idx_inner + ((points_count - 1 - i1) % points_count) * 2;
-            let v2 = idx_inner + ((points_count - 1 - i2) % points_count) * 2;
-
-            mesh.add_triangle(v0, v1, v2);
+            mesh.add_triangle(
+                idx_inner + triangle[0] * 2,
+                idx_inner + triangle[1] * 2,
+                idx_inner + triangle[2] * 2,
+            );
         }
 
+        let starts = std::iter::once(0).chain(holes.iter().copied());
+        let ends = holes.iter().copied().chain(std::iter::once(points_count));
+
         TEMP_BUFFER.with_borrow_mut(|normals| {
-            normals.clear();
-            normals.reserve(points_count as usize);
+            for (start, end) in starts.zip(ends) {
+                let ring = &path[start as usize..end as usize];
+                let count = ring.len() as u32;
 
-            // todo account for sharp angles
+                normals.clear();
+                normals.reserve(count as usize);
 
-            let mut i0 = points_count - 1;
-            for i1 in 0..points_count {
-                let p0 = path[i0 as usize];
-                let p1 = path[i1 as usize];
-                let edge = (p1 - p0).normalize().rot90();
-                normals.push(edge);
-                i0 = i1;
-            }
+                // todo account for sharp angles
 
-            // The feathering:
-            let mut i0 = points_count - 1;
-            for i1 in 0..points_count {
-                let n0 = normals[i0 as usize];
-                let n1 = normals[i1 as usize];
-                let dm = (n0 + n1).normalize() * feathering * 0.5;
-                let p = path[i0 as usize];
+                // normal of the edge from each point to the next
+                for i in 0..count {
+                    let p0 = ring[i as usize];
+                    let p1 = ring[((i + 1) % count) as usize];
+                    normals.push((p1 - p0).normalize().rot90());
+                }
 
-                let pos_inner = p - dm;
-                let pos_outer = p + dm;
+                // The feathering:
+                let mut i0 = count - 1;
+                for i1 in 0..count {
+                    let n0 = normals[i0 as usize];
+                    let n1 = normals[i1 as usize];
+                    let dm = (n0 + n1).normalize() * feathering * 0.5;
+                    let p = ring[i1 as usize];
+
+                    let pos_inner = p - dm;
+                    let pos_outer = p + dm;
+
+                    on_add(pos_inner);
+                    on_add(pos_outer);
+                    mesh.add_vertex(pos_inner, fill, WHITE_UV);
+                    mesh.add_vertex(pos_outer, out_color, WHITE_UV);
+                    i0 = i1;
+                }
 
-                on_add(pos_inner);
-                on_add(pos_outer);
-                mesh.add_vertex(pos_inner, fill, WHITE_UV);
-                mesh.add_vertex(pos_outer, out_color, WHITE_UV);
+                for i0 in 0..count {
+                    let i1 = (i0 + 1) % count;
+                    let [inner0, inner1] = [start + i0, start + i1].map(|i| idx_inner + i * 2);
+                    let [outer0, outer1] = [start + i0, start + i1].map(|i| idx_outer + i * 2);
 
-                mesh.add_triangle(idx_inner + i1 * 2, idx_inner + i0 * 2, idx_outer + 2 * i0);
-                mesh.add_triangle(idx_outer + i0 * 2, idx_outer + i1 * 2, idx_inner + 2 * i1);
-                i0 = i1;
+                    mesh.add_triangle(inner1, inner0, outer0);
+                    mesh.add_triangle(outer0, outer1, inner1);
+                }
             }
         });
     } else {
@@ -542,7 +613,7 @@ pub fn fill_path_concave(
         // Perform earcut triangulation
         earcut.earcut(
             path.iter().map(|p| [p.x, p.y]),
-            &[],
+            holes,
             &mut mesh.indices,
             false,
         );
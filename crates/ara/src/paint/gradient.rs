@@ -0,0 +1,378 @@
+use std::hash::{Hash, Hasher};
+
+use ara_math::{Mat3, Point};
+
+use super::{Color, Rgba};
+
+/// Maximum number of color stops a gradient can be drawn with, the stops after the
+/// first ones by offset are ignored and a warning is logged when they are added
+pub const MAX_GRADIENT_STOPS: usize = 16;
+
+/// Shape of a gradient, points are in the space of the gradient, see `Gradient::transform`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientKind {
+    /// offset 0 at `start` and 1 at `end`
+    Linear { start: Point, end: Point },
+    /// offset 0 at `focal` and 1 on the circle, the focal point is kept inside the circle
+    Radial {
+        center: Point,
+        radius: f32,
+        focal: Point,
+    },
+    /// sweeps clockwise around `center` starting at `angle` in radians
+    Conic { center: Point, angle: f32 },
+}
+
+/// How offsets outside of 0..1 are mapped onto the stops
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SpreadMode {
+    /// extends the first and last color
+    #[default]
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+/// A gradient paint, evaluated per pixel so it stays smooth at any scale.
+/// At most `MAX_GRADIENT_STOPS` color stops are drawn
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    /// sorted by offset
+    pub stops: Vec<GradientStop>,
+    pub spread: SpreadMode,
+    /// transform from the space of the gradient into the space it is drawn in
+    pub transform: Mat3,
+}
+
+impl Gradient {
+    pub fn new(kind: GradientKind) -> Self {
+        Self {
+            kind,
+            stops: Vec::new(),
+            spread: SpreadMode::Pad,
+            transform: Mat3::IDENTITY,
+        }
+    }
+
+    pub fn linear(start: impl Into<Point>, end: impl Into<Point>) -> Self {
+        Self::new(GradientKind::Linear {
+            start: start.into(),
+            end: end.into(),
+        })
+    }
+
+    pub fn radial(center: impl Into<Point>, radius: f32) -> Self {
+        let center = center.into();
+        Self::new(GradientKind::Radial {
+            center,
+            radius,
+            focal: center,
+        })
+    }
+
+    /// A radial gradient starting at `focal` instead of the center
+    pub fn two_point_radial(
+        center: impl Into<Point>,
+        radius: f32,
+        focal: impl Into<Point>,
+    ) -> Self {
+        Self::new(GradientKind::Radial {
+            center: center.into(),
+            radius,
+            focal: focal.into(),
+        })
+    }
+
+    pub fn conic(center: impl Into<Point>, angle: f32) -> Self {
+        Self::new(GradientKind::Conic {
+            center: center.into(),
+            angle,
+        })
+    }
+
+    /// Adds a color stop, the offset is clamped to 0..1 and a NaN offset is ignored.
+    /// Only the first `MAX_GRADIENT_STOPS` stops by offset are drawn
+    pub fn add_stop(mut self, offset: f32, color: impl Into<Color>) -> Self {
+        if offset.is_nan() {
+            log::warn!("Gradient stop with a NaN offset ignored");
+            return self;
+        }
+        if self.stops.len() == MAX_GRADIENT_STOPS {
+            log::warn!(
+                "Gradient has more than {} stops, the last ones are not drawn",
+                MAX_GRADIENT_STOPS
+            );
+        }
+
+        let offset = offset.clamp(0.0, 1.0);
+        let index = self.stops.partition_point(|stop| stop.offset <= offset);
+        self.stops.insert(
+            index,
+            GradientStop {
+                offset,
+                color: color.into(),
+            },
+        );
+        self
+    }
+
+    pub fn spread(mut self, spread: SpreadMode) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn transform(mut self, transform: Mat3) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Offset of `point` before spreading, `point` is in the space of the gradient
+    pub fn offset_at(&self, point: Point) -> f32 {
+        match self.kind {
+            GradientKind::Linear { start, end } => {
+                let dir = end - start;
+                let len = dir.x * dir.x + dir.y * dir.y;
+                if len <= f32::EPSILON {
+                    return 0.0;
+                }
+                let p = point - start;
+                (p.x * dir.x + p.y * dir.y) / len
+            }
+            GradientKind::Radial {
+                center,
+                radius,
+                focal,
+            } => {
+                let radius = radius.max(f32::EPSILON);
+                let focal = clamp_focal(center, radius, focal);
+                let d = center - focal;
+                let q = point - focal;
+                let a = d.x * d.x + d.y * d.y - radius * radius;
+                let b = q.x * d.x + q.y * d.y;
+                let c = q.x * q.x + q.y * q.y;
+                (b - (b * b - a * c).max(0.0).sqrt()) / a
+            }
+            GradientKind::Conic { center, angle } => {
+                let p = point - center;
+                (p.y.atan2(p.x) - angle).rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU
+            }
+        }
+    }
+
+    /// Color at `point` in the space of the gradient, mirrors the shader
+    pub fn color_at(&self, point: Point) -> Color {
+        let Some(first) = self.stops.first() else {
+            return Color::TRANSPARENT;
+        };
+
+        let t = self.spread.apply(self.offset_at(point));
+        let mut color = premultiply(first.color);
+        for pair in self.stops[..self.stops.len().min(MAX_GRADIENT_STOPS)].windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if t >= to.offset {
+                color = premultiply(to.color);
+                continue;
+            }
+            if t > from.offset {
+                let f = (t - from.offset) / (to.offset - from.offset).max(1e-6);
+                let (a, b) = (premultiply(from.color), premultiply(to.color));
+                color = std::array::from_fn(|i| a[i] + (b[i] - a[i]) * f);
+            }
+            break;
+        }
+
+        let [r, g, b, a] = color;
+        if a <= 0.0 {
+            return Color::TRANSPARENT;
+        }
+        let channel = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        Color {
+            r: channel(r / a),
+            g: channel(g / a),
+            b: channel(b / a),
+            a: channel(a),
+        }
+    }
+}
+
+impl SpreadMode {
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            SpreadMode::Pad => t.clamp(0.0, 1.0),
+            SpreadMode::Repeat => t - t.floor(),
+            SpreadMode::Reflect => {
+                let t = t * 0.5;
+                1.0 - ((t - t.floor()) * 2.0 - 1.0).abs()
+            }
+        }
+    }
+
+    pub(super) fn shader_index(&self) -> u32 {
+        match self {
+            SpreadMode::Pad => 0,
+            SpreadMode::Repeat => 1,
+            SpreadMode::Reflect => 2,
+        }
+    }
+}
+
+impl Gradient {
+    fn kind_index(&self) -> u8 {
+        match self.kind {
+            GradientKind::Linear { .. } => 0,
+            GradientKind::Radial { .. } => 1,
+            GradientKind::Conic { .. } => 2,
+        }
+    }
+
+    /// Bits of the floats of the gradient with -0.0 as 0.0,
+    /// comparing and hashing them keeps `Eq` and `Hash` consistent even for NaN
+    fn float_bits(&self) -> impl Iterator<Item = u32> + '_ {
+        let kind = match self.kind {
+            GradientKind::Linear { start, end } => vec![start.x, start.y, end.x, end.y],
+            GradientKind::Radial {
+                center,
+                radius,
+                focal,
+            } => vec![center.x, center.y, radius, focal.x, focal.y],
+            GradientKind::Conic { center, angle } => vec![center.x, center.y, angle],
+        };
+
+        kind.into_iter()
+            .chain(self.stops.iter().map(|stop| stop.offset))
+            .chain(self.transform.to_cols_array_padded().into_iter().flatten())
+            // adding zero turns -0.0 into 0.0
+            .map(|value| (value + 0.0).to_bits())
+    }
+}
+
+impl PartialEq for Gradient {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind_index() == other.kind_index()
+            && self.spread == other.spread
+            && self.stops.len() == other.stops.len()
+            && self
+                .stops
+                .iter()
+                .zip(&other.stops)
+                .all(|(a, b)| a.color == b.color)
+            && self.float_bits().eq(other.float_bits())
+    }
+}
+
+impl Eq for Gradient {}
+
+impl Hash for Gradient {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind_index().hash(state);
+        self.spread.hash(state);
+        self.stops.len().hash(state);
+        for stop in &self.stops {
+            stop.color.hash(state);
+        }
+        for bits in self.float_bits() {
+            bits.hash(state);
+        }
+    }
+}
+
+pub(super) fn clamp_focal(center: Point, radius: f32, focal: Point) -> Point {
+    // a focal point on or outside of the circle makes the gradient a cone
+    let d = focal - center;
+    let len = (d.x * d.x + d.y * d.y).sqrt();
+    let max = radius * 0.999;
+    if len > max {
+        center + d * (max / len)
+    } else {
+        focal
+    }
+}
+
+pub(super) fn premultiply(color: Color) -> [f32; 4] {
+    let Rgba { r, g, b, a } = color.into();
+    [r * a, g * a, b * a, a]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2;
+
+    #[test]
+    fn linear_gradient_interpolates_premultiplied_stops() {
+        let gradient = Gradient::linear((0.0, 0.0), (100.0, 0.0))
+            .add_stop(1.0, Color::BLUE)
+            .add_stop(0.0, Color::RED);
+
+        assert_eq!(gradient.stops[0].color, Color::RED);
+        assert_eq!(gradient.color_at(vec2(-10.0, 5.0)), Color::RED);
+        assert_eq!(gradient.color_at(vec2(150.0, 5.0)), Color::BLUE);
+        assert_eq!(
+            gradient.color_at(vec2(50.0, 40.0)),
+            Color {
+                r: 128,
+                g: 0,
+                b: 128,
+                a: 255
+            }
+        );
+
+        // a transparent stop does not darken the other one
+        let fade = Gradient::linear((0.0, 0.0), (100.0, 0.0))
+            .add_stop(0.0, Color::RED)
+            .add_stop(1.0, Color::TRANSPARENT);
+        let half = fade.color_at(vec2(50.0, 0.0));
+        assert_eq!((half.r, half.g, half.a), (255, 0, 128));
+    }
+
+    #[test]
+    fn gradient_offsets_and_spread_modes() {
+        let radial = Gradient::radial((0.0, 0.0), 10.0);
+        assert!((radial.offset_at(vec2(5.0, 0.0)) - 0.5).abs() < 1e-5);
+        assert!((radial.offset_at(vec2(0.0, -10.0)) - 1.0).abs() < 1e-5);
+
+        // the focal point is offset 0, the circle is offset 1 in every direction
+        let focal = Gradient::two_point_radial((0.0, 0.0), 10.0, (5.0, 0.0));
+        assert!(focal.offset_at(vec2(5.0, 0.0)).abs() < 1e-5);
+        assert!((focal.offset_at(vec2(10.0, 0.0)) - 1.0).abs() < 1e-5);
+        assert!((focal.offset_at(vec2(-10.0, 0.0)) - 1.0).abs() < 1e-5);
+
+        let conic = Gradient::conic((0.0, 0.0), 0.0);
+        assert!((conic.offset_at(vec2(0.0, 10.0)) - 0.25).abs() < 1e-5);
+        assert!((conic.offset_at(vec2(-10.0, 0.0)) - 0.5).abs() < 1e-5);
+
+        assert_eq!(SpreadMode::Pad.apply(1.25), 1.0);
+        assert_eq!(SpreadMode::Repeat.apply(1.25), 0.25);
+        assert_eq!(SpreadMode::Reflect.apply(1.25), 0.75);
+        assert_eq!(SpreadMode::Reflect.apply(-0.25), 0.25);
+    }
+
+    #[test]
+    fn gradient_eq_and_hash_agree() {
+        let hash = |gradient: &Gradient| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            gradient.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let a = Gradient::linear((0.0, 0.0), (10.0, 0.0)).add_stop(0.0, Color::RED);
+        let b = Gradient::linear((-0.0, 0.0), (10.0, 0.0)).add_stop(-0.0, Color::RED);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+
+        let nan = Gradient::linear((f32::NAN, 0.0), (10.0, 0.0));
+        assert_eq!(nan, nan.clone());
+        assert_eq!(hash(&nan), hash(&nan.clone()));
+
+        // stops with a NaN offset are not added
+        assert_eq!(a.clone().add_stop(f32::NAN, Color::BLUE).stops.len(), 1);
+        assert_ne!(a, a.clone().add_stop(1.0, Color::BLUE));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{paint::Primitive, Brush, TextureId};
+use crate::{paint::Primitive, Brush, PaintShader, TextureId};
 use std::{iter::Peekable, slice};
 
 use super::Color;
@@ -16,6 +16,19 @@ impl GraphicsInstruction {
         self.brush.nothing_to_draw()
     }
 
+    /// Shader the instruction is drawn with, paths use the one of their default brush
+    pub fn shader(&self) -> Option<&PaintShader> {
+        let brush = match &self.primitive {
+            Primitive::Path { brush, .. } => &brush.default,
+            _ => &self.brush,
+        };
+        brush
+            .fill_style
+            .shader
+            .as_deref()
+            .or(brush.stroke_style.shader.as_deref())
+    }
+
     pub fn textured(primitive: impl Into<Primitive>, texture_id: TextureId) -> Self {
         Self {
             primitive: primitive.into(),
@@ -45,7 +58,7 @@ impl GraphicsInstruction {
     }
 }
 
-// batches instructions with the same texture
+// batches instructions with the same texture and shader
 pub(crate) struct GraphicsInstructionBatcher<'a, TexMap>
 where
     TexMap: Fn(&'a TextureId) -> Option<TextureId> + 'a,
@@ -91,6 +104,7 @@ where
         let first_instr = &self.instructions[self.instruction_start];
         let render_texture = (self.get_renderer_texture)(&first_instr.texture_id)
             .unwrap_or(first_instr.texture_id.clone());
+        let shader = first_instr.shader();
 
         let mut end = self.instruction_start;
 
@@ -98,7 +112,7 @@ where
             let next_render_texture = (self.get_renderer_texture)(&next_instr.texture_id)
                 .unwrap_or(next_instr.texture_id.clone());
 
-            if next_render_texture != render_texture {
+            if next_render_texture != render_texture || next_instr.shader() != shader {
                 break;
             }
 
@@ -109,6 +123,7 @@ where
         let batch = InstructionBatch {
             instructions_iter: self.instructions[self.instruction_start..end].iter(),
             renderer_texture: render_texture,
+            shader,
         };
 
         self.instruction_start = end;
@@ -124,6 +139,7 @@ where
 pub struct InstructionBatch<'a> {
     instructions_iter: std::slice::Iter<'a, GraphicsInstruction>,
     pub renderer_texture: TextureId,
+    pub shader: Option<&'a PaintShader>,
 }
 
 impl<'a> Iterator for InstructionBatch<'a> {
@@ -1,3 +1,13 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{atomic::AtomicUsize, Arc, Weak},
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::{gpu, math::Size};
+
 use super::TextureKind;
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
@@ -37,3 +47,140 @@ impl AtlasImage {
         self
     }
 }
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct ImageId(usize);
+
+impl ImageId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
+/// RGBA pixels with straight alpha, renderers upload them the first time they are drawn.
+/// Clones share the pixels and compare equal
+#[derive(Clone)]
+pub struct Image {
+    id: ImageId,
+    size: Size<u32>,
+    pixels: Arc<[u8]>,
+}
+
+impl Image {
+    /// Returns `None` when `pixels` does not hold four bytes for every pixel of `size`
+    pub fn from_rgba(size: impl Into<Size<u32>>, pixels: impl Into<Vec<u8>>) -> Option<Self> {
+        let size = size.into();
+        let pixels = pixels.into();
+        if size.width == 0
+            || size.height == 0
+            || pixels.len() != (size.width * size.height * 4) as usize
+        {
+            return None;
+        }
+
+        Some(Self {
+            id: ImageId::new(),
+            size,
+            pixels: pixels.into(),
+        })
+    }
+
+    pub fn id(&self) -> ImageId {
+        self.id
+    }
+
+    pub fn size(&self) -> Size<u32> {
+        self.size
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Whether a clone of the image is still alive, used by renderers to drop uploads
+    pub(crate) fn downgrade(&self) -> Weak<[u8]> {
+        Arc::downgrade(&self.pixels)
+    }
+
+    /// Uploads the image with premultiplied alpha so filtering does not bleed hidden colors
+    pub(crate) fn create_texture(&self, gpu: &gpu::Context) -> wgpu::TextureView {
+        let pixels: Vec<u8> = self
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|px| {
+                let a = px[3] as u32;
+                let mul = |c: u8| ((c as u32 * a + 127) / 255) as u8;
+                [mul(px[0]), mul(px[1]), mul(px[2]), px[3]]
+            })
+            .collect();
+
+        let texture = gpu.device.create_texture_with_data(
+            &gpu.queue,
+            &wgpu::TextureDescriptor {
+                label: Some("ara::Image"),
+                size: wgpu::Extent3d {
+                    width: self.size.width,
+                    height: self.size.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &pixels,
+        );
+
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+}
+
+impl PartialEq for Image {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Image {}
+
+impl Hash for Image {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("id", &self.id)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_equal_to_their_clones_only() {
+        assert!(Image::from_rgba((2, 2), vec![0; 15]).is_none());
+        assert!(Image::from_rgba((0, 2), Vec::new()).is_none());
+
+        let image = Image::from_rgba((2, 2), vec![255; 16]).unwrap();
+        let other = Image::from_rgba((2, 2), vec![255; 16]).unwrap();
+        assert_eq!(image, image.clone());
+        assert_ne!(image, other);
+
+        let weak = image.downgrade();
+        let clone = image.clone();
+        drop(image);
+        assert_eq!(weak.strong_count(), 1);
+        drop(clone);
+        assert_eq!(weak.strong_count(), 0);
+    }
+}
@@ -0,0 +1,312 @@
+use ahash::HashMap;
+use ara_math::Mat3;
+use std::sync::Weak;
+use wgpu::util::DeviceExt;
+
+use crate::gpu;
+
+use super::{
+    gradient::{clamp_focal, premultiply},
+    Gradient, GradientKind, Image, ImageId, Pattern, TextureAddressMode, TextureFilterMode,
+    MAX_GRADIENT_STOPS,
+};
+
+/// A paint evaluated per pixel, fill and stroke styles tint it with their color
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PaintShader {
+    Gradient(Gradient),
+    Pattern(Pattern),
+}
+
+impl PaintShader {
+    /// Transform from the space of the shader into the space it is drawn in
+    pub fn transform(&self) -> &Mat3 {
+        match self {
+            PaintShader::Gradient(gradient) => &gradient.transform,
+            PaintShader::Pattern(pattern) => &pattern.transform,
+        }
+    }
+}
+
+/// A paint as read by the shaders, see `render/shaders/paint.wgsl`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct PaintUniform {
+    transform: [[f32; 4]; 3],
+    kind: u32,
+    spread: u32,
+    stop_count: u32,
+    _pad: u32,
+    points: [f32; 4],
+    params: [f32; 4],
+    offsets: [f32; MAX_GRADIENT_STOPS],
+    colors: [[f32; 4]; MAX_GRADIENT_STOPS],
+}
+
+impl PaintUniform {
+    /// The uniform of a solid color, the shaders use the vertex color only
+    pub(crate) fn solid() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+
+    /// `transform` is the transform the geometry was tessellated with,
+    /// the shaders receive positions after it
+    pub(crate) fn new(shader: &PaintShader, transform: &Mat3) -> Self {
+        let mut uniform = Self::solid();
+        uniform.transform =
+            (transform.inverse() * shader.transform().inverse()).to_cols_array_padded();
+
+        match shader {
+            PaintShader::Gradient(gradient) => uniform.set_gradient(gradient),
+            PaintShader::Pattern(pattern) => {
+                let size = pattern.image.size();
+                uniform.kind = 4;
+                uniform.params = [size.width as f32, size.height as f32, 0.0, 0.0];
+            }
+        }
+
+        uniform
+    }
+
+    fn set_gradient(&mut self, gradient: &Gradient) {
+        self.spread = gradient.spread.shader_index();
+
+        match gradient.kind {
+            GradientKind::Linear { start, end } => {
+                self.kind = 1;
+                self.points = [start.x, start.y, end.x, end.y];
+            }
+            GradientKind::Radial {
+                center,
+                radius,
+                focal,
+            } => {
+                let radius = radius.max(f32::EPSILON);
+                let focal = clamp_focal(center, radius, focal);
+                self.kind = 2;
+                self.points = [center.x, center.y, focal.x, focal.y];
+                self.params[0] = radius;
+            }
+            GradientKind::Conic { center, angle } => {
+                self.kind = 3;
+                self.points = [center.x, center.y, 0.0, 0.0];
+                self.params[0] = angle;
+            }
+        }
+
+        let stops = &gradient.stops[..gradient.stops.len().min(MAX_GRADIENT_STOPS)];
+        self.stop_count = stops.len() as u32;
+        for (i, stop) in stops.iter().enumerate() {
+            self.offsets[i] = stop.offset;
+            self.colors[i] = premultiply(stop.color);
+        }
+
+        // without stops nothing is drawn
+        if stops.is_empty() {
+            self.stop_count = 1;
+        }
+    }
+}
+
+/// The bind group layout of paints and the resources bound when a paint has no texture
+#[derive(Debug)]
+pub(crate) struct PaintBindings {
+    layout: wgpu::BindGroupLayout,
+    white: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    /// bound for draws with a solid color
+    solid: wgpu::BindGroup,
+}
+
+impl PaintBindings {
+    pub(crate) fn new(gpu: &gpu::Context) -> Self {
+        let device = &gpu.device;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ara::Paint::BindGroupLayout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let white = device
+            .create_texture_with_data(
+                &gpu.queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("ara::Paint::White"),
+                    size: wgpu::Extent3d {
+                        width: 1,
+                        height: 1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+                wgpu::util::TextureDataOrder::LayerMajor,
+                &[255; 4],
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        let solid =
+            Self::create_bind_group(device, &layout, &PaintUniform::solid(), &white, &sampler);
+
+        Self {
+            layout,
+            white,
+            sampler,
+            solid,
+        }
+    }
+
+    pub(crate) fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub(crate) fn solid(&self) -> &wgpu::BindGroup {
+        &self.solid
+    }
+
+    /// A white texel
+    pub(crate) fn white(&self) -> &wgpu::TextureView {
+        &self.white
+    }
+
+    /// `texture` is the image and sampler of a pattern
+    pub(crate) fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        uniform: &PaintUniform,
+        texture: Option<(&wgpu::TextureView, &wgpu::Sampler)>,
+    ) -> wgpu::BindGroup {
+        let (view, sampler) = texture.unwrap_or((&self.white, &self.sampler));
+        Self::create_bind_group(device, &self.layout, uniform, view, sampler)
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform: &PaintUniform,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("ara::Paint::Uniform"),
+            contents: bytemuck::bytes_of(uniform),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ara::Paint::BindGroup"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+}
+
+/// Uploaded images and the samplers of patterns,
+/// an upload is dropped once every clone of its image is
+#[derive(Debug, Default)]
+pub(crate) struct PatternTextures {
+    images: HashMap<ImageId, (Weak<[u8]>, wgpu::TextureView)>,
+    samplers: HashMap<(TextureAddressMode, TextureFilterMode), wgpu::Sampler>,
+}
+
+impl PatternTextures {
+    pub(crate) fn get(
+        &mut self,
+        gpu: &gpu::Context,
+        pattern: &Pattern,
+    ) -> (wgpu::TextureView, wgpu::Sampler) {
+        let view = self.image(gpu, &pattern.image);
+        let sampler = self
+            .samplers
+            .entry((pattern.address_mode, pattern.filter))
+            .or_insert_with(|| {
+                gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+                    label: Some("ara::Pattern::Sampler"),
+                    address_mode_u: pattern.address_mode,
+                    address_mode_v: pattern.address_mode,
+                    mag_filter: pattern.filter,
+                    min_filter: pattern.filter,
+                    ..Default::default()
+                })
+            });
+
+        (view, sampler.clone())
+    }
+
+    /// The upload of the image, made the first time it is asked for
+    pub(crate) fn image(&mut self, gpu: &gpu::Context, image: &Image) -> wgpu::TextureView {
+        let (_, view) = self
+            .images
+            .entry(image.id())
+            .or_insert_with(|| (image.downgrade(), image.create_texture(gpu)));
+        view.clone()
+    }
+
+    /// Drops the uploads of images which no longer exist
+    pub(crate) fn trim(&mut self) {
+        self.images
+            .retain(|_, (pixels, _)| pixels.strong_count() > 0);
+    }
+
+    /// Bind group drawing `shader` on geometry tessellated with `transform`
+    pub(crate) fn bind_group(
+        &mut self,
+        gpu: &gpu::Context,
+        bindings: &PaintBindings,
+        shader: &PaintShader,
+        transform: &Mat3,
+    ) -> wgpu::BindGroup {
+        let uniform = PaintUniform::new(shader, transform);
+        match shader {
+            PaintShader::Pattern(pattern) => {
+                let (view, sampler) = self.get(gpu, pattern);
+                bindings.bind_group(&gpu.device, &uniform, Some((&view, &sampler)))
+            }
+            PaintShader::Gradient(_) => bindings.bind_group(&gpu.device, &uniform, None),
+        }
+    }
+}
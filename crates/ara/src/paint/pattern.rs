@@ -0,0 +1,64 @@
+use std::hash::{Hash, Hasher};
+
+use ara_math::Mat3;
+
+use super::{Image, TextureAddressMode, TextureFilterMode};
+
+/// Fills with an image, one pixel of the image covers one unit of the space of the pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub image: Image,
+    /// transform from the space of the pattern into the space it is drawn in
+    pub transform: Mat3,
+    /// how the image is extended past its edges
+    pub address_mode: TextureAddressMode,
+    pub filter: TextureFilterMode,
+}
+
+impl Pattern {
+    /// A repeating pattern with linear filtering
+    pub fn new(image: Image) -> Self {
+        Self {
+            image,
+            transform: Mat3::IDENTITY,
+            address_mode: TextureAddressMode::Repeat,
+            filter: TextureFilterMode::Linear,
+        }
+    }
+
+    pub fn transform(mut self, transform: Mat3) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn address_mode(mut self, address_mode: TextureAddressMode) -> Self {
+        self.address_mode = address_mode;
+        self
+    }
+
+    pub fn filter(mut self, filter: TextureFilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl From<Image> for Pattern {
+    fn from(image: Image) -> Self {
+        Self::new(image)
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Hash for Pattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.image.hash(state);
+        for column in self.transform.to_cols_array_padded() {
+            for value in column {
+                value.to_bits().hash(state);
+            }
+        }
+        self.address_mode.hash(state);
+        self.filter.hash(state);
+    }
+}
@@ -0,0 +1,414 @@
+use ahash::HashMap;
+
+use crate::{Mat3, Mesh, Rect, Rgba, Size, TextureId, TextureKind, Vec2};
+
+/// A texel returned by a `TextureLookup`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Texel {
+    /// multiplied with the vertex color, like `fs_poly`
+    Color(Rgba),
+    /// coverage of a mask texture, multiplied with the vertex alpha like `fs_mono`
+    Mask(f32),
+}
+
+/// Provides texels to the rasterizer, `uv` is normalized like in the shaders
+pub trait TextureLookup {
+    fn sample(&self, texture: &TextureId, uv: Vec2<f32>) -> Option<Texel>;
+}
+
+impl<F> TextureLookup for F
+where
+    F: Fn(&TextureId, Vec2<f32>) -> Option<Texel>,
+{
+    fn sample(&self, texture: &TextureId, uv: Vec2<f32>) -> Option<Texel> {
+        self(texture, uv)
+    }
+}
+
+/// Texture data for the rasterizer, sampled with nearest filtering and clamped to the edges
+#[derive(Debug, Clone)]
+pub struct RasterTexture {
+    pub size: Size<u32>,
+    pub kind: TextureKind,
+    /// one byte per texel for masks, four (RGBA) for colors
+    pub data: Vec<u8>,
+}
+
+impl RasterTexture {
+    pub fn new(size: Size<u32>, kind: TextureKind, data: Vec<u8>) -> Self {
+        debug_assert_eq!(
+            data.len(),
+            (size.width * size.height * kind.bytes_per_pixel()) as usize
+        );
+        Self { size, kind, data }
+    }
+
+    pub fn sample(&self, uv: Vec2<f32>) -> Texel {
+        let x = ((uv.x * self.size.width as f32) as i64).clamp(0, self.size.width as i64 - 1);
+        let y = ((uv.y * self.size.height as f32) as i64).clamp(0, self.size.height as i64 - 1);
+        let index = (y as usize * self.size.width as usize) + x as usize;
+
+        match self.kind {
+            TextureKind::Mask => Texel::Mask(unorm(self.data[index])),
+            TextureKind::Color => {
+                let texel = &self.data[index * 4..index * 4 + 4];
+                Texel::Color(Rgba {
+                    r: unorm(texel[0]),
+                    g: unorm(texel[1]),
+                    b: unorm(texel[2]),
+                    a: unorm(texel[3]),
+                })
+            }
+        }
+    }
+}
+
+impl TextureLookup for HashMap<TextureId, RasterTexture> {
+    fn sample(&self, texture: &TextureId, uv: Vec2<f32>) -> Option<Texel> {
+        self.get(texture).map(|texture| texture.sample(uv))
+    }
+}
+
+/// Renders meshes into an RGBA8 buffer on the cpu.
+///
+/// Follows the gpu pipelines: pixels are sampled at their centers, shared edges are drawn once
+/// (top-left rule), clip rects behave like scissor rects and colors are blended with
+/// src-alpha over, as set up for `ara.wgsl`. There is no multisampling
+#[derive(Debug, Clone)]
+pub struct Rasterizer {
+    size: Size<u32>,
+    data: Vec<u8>,
+    transform: Mat3,
+    alpha: f32,
+}
+
+impl Rasterizer {
+    pub fn new(size: Size<u32>) -> Self {
+        Self {
+            size,
+            data: vec![0; (size.width * size.height * 4) as usize],
+            transform: Mat3::identity(),
+            alpha: 1.0,
+        }
+    }
+
+    pub fn size(&self) -> Size<u32> {
+        self.size
+    }
+
+    /// Tightly packed RGBA8 rows
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.size.width || y >= self.size.height {
+            return None;
+        }
+        let index = ((y * self.size.width + x) * 4) as usize;
+        self.data[index..index + 4].try_into().ok()
+    }
+
+    pub fn clear(&mut self, color: impl Into<Rgba>) {
+        let color = color.into();
+        let texel = [
+            to_unorm(color.r),
+            to_unorm(color.g),
+            to_unorm(color.b),
+            to_unorm(color.a),
+        ];
+        for pixel in self.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&texel);
+        }
+    }
+
+    /// Transform applied to vertex positions, the node transform in `ara.wgsl`
+    pub fn set_transform(&mut self, transform: Mat3) {
+        self.transform = transform;
+    }
+
+    /// Multiplied with the vertex alpha, the node alpha in `ara.wgsl`
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+
+    /// Draws the mesh inside of the clip rect.
+    /// Textures other than the white texture are sampled from `textures`, missing texels are treated as white
+    pub fn draw_mesh(
+        &mut self,
+        mesh: &Mesh,
+        clip_rect: &Rect<f32>,
+        textures: Option<&dyn TextureLookup>,
+    ) {
+        debug_assert!(mesh.is_valid());
+
+        let Some(clip) = self.clip_bounds(clip_rect) else {
+            return;
+        };
+
+        let textures = textures.filter(|_| !mesh.texture.is_white());
+
+        let positions: Vec<Vec2<f32>> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| self.transform * Vec2::new(vertex.position[0], vertex.position[1]))
+            .collect();
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [
+                triangle[0] as usize,
+                triangle[1] as usize,
+                triangle[2] as usize,
+            ];
+            self.fill_triangle(mesh, [a, b, c], &positions, clip, textures);
+        }
+    }
+
+    /// The clip rect in pixels, rounded the same way as the scissor rect
+    fn clip_bounds(&self, clip_rect: &Rect<f32>) -> Option<[u32; 4]> {
+        let min = clip_rect.min().round();
+        let max = clip_rect.max().round();
+
+        let min_x = min.x.clamp(0.0, self.size.width as f32) as u32;
+        let min_y = min.y.clamp(0.0, self.size.height as f32) as u32;
+        let max_x = max.x.clamp(min_x as f32, self.size.width as f32) as u32;
+        let max_y = max.y.clamp(min_y as f32, self.size.height as f32) as u32;
+
+        (min_x < max_x && min_y < max_y).then_some([min_x, min_y, max_x, max_y])
+    }
+
+    fn fill_triangle(
+        &mut self,
+        mesh: &Mesh,
+        [a, mut b, mut c]: [usize; 3],
+        positions: &[Vec2<f32>],
+        clip: [u32; 4],
+        textures: Option<&dyn TextureLookup>,
+    ) {
+        let mut area = edge(positions[a], positions[b], positions[c]);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+        // wind every triangle the same way so the inside is where all edges are positive
+        if area < 0.0 {
+            std::mem::swap(&mut b, &mut c);
+            area = -area;
+        }
+
+        let (p0, p1, p2) = (positions[a], positions[b], positions[c]);
+
+        let min = p0.min(&p1).min(&p2);
+        let max = p0.max(&p1).max(&p2);
+
+        // pixels whose centers may be covered
+        let x0 = ((min.x - 0.5).ceil().max(clip[0] as f32)) as u32;
+        let y0 = ((min.y - 0.5).ceil().max(clip[1] as f32)) as u32;
+        let x1 = ((max.x - 0.5).floor() + 1.0).min(clip[2] as f32) as u32;
+        let y1 = ((max.y - 0.5).floor() + 1.0).min(clip[3] as f32) as u32;
+
+        let owns = [owns_edge(p1, p2), owns_edge(p2, p0), owns_edge(p0, p1)];
+        let vertices = [&mesh.vertices[a], &mesh.vertices[b], &mesh.vertices[c]];
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [edge(p1, p2, p), edge(p2, p0, p), edge(p0, p1, p)];
+
+                let inside = weights
+                    .iter()
+                    .zip(owns)
+                    .all(|(&w, owns)| w > 0.0 || (w == 0.0 && owns));
+
+                if !inside {
+                    continue;
+                }
+
+                let [w0, w1, w2] = weights.map(|w| w / area);
+                let lerp = |f: &dyn Fn(usize) -> f32| w0 * f(0) + w1 * f(1) + w2 * f(2);
+
+                let mut color = Rgba {
+                    r: lerp(&|i| vertices[i].color.r),
+                    g: lerp(&|i| vertices[i].color.g),
+                    b: lerp(&|i| vertices[i].color.b),
+                    a: lerp(&|i| vertices[i].color.a) * self.alpha,
+                };
+
+                if let Some(textures) = textures {
+                    let uv = Vec2::new(lerp(&|i| vertices[i].uv[0]), lerp(&|i| vertices[i].uv[1]));
+
+                    match textures.sample(&mesh.texture, uv) {
+                        Some(Texel::Color(texel)) => {
+                            color.r *= texel.r;
+                            color.g *= texel.g;
+                            color.b *= texel.b;
+                            color.a *= texel.a;
+                        }
+                        Some(Texel::Mask(coverage)) => color.a *= coverage,
+                        None => {}
+                    }
+                }
+
+                self.blend(x, y, color);
+            }
+        }
+    }
+
+    fn blend(&mut self, x: u32, y: u32, src: Rgba) {
+        let index = ((y * self.size.width + x) * 4) as usize;
+        let dst = &mut self.data[index..index + 4];
+
+        let src_alpha = src.a.clamp(0.0, 1.0);
+        let one_minus = 1.0 - src_alpha;
+
+        dst[0] = to_unorm(src.r * src_alpha + unorm(dst[0]) * one_minus);
+        dst[1] = to_unorm(src.g * src_alpha + unorm(dst[1]) * one_minus);
+        dst[2] = to_unorm(src.b * src_alpha + unorm(dst[2]) * one_minus);
+        dst[3] = to_unorm(src_alpha + unorm(dst[3]) * one_minus);
+    }
+}
+
+/// Twice the signed area of the triangle (a, b, p), positive when p is on the inner side of a -> b
+#[inline]
+fn edge(a: Vec2<f32>, b: Vec2<f32>, p: Vec2<f32>) -> f32 {
+    (p.x - a.x) * (b.y - a.y) - (p.y - a.y) * (b.x - a.x)
+}
+
+/// Top-left rule, pixel centers exactly on an edge belong to top and left edges only
+#[inline]
+fn owns_edge(a: Vec2<f32>, b: Vec2<f32>) -> bool {
+    let d = b - a;
+    d.y > 0.0 || (d.y == 0.0 && d.x < 0.0)
+}
+
+#[inline]
+fn unorm(v: u8) -> f32 {
+    v as f32 / 255.0
+}
+
+#[inline]
+fn to_unorm(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::HashMap;
+
+    use super::{RasterTexture, Rasterizer, Texel, TextureLookup};
+    use crate::{Color, Mat3, Mesh, Rect, Size, TextureId, TextureKind, Vec2};
+
+    fn quad(rect: Rect<f32>, color: Color) -> Mesh {
+        let mut mesh = Mesh::default();
+        mesh.add_vertex(rect.top_left(), color, (0.0, 0.0));
+        mesh.add_vertex(rect.top_right(), color, (1.0, 0.0));
+        mesh.add_vertex(rect.bottom_left(), color, (0.0, 1.0));
+        mesh.add_vertex(rect.bottom_right(), color, (1.0, 1.0));
+        mesh.add_triangle(0, 1, 2);
+        mesh.add_triangle(2, 1, 3);
+        mesh
+    }
+
+    fn everything() -> Rect<f32> {
+        Rect::xywh(0.0, 0.0, f32::MAX, f32::MAX)
+    }
+
+    #[test]
+    fn fills_shared_edges_once() {
+        let mut raster = Rasterizer::new(Size::new(8, 8));
+        raster.clear(Color::BLACK);
+
+        let half_red = Color::from_rgba(0xff000080);
+        raster.draw_mesh(
+            &quad(Rect::xywh(0.0, 0.0, 8.0, 8.0), half_red),
+            &everything(),
+            None,
+        );
+
+        // src-alpha over: 1.0 * 0.5 + 0.0 * 0.5, a diagonal drawn twice would be brighter
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(raster.get_pixel(x, y), Some([128, 0, 0, 255]), "{x} {y}");
+            }
+        }
+
+        // adjacent quads split at a pixel center do not overlap or leave gaps
+        let mut raster = Rasterizer::new(Size::new(8, 1));
+        raster.draw_mesh(
+            &quad(Rect::xywh(0.0, 0.0, 3.5, 1.0), half_red),
+            &everything(),
+            None,
+        );
+        raster.draw_mesh(
+            &quad(Rect::xywh(3.5, 0.0, 4.5, 1.0), half_red),
+            &everything(),
+            None,
+        );
+        assert!(raster
+            .data()
+            .chunks(4)
+            .all(|pixel| pixel == [128, 0, 0, 128]));
+    }
+
+    #[test]
+    fn clips_and_transforms() {
+        let mut raster = Rasterizer::new(Size::new(8, 8));
+        raster.set_transform(Mat3::from_translation(2.0, 2.0));
+
+        let clip = Rect::xywh(0.0, 0.0, 4.4, 8.0);
+        raster.draw_mesh(
+            &quad(Rect::xywh(0.0, 0.0, 4.0, 4.0), Color::WHITE),
+            &clip,
+            None,
+        );
+
+        assert_eq!(raster.get_pixel(1, 2), Some([0, 0, 0, 0]));
+        assert_eq!(raster.get_pixel(2, 2), Some([255, 255, 255, 255]));
+        assert_eq!(raster.get_pixel(3, 5), Some([255, 255, 255, 255]));
+        assert_eq!(raster.get_pixel(4, 2), Some([0, 0, 0, 0]));
+        assert_eq!(raster.get_pixel(3, 6), Some([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn samples_textures() {
+        let color = TextureId::User(0);
+        let mask = TextureId::User(1);
+
+        let mut textures = HashMap::default();
+        textures.insert(
+            color.clone(),
+            RasterTexture::new(
+                Size::new(2, 1),
+                TextureKind::Color,
+                vec![255, 0, 0, 255, 0, 0, 255, 255],
+            ),
+        );
+        textures.insert(
+            mask.clone(),
+            RasterTexture::new(Size::new(1, 1), TextureKind::Mask, vec![51]),
+        );
+
+        let mut raster = Rasterizer::new(Size::new(4, 1));
+        let mut mesh = quad(Rect::xywh(0.0, 0.0, 4.0, 1.0), Color::WHITE);
+        mesh.texture = color;
+        raster.draw_mesh(&mesh, &everything(), Some(&textures));
+        assert_eq!(raster.get_pixel(1, 0), Some([255, 0, 0, 255]));
+        assert_eq!(raster.get_pixel(2, 0), Some([0, 0, 255, 255]));
+
+        let mut raster = Rasterizer::new(Size::new(1, 1));
+        mesh.texture = mask;
+        raster.draw_mesh(&mesh, &everything(), Some(&textures));
+        assert_eq!(raster.get_pixel(0, 0), Some([51, 51, 51, 51]));
+
+        // closures work as lookups and the white texture is never sampled
+        let lookup = |_: &TextureId, _: Vec2<f32>| Some(Texel::Mask(0.0));
+        let lookup: &dyn TextureLookup = &lookup;
+        let mut raster = Rasterizer::new(Size::new(1, 1));
+        mesh.texture = TextureId::WHITE_TEXTURE;
+        raster.draw_mesh(&mesh, &everything(), Some(lookup));
+        assert_eq!(raster.get_pixel(0, 0), Some([255, 255, 255, 255]));
+    }
+}
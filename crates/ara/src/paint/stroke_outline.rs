@@ -0,0 +1,305 @@
+use crate::{LineJoin, Path, PathBuilder, PathEvent, PathEventsIter, PathGeometryBuilder, Point};
+
+use super::{dash_polyline, LineCap, StrokeStyle};
+
+/// Angle between the points of round joins and caps, like the tessellated ones
+const ROUND_MIN_ANGLE: f32 = 0.174533; // ~10 deg
+
+/// Outline of the stroke of a path with its joins, caps and dashes. Filled with the
+/// non zero fill rule the outline covers what the stroke draws, the line width is in
+/// units of the path so non scaling strokes and hairlines are outlined as they are
+pub fn stroke_to_path(
+    path: impl IntoIterator<Item = PathEvent>,
+    stroke_style: &StrokeStyle,
+) -> Path {
+    let mut builder = PathBuilder::default();
+    builder.extend(path);
+
+    let mut points = Vec::new();
+    let contours: Vec<_> =
+        <PathGeometryBuilder<PathEventsIter>>::new(builder.path_events(), &mut points)
+            .with_auto_segments()
+            .map(|(_, range)| range)
+            .collect();
+
+    let mut outline = PathBuilder::default();
+    for range in contours {
+        stroke_outline(&points[range], stroke_style, |polygon| {
+            outline.begin(polygon[0]);
+            for &point in &polygon[1..] {
+                outline.line_to(point);
+            }
+            outline.end(true);
+        });
+    }
+
+    outline.build()
+}
+
+/// Calls `f` with the closed polygons outlining the stroke of a polyline,
+/// a polyline ending where it starts is closed like `StrokeTessellator` does
+pub(crate) fn stroke_outline(
+    points: &[Point],
+    stroke_style: &StrokeStyle,
+    mut f: impl FnMut(&[Point]),
+) {
+    let half_width = stroke_style.line_width / 2.0;
+    if half_width.is_nan() || half_width <= 0.0 {
+        return;
+    }
+
+    let mut polygon = Vec::new();
+    dash_polyline(points, &stroke_style.line_dash, |dash| {
+        let mut dash = dash.to_vec();
+        dash.dedup();
+        if dash.len() < 2 {
+            return;
+        }
+
+        if dash.len() > 3 && dash.first() == dash.last() {
+            // the outer and the inner side, going opposite ways the inner one is a hole
+            dash.pop();
+            for _ in 0..2 {
+                polygon.clear();
+                add_side(&mut polygon, &dash, true, half_width, stroke_style);
+                f(&polygon);
+                dash.reverse();
+            }
+        } else {
+            // down one side and back up the other with the caps in between
+            polygon.clear();
+            for _ in 0..2 {
+                add_side(&mut polygon, &dash, false, half_width, stroke_style);
+                let [.., before, last] = dash[..] else {
+                    unreachable!()
+                };
+                add_cap(
+                    &mut polygon,
+                    last,
+                    (last - before).normalize(),
+                    half_width,
+                    stroke_style.line_cap,
+                );
+                dash.reverse();
+            }
+            f(&polygon);
+        }
+    });
+}
+
+/// Offsets the polyline to its left as seen on the screen, with the joins in between
+fn add_side(
+    polygon: &mut Vec<Point>,
+    points: &[Point],
+    closed: bool,
+    half_width: f32,
+    stroke_style: &StrokeStyle,
+) {
+    let count = points.len();
+    let direction = |i: usize| (points[(i + 1) % count] - points[i]).normalize();
+
+    if closed {
+        for (i, &point) in points.iter().enumerate() {
+            let before = direction((i + count - 1) % count);
+            add_join(
+                polygon,
+                point,
+                before,
+                direction(i),
+                half_width,
+                stroke_style,
+            );
+        }
+    } else {
+        polygon.push(points[0] + direction(0).rot90() * half_width);
+        for (i, &point) in points.iter().enumerate().take(count - 1).skip(1) {
+            let before = direction(i - 1);
+            add_join(
+                polygon,
+                point,
+                before,
+                direction(i),
+                half_width,
+                stroke_style,
+            );
+        }
+        polygon.push(points[count - 1] + direction(count - 2).rot90() * half_width);
+    }
+}
+
+fn add_join(
+    polygon: &mut Vec<Point>,
+    point: Point,
+    before: Point,
+    after: Point,
+    half_width: f32,
+    stroke_style: &StrokeStyle,
+) {
+    let start = point + before.rot90() * half_width;
+    let end = point + after.rot90() * half_width;
+    let cross = before.cross(&after);
+
+    if cross < 0.0 {
+        // the inner side goes through the point, the loop it makes is wound like the stroke
+        polygon.extend([start, point, end]);
+        return;
+    }
+    if cross == 0.0 && before.dot(&after) > 0.0 {
+        polygon.push(start);
+        return;
+    }
+
+    let angle = before.angle(&after);
+    let miter_ratio = 1.0 / (angle / 2.0).cos();
+    let bisector = (before.rot90() + after.rot90()).normalize();
+
+    polygon.push(start);
+    match stroke_style.line_join {
+        LineJoin::Miter | LineJoin::MiterClip if miter_ratio <= stroke_style.miter_limit => {
+            polygon.push(point + bisector * half_width * miter_ratio);
+        }
+        LineJoin::MiterClip => {
+            // the miter cut off by a line across its bisector at the miter limit
+            let bisector = if bisector == Point::default() {
+                before
+            } else {
+                bisector
+            };
+            let distance = stroke_style.miter_limit * half_width;
+            let t = ((distance - (start - point).dot(&bisector)) / before.dot(&bisector)).max(0.0);
+            polygon.extend([start + before * t, end - after * t]);
+        }
+        LineJoin::Round => add_arc(polygon, point, start - point, angle),
+        LineJoin::Miter | LineJoin::Bevel => {}
+    }
+    polygon.push(end);
+}
+
+/// Goes around the end of a polyline from its left to its right
+fn add_cap(
+    polygon: &mut Vec<Point>,
+    point: Point,
+    direction: Point,
+    half_width: f32,
+    line_cap: LineCap,
+) {
+    let normal = direction.rot90() * half_width;
+    match line_cap {
+        LineCap::Butt => {}
+        LineCap::Square => {
+            let forward = direction * half_width;
+            polygon.extend([point + normal + forward, point - normal + forward]);
+        }
+        LineCap::Round => add_arc(polygon, point, normal, std::f32::consts::PI),
+    }
+}
+
+/// Points between the ends of an arc turning clockwise on the screen
+fn add_arc(polygon: &mut Vec<Point>, center: Point, from: Point, sweep: f32) {
+    let count = (sweep / ROUND_MIN_ANGLE).floor().max(1.0) as usize;
+    let step = sweep / count as f32;
+
+    polygon.extend((1..count).map(|i| {
+        let (sin, cos) = (step * i as f32).sin_cos();
+        center + Point::new(cos * from.x - sin * from.y, sin * from.x + cos * from.y)
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point_in_contours, vec2, FillRule, LineDash};
+
+    fn outline(points: &[Point], stroke_style: &StrokeStyle) -> Vec<Vec<Point>> {
+        let mut polygons = Vec::new();
+        stroke_outline(points, stroke_style, |polygon| {
+            polygons.push(polygon.to_vec())
+        });
+        polygons
+    }
+
+    fn covers(polygons: &[Vec<Point>], point: Point) -> bool {
+        point_in_contours(point, polygons.iter().map(|p| &p[..]), FillRule::NonZero)
+    }
+
+    #[test]
+    fn test_stroke_outline_caps() {
+        let line = [vec2(0.0, 0.0), vec2(10.0, 0.0)];
+        let style = StrokeStyle::default().line_width(4.0);
+
+        assert_eq!(
+            outline(&line, &style),
+            [vec![
+                vec2(0.0, -2.0),
+                vec2(10.0, -2.0),
+                vec2(10.0, 2.0),
+                vec2(0.0, 2.0),
+            ]]
+        );
+
+        let square = outline(&line, &style.clone().line_cap(LineCap::Square));
+        assert!(covers(&square, vec2(-1.5, 1.5)));
+        assert!(covers(&square, vec2(11.5, -1.5)));
+
+        let round = outline(&line, &style.clone().line_cap(LineCap::Round));
+        assert!(covers(&round, vec2(-1.9, 0.0)));
+        assert!(!covers(&round, vec2(-1.5, 1.5)));
+    }
+
+    #[test]
+    fn test_stroke_outline_joins() {
+        let corner = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 10.0)];
+        let style = StrokeStyle::default().line_width(4.0);
+
+        // the outer corner is mitered and the inner corner stays filled
+        let miter = outline(&corner, &style);
+        assert!(covers(&miter, vec2(11.9, -1.9)));
+        assert!(covers(&miter, vec2(8.5, 1.5)));
+        assert!(!covers(&miter, vec2(7.0, 3.0)));
+
+        let bevel = outline(&corner, &style.clone().line_join(LineJoin::Bevel));
+        assert!(!covers(&bevel, vec2(11.9, -1.9)));
+
+        let round = outline(&corner, &style.clone().line_join(LineJoin::Round));
+        assert!(covers(&round, vec2(11.3, -1.3)));
+        assert!(!covers(&round, vec2(11.9, -1.9)));
+    }
+
+    #[test]
+    fn test_stroke_outline_closed() {
+        let square = [
+            vec2(0.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(0.0, 10.0),
+            vec2(0.0, 0.0),
+        ];
+        let style = StrokeStyle::default().line_width(2.0);
+
+        // an outer ring and a hole
+        let ring = outline(&square, &style);
+        assert_eq!(ring.len(), 2);
+        assert!(covers(&ring, vec2(-0.9, -0.9)));
+        assert!(covers(&ring, vec2(0.9, 0.9)));
+        assert!(!covers(&ring, vec2(5.0, 5.0)));
+
+        // each dash is outlined on its own
+        let dashed = outline(&square, &style.clone().line_dash(LineDash::new([5.0, 5.0])));
+        assert_eq!(dashed.len(), 4);
+        assert!(covers(&dashed, vec2(2.0, 0.0)));
+        assert!(!covers(&dashed, vec2(7.0, 0.0)));
+    }
+
+    #[test]
+    fn test_stroke_to_path() {
+        let mut path = PathBuilder::default();
+        path.rect(&crate::Rect::xywh(0.0, 0.0, 10.0, 10.0));
+
+        let outline = stroke_to_path(&path.build(), &StrokeStyle::default().line_width(2.0));
+        let contours = outline
+            .events()
+            .filter(|event| matches!(event, PathEvent::End { close: true, .. }))
+            .count();
+        assert_eq!(contours, 2);
+    }
+}
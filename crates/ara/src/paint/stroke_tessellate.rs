@@ -2,21 +2,26 @@
 // https://github.com/CrushedPixel/Polyline2D
 // https://artgrammer.blogspot.com/2011/07/drawing-polylines-by-tessellation.html?m=1
 use std::{
+    borrow::Cow,
     f32,
     ops::{Deref, DerefMut},
 };
 
-const MITER_MIN_ANGLE: f32 = 0.349066; // ~20 degrees
+/// Length of the segment a zero length dash is drawn as, so its caps have a direction
+const DOT_LENGTH: f32 = 0.001;
+
+/// Dashes too small to see add up past this, a polyline with more of them is drawn solid
+const MAX_DASHES: f32 = 100_000.0;
 
 use crate::{LineJoin, Vec2};
 
-use super::{LineCap, Mesh, StrokeStyle, WHITE_UV};
+use super::{LineCap, LineDash, Mesh, StrokeStyle, WHITE_UV};
 
 /*
 TODO
 
 - [] support antialiasing
-- [] support line dash
+- [x] support line dash
 
 */
 
@@ -26,20 +31,27 @@ pub struct StrokeTessellator<'a> {
 }
 
 impl<'a> StrokeTessellator<'a> {
-    pub fn add_to_mesh(mesh: &'a mut Mesh, points: &[Vec2<f32>], stroke_style: &StrokeStyle) {
+    /// `pixel_scale` is the number of pixels per unit of the points,
+    /// non scaling strokes and hairlines are measured with it
+    pub fn add_to_mesh(
+        mesh: &'a mut Mesh,
+        points: &[Vec2<f32>],
+        stroke_style: &StrokeStyle,
+        pixel_scale: f32,
+    ) {
         let mut polyline = Self {
             mesh: StrokeTessellatorMesh::Borrowed(mesh),
         };
 
-        polyline.add_polyline(points, stroke_style);
+        polyline.add_stroke(points, stroke_style, pixel_scale);
     }
 
-    pub fn create(points: &[Vec2<f32>], stroke_style: &StrokeStyle) -> Mesh {
+    pub fn create(points: &[Vec2<f32>], stroke_style: &StrokeStyle, pixel_scale: f32) -> Mesh {
         let mut polyline = Self {
             mesh: StrokeTessellatorMesh::Owned(Default::default()),
         };
 
-        polyline.add_polyline(points, stroke_style);
+        polyline.add_stroke(points, stroke_style, pixel_scale);
 
         match polyline.mesh {
             StrokeTessellatorMesh::Owned(mesh) => mesh,
@@ -47,12 +59,26 @@ impl<'a> StrokeTessellator<'a> {
         }
     }
 
+    fn add_stroke(&mut self, points: &[Vec2<f32>], stroke_style: &StrokeStyle, pixel_scale: f32) {
+        if stroke_style.color.is_transparent() {
+            return;
+        }
+        let Some(stroke_style) = resolve_stroke(stroke_style, pixel_scale) else {
+            return;
+        };
+
+        dash_polyline(points, &stroke_style.line_dash, |dash| {
+            self.add_polyline(dash, &stroke_style)
+        });
+    }
+
     fn add_polyline(&mut self, points: &[Vec2<f32>], stroke_style: &StrokeStyle) {
-        if points.len() < 2 || stroke_style.line_width == 0 || stroke_style.color.is_transparent() {
+        if points.len() < 2 || stroke_style.line_width <= 0.0 || stroke_style.color.is_transparent()
+        {
             return;
         }
 
-        let h_linewidth = (stroke_style.line_width.max(1) as f32) / 2.0;
+        let h_linewidth = stroke_style.line_width / 2.0;
 
         let segments: Vec<PolySegment> = points
             .windows(2)
@@ -196,14 +222,16 @@ impl<'a> StrokeTessellator<'a> {
 
         let angle = dir1.angle(&dir2);
 
-        let mut wrapped_angle = angle;
-        if wrapped_angle > f32::consts::FRAC_PI_2 {
-            wrapped_angle = f32::consts::PI - wrapped_angle;
-        }
+        // distance from the center to the tip of the miter relative to the half width
+        let miter_ratio = 1.0 / (angle / 2.0).cos();
 
         let mut joint_style = style.line_join;
 
-        if joint_style == LineJoin::Miter && wrapped_angle < MITER_MIN_ANGLE {
+        if matches!(joint_style, LineJoin::Miter | LineJoin::MiterClip)
+            && miter_ratio <= style.miter_limit
+        {
+            joint_style = LineJoin::Miter;
+        } else if joint_style == LineJoin::Miter {
             joint_style = LineJoin::Bevel;
         }
 
@@ -292,9 +320,184 @@ impl<'a> StrokeTessellator<'a> {
                     outer2.a,
                     clockwise,
                 );
+            } else if joint_style == LineJoin::MiterClip {
+                // the miter cut off by a line across its bisector at the miter limit
+                let center = segment1.center.b;
+                let normal = (outer1.b - center + outer2.a - center).normalize();
+                let distance = style.miter_limit * style.line_width / 2.0;
+
+                let clip1 = outer1.b
+                    + dir1 * ((distance - (outer1.b - center).dot(&normal)) / dir1.dot(&normal));
+                let clip2 = outer2.a
+                    - dir2 * ((distance - (outer2.a - center).dot(&normal)) / -dir2.dot(&normal));
+
+                self.mesh.reserve_prim(5, 9);
+
+                let cur_vertex_idx = self.mesh.vertex_count();
+
+                self.mesh.add_vertex(inner_sec, style.color, WHITE_UV);
+                self.mesh.add_vertex(outer1.b, style.color, WHITE_UV);
+                self.mesh.add_vertex(clip1, style.color, WHITE_UV);
+                self.mesh.add_vertex(clip2, style.color, WHITE_UV);
+                self.mesh.add_vertex(outer2.a, style.color, WHITE_UV);
+
+                for i in 1..4 {
+                    self.mesh.add_triangle(
+                        cur_vertex_idx,
+                        cur_vertex_idx + i,
+                        cur_vertex_idx + i + 1,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The stroke in units of the points, none when nothing is drawn.
+/// Strokes thinner than a pixel are drawn a pixel wide with their coverage as alpha
+pub(crate) fn resolve_stroke(
+    stroke_style: &StrokeStyle,
+    pixel_scale: f32,
+) -> Option<Cow<'_, StrokeStyle>> {
+    let pixel_scale = if pixel_scale.is_finite() && pixel_scale > 0.0 {
+        pixel_scale
+    } else {
+        1.0
+    };
+    let pixels = if stroke_style.non_scaling {
+        stroke_style.line_width
+    } else {
+        stroke_style.line_width * pixel_scale
+    };
+    if pixels.is_nan() || pixels <= 0.0 {
+        return None;
+    }
+
+    let coverage = pixels.min(1.0);
+    let line_width = pixels.max(1.0) / pixel_scale;
+    if line_width == stroke_style.line_width && coverage == 1.0 {
+        return Some(Cow::Borrowed(stroke_style));
+    }
+
+    let mut resolved = stroke_style.clone();
+    resolved.line_width = line_width;
+    resolved.color.a = (resolved.color.a as f32 * coverage).round() as u8;
+    if stroke_style.non_scaling {
+        for segment in &mut resolved.line_dash.segments {
+            *segment /= pixel_scale;
+        }
+        resolved.line_dash.offset /= pixel_scale;
+    }
+    Some(Cow::Owned(resolved))
+}
+
+/// Splits a polyline into its dashes, each of them is stroked on its own with caps.
+/// The pattern continues around corners, and across the start of a closed polyline
+/// where the last and the first dash are joined. Solid dashes pass the polyline through
+pub(crate) fn dash_polyline(
+    points: &[Vec2<f32>],
+    dash: &LineDash,
+    mut f: impl FnMut(&[Vec2<f32>]),
+) {
+    let Some(pattern) = dash.pattern() else {
+        f(points);
+        return;
+    };
+
+    let total: f32 = pattern.iter().sum();
+    let start = dash.offset.rem_euclid(total);
+    let length: f32 = points
+        .windows(2)
+        .map(|window| (window[1] - window[0]).magnitude())
+        .sum();
+    if (length + start) / total * pattern.len() as f32 > MAX_DASHES {
+        f(points);
+        return;
+    }
+
+    // where the dashes and gaps end along the polyline, counted from its start
+    // so a long polyline does not gather the error of adding up the lengths
+    let ends: Vec<f32> = pattern
+        .iter()
+        .scan(0.0, |end, segment| {
+            *end += segment;
+            Some(*end)
+        })
+        .collect();
+    let end_of =
+        |step: usize| (step / pattern.len()) as f32 * total + ends[step % pattern.len()] - start;
+
+    // find where in the pattern the polyline starts
+    let mut step = 0;
+    while end_of(step) < 0.0 {
+        step += 1;
+    }
+    let mut index = step % pattern.len();
+
+    let closed = points.len() > 2 && points.first() == points.last();
+    let starts_on = index % 2 == 0;
+    // the first dash of a closed polyline is kept to join it with the last one
+    let mut first: Option<Vec<Vec2<f32>>> = None;
+    let mut dash_points: Vec<Vec2<f32>> = Vec::new();
+    if starts_on {
+        dash_points.extend(points.first());
+    }
+
+    let mut segment_start = 0.0;
+    for window in points.windows(2) {
+        let (a, b) = (window[0], window[1]);
+        let length = (b - a).magnitude();
+        if length == 0.0 {
+            continue;
+        }
+        let direction = (b - a) * (1.0 / length);
+
+        while end_of(step) - segment_start <= length {
+            let point = a + direction * (end_of(step) - segment_start);
+
+            if index % 2 == 0 {
+                dash_points.push(point);
+                if dash_points.iter().all(|p| *p == point) {
+                    dash_points = vec![point, point + direction * DOT_LENGTH];
+                }
+                if closed && starts_on && first.is_none() {
+                    first = Some(std::mem::take(&mut dash_points));
+                } else {
+                    f(&dash_points);
+                }
+                dash_points.clear();
+            } else {
+                dash_points.clear();
+                dash_points.push(point);
             }
+
+            step += 1;
+            index = step % pattern.len();
+        }
+
+        segment_start += length;
+        if index % 2 == 0 && dash_points.last() != Some(&b) {
+            dash_points.push(b);
         }
     }
+
+    let ends_on = index % 2 == 0 && dash_points.len() > 1;
+    match first {
+        Some(mut first) if ends_on => {
+            dash_points.extend(first.drain(1..));
+            f(&dash_points);
+        }
+        Some(first) => {
+            f(&first);
+            if ends_on {
+                f(&dash_points);
+            }
+        }
+        // the whole polyline is a single dash
+        None if closed && starts_on && ends_on => f(points),
+        None if ends_on => f(&dash_points),
+        None => {}
+    }
 }
 
 #[derive(Debug)]
@@ -426,3 +629,174 @@ impl LineSegment {
         Some(self.a + dir_self * t)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vec2, Color};
+
+    fn dashes(points: &[Vec2<f32>], dash: LineDash) -> Vec<Vec<Vec2<f32>>> {
+        let mut dashes = Vec::new();
+        dash_polyline(points, &dash, |points| dashes.push(points.to_vec()));
+        dashes
+    }
+
+    #[test]
+    fn test_dash_polyline_open() {
+        let line = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 10.0)];
+
+        // the third dash turns the corner
+        assert_eq!(
+            dashes(&line, LineDash::new([4.0, 2.0])),
+            [
+                vec![vec2(0.0, 0.0), vec2(4.0, 0.0)],
+                vec![vec2(6.0, 0.0), vec2(10.0, 0.0)],
+                vec![vec2(10.0, 2.0), vec2(10.0, 6.0)],
+                vec![vec2(10.0, 8.0), vec2(10.0, 10.0)],
+            ]
+        );
+
+        // an odd pattern is repeated, 1 on 3 off 1 on 1 off 3 on 1 off
+        assert_eq!(
+            dashes(&line[..2], LineDash::new([1.0, 3.0, 1.0])),
+            [
+                vec![vec2(0.0, 0.0), vec2(1.0, 0.0)],
+                vec![vec2(4.0, 0.0), vec2(5.0, 0.0)],
+                vec![vec2(6.0, 0.0), vec2(9.0, 0.0)],
+            ]
+        );
+
+        // the offset moves the pattern back along the line
+        assert_eq!(
+            dashes(&line[..2], LineDash::new([4.0, 2.0]).offset(5.0)),
+            [
+                vec![vec2(1.0, 0.0), vec2(5.0, 0.0)],
+                vec![vec2(7.0, 0.0), vec2(10.0, 0.0)],
+            ]
+        );
+        assert_eq!(
+            dashes(&line[..2], LineDash::new([4.0, 2.0]).offset(-1.0)),
+            dashes(&line[..2], LineDash::new([4.0, 2.0]).offset(5.0)),
+        );
+
+        // zero length dashes are dots along the line
+        let dots = dashes(&line[..2], LineDash::new([0.0, 5.0]));
+        assert_eq!(dots.len(), 3);
+        assert_eq!(dots[1], [vec2(5.0, 0.0), vec2(5.0 + DOT_LENGTH, 0.0)]);
+    }
+
+    #[test]
+    fn test_dash_polyline_solid() {
+        let line = [vec2(0.0, 0.0), vec2(10.0, 0.0)];
+
+        for dash in [
+            LineDash::default(),
+            LineDash::new([0.0, 0.0]),
+            LineDash::new([4.0, -1.0]),
+        ] {
+            assert_eq!(dashes(&line, dash), [line.to_vec()]);
+        }
+    }
+
+    #[test]
+    fn test_dash_polyline_tiny() {
+        let line = [vec2(0.0, 0.0), vec2(1e6, 0.0)];
+
+        // far too many dashes to draw, the line is drawn solid
+        assert_eq!(dashes(&line, LineDash::new([1e-6, 1e-6])), [line.to_vec()]);
+
+        // dashes far from the start keep their length
+        let dashed = dashes(&line, LineDash::new([10.0, 30.0]));
+        assert_eq!(dashed.len(), 25_000);
+        assert_eq!(
+            dashed.last().unwrap(),
+            &[vec2(999_960.0, 0.0), vec2(999_970.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn test_dash_polyline_closed() {
+        let square = [
+            vec2(0.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(0.0, 10.0),
+            vec2(0.0, 0.0),
+        ];
+
+        // the last dash continues into the first one
+        assert_eq!(
+            dashes(&square, LineDash::new([6.0, 4.0]).offset(3.0)),
+            [
+                vec![vec2(7.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 3.0)],
+                vec![vec2(10.0, 7.0), vec2(10.0, 10.0), vec2(7.0, 10.0)],
+                vec![vec2(3.0, 10.0), vec2(0.0, 10.0), vec2(0.0, 7.0)],
+                vec![vec2(0.0, 3.0), vec2(0.0, 0.0), vec2(3.0, 0.0)],
+            ]
+        );
+
+        // a dash longer than the contour keeps it closed
+        assert_eq!(
+            dashes(&square, LineDash::new([50.0, 10.0])),
+            [square.to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_resolve_stroke() {
+        let style = StrokeStyle::default()
+            .color(Color::BLACK)
+            .line_width(4.0)
+            .line_dash([8.0, 4.0]);
+
+        // a scaling stroke is left as it is
+        let resolved = resolve_stroke(&style, 2.0).unwrap();
+        assert!(matches!(resolved, Cow::Borrowed(_)));
+
+        // a non scaling stroke keeps its width and dashes in pixels
+        let resolved = resolve_stroke(&style.clone().non_scaling(true), 2.0)
+            .unwrap()
+            .into_owned();
+        assert_eq!(resolved.line_width, 2.0);
+        assert_eq!(resolved.line_dash.segments, [4.0, 2.0]);
+
+        // a hairline is a pixel wide and fades out with its coverage
+        let resolved = resolve_stroke(&style.clone().line_width(0.5), 1.0)
+            .unwrap()
+            .into_owned();
+        assert_eq!(resolved.line_width, 1.0);
+        assert_eq!(resolved.color.a, 128);
+
+        let resolved = resolve_stroke(&style.clone().line_width(1.0), 0.25)
+            .unwrap()
+            .into_owned();
+        assert_eq!(resolved.line_width, 4.0);
+        assert_eq!(resolved.color.a, 64);
+
+        assert!(resolve_stroke(&style.clone().line_width(0.0), 1.0).is_none());
+    }
+
+    #[test]
+    fn test_miter_limit() {
+        // the miter of this corner is about 2.24 times half the line width
+        let corner = [vec2(-10.0, 20.0), vec2(0.0, 0.0), vec2(10.0, 20.0)];
+        let top = |stroke_style: StrokeStyle| {
+            StrokeTessellator::create(&corner, &stroke_style.line_width(4.0), 1.0)
+                .vertices
+                .iter()
+                .map(|vertex| vertex.position[1])
+                .fold(f32::INFINITY, f32::min)
+        };
+        let style = StrokeStyle::default().color(Color::BLACK);
+
+        assert!((top(style.clone()) + 5.0_f32.sqrt() * 2.0).abs() < 1e-3);
+        // beveled past the limit
+        assert!((top(style.clone().miter_limit(2.0)) + 0.894).abs() < 1e-3);
+        // clipped at the limit
+        assert!((top(style.clone().miter_clip_join().miter_limit(2.0)) + 4.0).abs() < 1e-3);
+        assert_eq!(
+            top(style.clone().miter_clip_join()),
+            top(style.clone().miter_join())
+        );
+    }
+}
@@ -100,7 +100,7 @@ impl PathBuilder {
     }
 
     #[inline]
-    pub fn path_events(&self) -> PathEventsIter {
+    pub fn path_events(&self) -> PathEventsIter<'_> {
         self.validator.build();
         PathEventsIter::new(&self.points, &self.verbs)
     }
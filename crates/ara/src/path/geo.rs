@@ -212,6 +212,88 @@ pub fn get_path_bounds(path: &[Point]) -> Rect<f32> {
     Rect::from_corners((min_x, min_y).into(), (max_x, max_y).into())
 }
 
+/// Rule used to decide which regions of a path are inside
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+impl FillRule {
+    pub fn is_inside(&self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// Winding number of a polygon around the point. The polygon is implicitly closed
+pub fn winding_number(point: Point, polygon: &[Point]) -> i32 {
+    let mut winding = 0;
+
+    let Some(&last) = polygon.last() else {
+        return 0;
+    };
+
+    let mut a = last;
+    for &b in polygon {
+        // signed area of the triangle (a, b, point)
+        let side = (b.x - a.x) * (point.y - a.y) - (point.x - a.x) * (b.y - a.y);
+
+        if a.y <= point.y {
+            if b.y > point.y && side > 0.0 {
+                winding += 1;
+            }
+        } else if b.y <= point.y && side < 0.0 {
+            winding -= 1;
+        }
+        a = b;
+    }
+
+    winding
+}
+
+/// Checks if the point is inside the filled area of the contours
+pub fn point_in_contours<'a>(
+    point: Point,
+    contours: impl IntoIterator<Item = &'a [Point]>,
+    fill_rule: FillRule,
+) -> bool {
+    let winding = contours
+        .into_iter()
+        .map(|contour| winding_number(point, contour))
+        .sum();
+
+    fill_rule.is_inside(winding)
+}
+
+/// Shortest distance from the point to the polyline
+pub fn distance_to_polyline(point: Point, polyline: &[Point]) -> f32 {
+    match polyline {
+        [] => f32::INFINITY,
+        [single] => (point - *single).magnitude(),
+        _ => polyline
+            .windows(2)
+            .map(|segment| distance_to_segment(point, segment[0], segment[1]))
+            .fold(f32::INFINITY, f32::min),
+    }
+}
+
+fn distance_to_segment(point: Point, a: Point, b: Point) -> f32 {
+    let ab = b - a;
+    let len_sq = ab.dot(&ab);
+
+    if len_sq == 0.0 {
+        return (point - a).magnitude();
+    }
+
+    let t = ((point - a).dot(&ab) / len_sq).clamp(0.0, 1.0);
+    let closest = a + ab * t;
+    (point - closest).magnitude()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::path::{PathBuilder, PathEventsIter, Point};
@@ -736,4 +818,54 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn point_in_contours_fill_rules() {
+        use super::{point_in_contours, winding_number, FillRule};
+
+        let outer = [
+            vec2(0.0, 0.0),
+            vec2(100.0, 0.0),
+            vec2(100.0, 100.0),
+            vec2(0.0, 100.0),
+        ];
+        let inner = [
+            vec2(25.0, 25.0),
+            vec2(75.0, 25.0),
+            vec2(75.0, 75.0),
+            vec2(25.0, 75.0),
+        ];
+        let mut inner_reversed = inner;
+        inner_reversed.reverse();
+
+        let center = vec2(50.0, 50.0);
+        let ring = vec2(10.0, 50.0);
+
+        assert_eq!(winding_number(center, &outer).abs(), 1);
+        assert_eq!(winding_number(vec2(150.0, 50.0), &outer), 0);
+
+        // same direction, the hole is only cut with even odd
+        let contours = [&outer[..], &inner[..]];
+        assert!(point_in_contours(center, contours, FillRule::NonZero));
+        assert!(!point_in_contours(center, contours, FillRule::EvenOdd));
+        assert!(point_in_contours(ring, contours, FillRule::EvenOdd));
+
+        // opposite direction, the hole is cut with both rules
+        let contours = [&outer[..], &inner_reversed[..]];
+        assert!(!point_in_contours(center, contours, FillRule::NonZero));
+        assert!(!point_in_contours(center, contours, FillRule::EvenOdd));
+        assert!(point_in_contours(ring, contours, FillRule::NonZero));
+    }
+
+    #[test]
+    fn polyline_distance() {
+        use super::distance_to_polyline;
+
+        let line = [vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 100.0)];
+
+        assert_eq!(distance_to_polyline(vec2(50.0, 10.0), &line), 10.0);
+        assert_eq!(distance_to_polyline(vec2(110.0, 50.0), &line), 10.0);
+        assert_eq!(distance_to_polyline(vec2(-3.0, -4.0), &line), 5.0);
+        assert_eq!(distance_to_polyline(vec2(0.0, 0.0), &[]), f32::INFINITY);
+    }
 }
@@ -3,8 +3,10 @@ pub mod geo;
 
 pub use geo::*;
 pub mod polygon;
+mod region;
 pub use builder::*;
 pub use polygon::*;
+pub(crate) use region::*;
 
 pub type Point = ara_math::Vec2<f32>;
 
@@ -33,7 +35,7 @@ impl Path {
     pub fn builder() -> PathBuilder {
         PathBuilder::default()
     }
-    pub fn events(&self) -> PathEventsIter {
+    pub fn events(&self) -> PathEventsIter<'_> {
         PathEventsIter::new(&self.points, &self.verbs)
     }
 }
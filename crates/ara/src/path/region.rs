@@ -0,0 +1,421 @@
+use std::f32::consts::TAU;
+
+use ara_math::vec2;
+
+use super::{winding_number, FillRule, Point};
+
+/// An outer ring followed by the holes cut into it. The outer ring is clockwise
+/// and the holes are counter clockwise, so the filled area is always right of an edge
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FillRegion {
+    pub points: Vec<Point>,
+    /// start of each hole in `points`
+    pub holes: Vec<u32>,
+}
+
+type PointKey = (u32, u32);
+
+fn point_key(point: Point) -> PointKey {
+    // adding zero turns -0.0 into 0.0
+    ((point.x + 0.0).to_bits(), (point.y + 0.0).to_bits())
+}
+
+/// Splits implicitly closed contours into the regions they fill with the fill rule.
+/// The contours are cut where they cross, and only the pieces with the filled area on
+/// one side are kept, so overlaps and self intersections are resolved like a canvas does
+pub(crate) fn fill_regions<'a>(
+    contours: impl IntoIterator<Item = &'a [Point]> + Clone,
+    fill_rule: FillRule,
+) -> Vec<FillRegion> {
+    let edges: Vec<(Point, Point)> = contours.clone().into_iter().flat_map(ring_edges).collect();
+
+    let inside = |point: Point| {
+        let winding = contours
+            .clone()
+            .into_iter()
+            .map(|contour| winding_number(point, contour))
+            .sum();
+        fill_rule.is_inside(winding)
+    };
+
+    let mut boundary = Vec::new();
+    let mut seen = ahash::HashSet::default();
+    for (a, b) in split_edges(&edges) {
+        let (left, right) = side_samples(a, b);
+        let (a, b) = match (inside(left), inside(right)) {
+            (false, true) => (a, b),
+            (true, false) => (b, a),
+            _ => continue,
+        };
+
+        // coincident edges facing the same way bound the area once
+        if seen.insert((point_key(a), point_key(b))) {
+            boundary.push((a, b));
+        }
+    }
+
+    let mut outers = Vec::new();
+    let mut holes = Vec::new();
+    for ring in trace_rings(&boundary) {
+        let area = signed_area(&ring);
+        if area > 0.0 {
+            outers.push((ring, area));
+        } else if area < 0.0 {
+            holes.push(ring);
+        }
+    }
+
+    let mut regions: Vec<FillRegion> = outers
+        .iter()
+        .map(|(ring, _)| FillRegion {
+            points: ring.clone(),
+            holes: Vec::new(),
+        })
+        .collect();
+
+    // a hole belongs to the smallest outer ring around the area next to it
+    for hole in holes {
+        let (_, filled) = side_samples(hole[0], hole[1]);
+        let owner = outers
+            .iter()
+            .enumerate()
+            .filter(|(_, (ring, _))| winding_number(filled, ring) != 0)
+            .min_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+            .map(|(i, _)| i);
+
+        if let Some(region) = owner.map(|i| &mut regions[i]) {
+            region.holes.push(region.points.len() as u32);
+            region.points.extend(hole);
+        }
+    }
+
+    regions
+}
+
+/// Edges of an implicitly closed contour without the zero length ones
+fn ring_edges(contour: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    let len = match contour {
+        [first, .., last] if first == last => contour.len() - 1,
+        _ => contour.len(),
+    };
+
+    (0..len)
+        .map(move |i| (contour[i], contour[(i + 1) % len]))
+        .filter(|(a, b)| a != b)
+}
+
+/// Cuts the edges where they cross or touch each other, the cuts shared by two edges
+/// have the exact same position so the pieces can be joined back by their end points
+fn split_edges(edges: &[(Point, Point)]) -> Vec<(Point, Point)> {
+    let mut cuts: Vec<Vec<Point>> = vec![Vec::new(); edges.len()];
+
+    // sweep down the edges so only the ones overlapping vertically are tested
+    let min_y = |(a, b): (Point, Point)| a.y.min(b.y);
+    let max_y = |(a, b): (Point, Point)| a.y.max(b.y);
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by(|&i, &j| min_y(edges[i]).total_cmp(&min_y(edges[j])));
+
+    for (k, &i) in order.iter().enumerate() {
+        let bottom = max_y(edges[i]);
+        for &j in &order[k + 1..] {
+            if min_y(edges[j]) > bottom {
+                break;
+            }
+            intersect(edges[i], edges[j], |on_first, point| {
+                cuts[if on_first { i } else { j }].push(point);
+            });
+        }
+    }
+
+    let mut pieces = Vec::with_capacity(edges.len());
+    for (&(a, b), mut cuts) in edges.iter().zip(cuts) {
+        let direction = b - a;
+        cuts.sort_by(|p, q| {
+            (*p - a)
+                .dot(&direction)
+                .total_cmp(&(*q - a).dot(&direction))
+        });
+
+        let mut from = a;
+        for to in cuts.into_iter().chain(std::iter::once(b)) {
+            if to != from {
+                pieces.push((from, to));
+                from = to;
+            }
+        }
+    }
+    pieces
+}
+
+/// Reports the points where the edges cross or touch, on the edge they are inside of
+fn intersect((a0, a1): (Point, Point), (b0, b1): (Point, Point), mut cut: impl FnMut(bool, Point)) {
+    const TOLERANCE: f64 = 1e-6;
+
+    let cross = |u: (f64, f64), v: (f64, f64)| u.0 * v.1 - u.1 * v.0;
+    let sub = |p: Point, q: Point| ((p.x - q.x) as f64, (p.y - q.y) as f64);
+
+    let r = sub(a1, a0);
+    let s = sub(b1, b0);
+    let qp = sub(b0, a0);
+    let denominator = cross(r, s);
+    let r_len = r.0.hypot(r.1);
+    let s_len = s.0.hypot(s.1);
+
+    if denominator.abs() > 1e-9 * r_len * s_len {
+        let t = cross(qp, s) / denominator;
+        let u = cross(qp, r) / denominator;
+        let range = -TOLERANCE..=1.0 + TOLERANCE;
+        if !range.contains(&t) || !range.contains(&u) {
+            return;
+        }
+
+        // snap to the end points so the edges meet there exactly
+        let point = if u <= TOLERANCE {
+            b0
+        } else if u >= 1.0 - TOLERANCE {
+            b1
+        } else if t <= TOLERANCE {
+            a0
+        } else if t >= 1.0 - TOLERANCE {
+            a1
+        } else {
+            vec2(
+                (a0.x as f64 + r.0 * t) as f32,
+                (a0.y as f64 + r.1 * t) as f32,
+            )
+        };
+
+        if point != a0 && point != a1 {
+            cut(true, point);
+        }
+        if point != b0 && point != b1 {
+            cut(false, point);
+        }
+    } else if cross(qp, r).abs() <= 1e-9 * r_len * r_len.max(s_len) {
+        // collinear edges cut each other at the end points inside the other
+        let inside = |point: Point, from: Point, direction: (f64, f64), len: f64| {
+            let offset = sub(point, from);
+            let t = (offset.0 * direction.0 + offset.1 * direction.1) / (len * len);
+            t > TOLERANCE && t < 1.0 - TOLERANCE
+        };
+
+        for point in [b0, b1] {
+            if inside(point, a0, r, r_len) {
+                cut(true, point);
+            }
+        }
+        for point in [a0, a1] {
+            if inside(point, b0, s, s_len) {
+                cut(false, point);
+            }
+        }
+    }
+}
+
+/// Points just left and right of the middle of an edge as seen on the screen
+fn side_samples(a: Point, b: Point) -> (Point, Point) {
+    let middle = (a + b) * 0.5;
+    let direction = b - a;
+    let len = direction.magnitude();
+    let right = vec2(-direction.y, direction.x) / len;
+
+    // close to the edge but far enough to not round onto it
+    let precision = f32::EPSILON * 64.0 * (middle.x.abs().max(middle.y.abs()) + 1.0);
+    let offset = (len * 1e-3).min(1e-3).max(precision);
+
+    (middle - right * offset, middle + right * offset)
+}
+
+/// Joins the pieces into closed rings with the filled area on their right. Where several
+/// pieces leave a point the sharpest turn towards the filled area is taken,
+/// so areas which only touch at a point become separate rings
+fn trace_rings(pieces: &[(Point, Point)]) -> Vec<Vec<Point>> {
+    let mut outgoing: ahash::HashMap<PointKey, Vec<usize>> = Default::default();
+    for (i, &(a, _)) in pieces.iter().enumerate() {
+        outgoing.entry(point_key(a)).or_default().push(i);
+    }
+
+    // clockwise angle from going back to going out, a u turn is the last choice
+    let turn = |incoming: Point, outgoing: Point| {
+        let back = -incoming;
+        let angle = -back.cross(&outgoing).atan2(back.dot(&outgoing));
+        if angle <= 0.0 {
+            angle + TAU
+        } else {
+            angle
+        }
+    };
+
+    let mut used = vec![false; pieces.len()];
+    let mut rings = Vec::new();
+
+    for start in 0..pieces.len() {
+        if used[start] {
+            continue;
+        }
+
+        let start_key = point_key(pieces[start].0);
+        let mut ring = Vec::new();
+        let mut current = start;
+        loop {
+            used[current] = true;
+            let (a, b) = pieces[current];
+            ring.push(a);
+
+            let key = point_key(b);
+            if key == start_key {
+                break;
+            }
+
+            let incoming = b - a;
+            let next = outgoing.get(&key).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|&i| !used[i])
+                    .min_by(|&i, &j| {
+                        let (i_from, i_to) = pieces[i];
+                        let (j_from, j_to) = pieces[j];
+                        turn(incoming, i_to - i_from).total_cmp(&turn(incoming, j_to - j_from))
+                    })
+            });
+
+            // a chain which does not close because of rounding is closed as it is
+            match next {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+
+        if ring.len() >= 3 {
+            rings.push(ring);
+        }
+    }
+
+    rings
+}
+
+/// Twice the area of the ring, positive when it is clockwise on the screen
+fn signed_area(ring: &[Point]) -> f32 {
+    let Some(&last) = ring.last() else {
+        return 0.0;
+    };
+
+    let mut previous = last;
+    let mut area = 0.0;
+    for &point in ring {
+        area += previous.x * point.y - point.x * previous.y;
+        previous = point;
+    }
+    area
+}
+
+#[cfg(test)]
+mod tests {
+    use ara_math::vec2;
+
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Vec<Point> {
+        vec![
+            vec2(x, y),
+            vec2(x + w, y),
+            vec2(x + w, y + h),
+            vec2(x, y + h),
+        ]
+    }
+
+    fn filled_area(regions: &[FillRegion]) -> f32 {
+        regions
+            .iter()
+            .flat_map(|region| {
+                let starts = std::iter::once(0).chain(region.holes.iter().copied());
+                let ends = (region.holes.iter().copied()).chain([region.points.len() as u32]);
+                starts
+                    .zip(ends)
+                    .map(|(start, end)| &region.points[start as usize..end as usize])
+            })
+            .map(|ring| signed_area(ring) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_fill_regions_holes() {
+        let outer = rect(0.0, 0.0, 30.0, 30.0);
+        let mut inner = rect(10.0, 10.0, 10.0, 10.0);
+
+        // an inner ring going the other way is a hole for both rules
+        inner.reverse();
+        for fill_rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            let regions = fill_regions([&outer[..], &inner[..]], fill_rule);
+            assert_eq!(regions.len(), 1);
+            assert_eq!(regions[0].holes, [4]);
+            assert_eq!(filled_area(&regions), 800.0);
+        }
+
+        // going the same way it is only a hole with even odd
+        inner.reverse();
+        let regions = fill_regions([&outer[..], &inner[..]], FillRule::NonZero);
+        assert_eq!((regions.len(), filled_area(&regions)), (1, 900.0));
+        assert!(regions[0].holes.is_empty());
+
+        let regions = fill_regions([&outer[..], &inner[..]], FillRule::EvenOdd);
+        assert_eq!((regions.len(), filled_area(&regions)), (1, 800.0));
+
+        // an island in the hole is a region of its own
+        let island = rect(12.0, 12.0, 6.0, 6.0);
+        let regions = fill_regions([&outer[..], &inner[..], &island[..]], FillRule::EvenOdd);
+        assert_eq!((regions.len(), filled_area(&regions)), (2, 836.0));
+    }
+
+    #[test]
+    fn test_fill_regions_overlaps() {
+        // overlapping rects are merged into one outline
+        let a = rect(0.0, 0.0, 20.0, 20.0);
+        let b = rect(10.0, 10.0, 20.0, 20.0);
+        let regions = fill_regions([&a[..], &b[..]], FillRule::NonZero);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].points.len(), 8);
+        assert_eq!(filled_area(&regions), 700.0);
+
+        // the overlap is cut out with even odd
+        let regions = fill_regions([&a[..], &b[..]], FillRule::EvenOdd);
+        assert_eq!(filled_area(&regions), 600.0);
+
+        // rects sharing an edge become one
+        let c = rect(20.0, 0.0, 20.0, 20.0);
+        let regions = fill_regions([&a[..], &c[..]], FillRule::NonZero);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(filled_area(&regions), 800.0);
+    }
+
+    #[test]
+    fn test_fill_regions_self_intersections() {
+        // a bow tie is two triangles touching at the crossing
+        let bow_tie = [
+            vec2(0.0, 0.0),
+            vec2(20.0, 20.0),
+            vec2(20.0, 0.0),
+            vec2(0.0, 20.0),
+        ];
+        let regions = fill_regions([&bow_tie[..]], FillRule::NonZero);
+        assert_eq!(regions.len(), 2);
+        assert_eq!(filled_area(&regions), 200.0);
+
+        // the center of a star is wound twice
+        let star: Vec<Point> = (0..5)
+            .map(|i| {
+                let angle = i as f32 * 4.0 * std::f32::consts::PI / 5.0;
+                vec2(angle.sin(), -angle.cos()) * 10.0
+            })
+            .collect();
+
+        let regions = fill_regions([&star[..]], FillRule::NonZero);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].points.len(), 10);
+
+        let regions = fill_regions([&star[..]], FillRule::EvenOdd);
+        assert_eq!(regions.len(), 5);
+        assert!(regions.iter().all(|region| region.points.len() == 3));
+    }
+}
@@ -0,0 +1,170 @@
+use wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState};
+
+/// How drawn pixels are combined with what is already in the target, follows the canvas
+/// `globalCompositeOperation` names.
+///
+/// Pipelines output premultiplied colors. `Normal`, `Add`, `Screen` and `Erase` are exact fixed
+/// function blend states, `Multiply` is fixed function too but only exact over opaque backdrops.
+/// The remaining separable modes read the backdrop and are drawn through an offscreen layer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// source over
+    #[default]
+    Normal,
+    /// lighter, colors are added
+    Add,
+    Multiply,
+    Screen,
+    /// destination out, clears the target where the source is drawn
+    Erase,
+    Darken,
+    Lighten,
+    Overlay,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+}
+
+impl BlendMode {
+    /// The fixed function blend state for the mode, `None` for modes which need the backdrop
+    pub fn blend_state(&self) -> Option<BlendState> {
+        let (color, alpha) = match self {
+            BlendMode::Normal => (
+                component(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+                component(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ),
+            BlendMode::Add => (
+                component(BlendFactor::One, BlendFactor::One),
+                component(BlendFactor::One, BlendFactor::One),
+            ),
+            BlendMode::Multiply => (
+                component(BlendFactor::Dst, BlendFactor::OneMinusSrcAlpha),
+                component(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ),
+            BlendMode::Screen => (
+                component(BlendFactor::One, BlendFactor::OneMinusSrc),
+                component(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ),
+            BlendMode::Erase => (
+                component(BlendFactor::Zero, BlendFactor::OneMinusSrcAlpha),
+                component(BlendFactor::Zero, BlendFactor::OneMinusSrcAlpha),
+            ),
+            _ => return None,
+        };
+
+        Some(BlendState { color, alpha })
+    }
+
+    /// Whether the mode has to be composited in a separate pass with a copy of the backdrop
+    pub fn needs_backdrop(&self) -> bool {
+        self.blend_state().is_none()
+    }
+
+    /// Index of the blend function in `composite.wgsl`
+    pub(crate) fn shader_index(&self) -> u32 {
+        match self {
+            BlendMode::Darken => 1,
+            BlendMode::Lighten => 2,
+            BlendMode::Overlay => 3,
+            BlendMode::ColorDodge => 4,
+            BlendMode::ColorBurn => 5,
+            BlendMode::HardLight => 6,
+            BlendMode::SoftLight => 7,
+            BlendMode::Difference => 8,
+            BlendMode::Exclusion => 9,
+            _ => 0,
+        }
+    }
+}
+
+const fn component(src_factor: BlendFactor, dst_factor: BlendFactor) -> BlendComponent {
+    BlendComponent {
+        src_factor,
+        dst_factor,
+        operation: BlendOperation::Add,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates a blend component for premultiplied src and dst values
+    fn apply(component: BlendComponent, src: [f32; 4], dst: [f32; 4], channel: usize) -> f32 {
+        let factor = |factor: BlendFactor| match factor {
+            BlendFactor::Zero => 0.0,
+            BlendFactor::One => 1.0,
+            BlendFactor::Dst => dst[channel],
+            BlendFactor::OneMinusSrc => 1.0 - src[channel],
+            BlendFactor::OneMinusSrcAlpha => 1.0 - src[3],
+            other => unimplemented!("{other:?}"),
+        };
+        src[channel] * factor(component.src_factor) + dst[channel] * factor(component.dst_factor)
+    }
+
+    fn blend(mode: BlendMode, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+        let state = mode.blend_state().unwrap();
+        std::array::from_fn(|i| {
+            let component = if i == 3 { state.alpha } else { state.color };
+            apply(component, src, dst, i)
+        })
+    }
+
+    #[test]
+    fn fixed_function_modes() {
+        let half_red = [0.5, 0.0, 0.0, 0.5];
+        let gray = [0.5, 0.5, 0.5, 1.0];
+
+        assert_eq!(
+            blend(BlendMode::Normal, half_red, gray),
+            [0.75, 0.25, 0.25, 1.0]
+        );
+        assert_eq!(blend(BlendMode::Add, half_red, gray), [1.0, 0.5, 0.5, 1.5]);
+        assert_eq!(
+            blend(BlendMode::Multiply, [1.0, 0.0, 1.0, 1.0], gray),
+            [0.5, 0.0, 0.5, 1.0]
+        );
+        assert_eq!(
+            blend(BlendMode::Screen, [1.0, 0.0, 0.5, 1.0], gray),
+            [1.0, 0.5, 0.75, 1.0]
+        );
+        assert_eq!(
+            blend(BlendMode::Erase, half_red, gray),
+            [0.25, 0.25, 0.25, 0.5]
+        );
+    }
+
+    #[test]
+    fn advanced_modes_need_the_backdrop() {
+        let fixed = [
+            BlendMode::Normal,
+            BlendMode::Add,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Erase,
+        ];
+        for mode in fixed {
+            assert!(!mode.needs_backdrop(), "{mode:?}");
+            assert_eq!(mode.shader_index(), 0);
+        }
+
+        let advanced = [
+            BlendMode::Darken,
+            BlendMode::Lighten,
+            BlendMode::Overlay,
+            BlendMode::ColorDodge,
+            BlendMode::ColorBurn,
+            BlendMode::HardLight,
+            BlendMode::SoftLight,
+            BlendMode::Difference,
+            BlendMode::Exclusion,
+        ];
+        for (i, mode) in advanced.iter().enumerate() {
+            assert!(mode.needs_backdrop(), "{mode:?}");
+            assert_eq!(mode.shader_index(), i as u32 + 1);
+        }
+    }
+}
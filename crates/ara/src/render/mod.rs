@@ -9,8 +9,10 @@ use crate::gpu;
 use crate::scene::RenderRoot;
 use crate::scene::SceneNode;
 use crate::scene::ScenePlugin;
+use crate::AraAtlas;
 use crate::Slot;
 use crate::Subscription;
+use crate::TextSystem;
 use ahash::HashSet;
 use ara_math::Size;
 use pipes::{PipeCollection, RenderPipe};
@@ -18,10 +20,12 @@ use render_target::RenderTarget;
 use render_target::RenderTargetSystem;
 use renderable::{DisplayObject, Renderable, View};
 use runner::{RenderExecContext, RenderRunners};
+use std::sync::Arc;
 use systems::EncoderSystem;
+use texture::{ImageHandle, RenderImage, TextureSource, TextureSourceDescriptor};
 pub use view::{ViewConfig, ViewSystem, ViewSystemExt, ViewTarget};
 
-use systems::{GeometryHandle, RenderBufferRange, System, SystemCollection};
+use systems::{GeometryHandle, GlyphSystem, RenderBufferRange, System, SystemCollection};
 
 pub mod context;
 pub use context::*;
@@ -37,6 +41,18 @@ pub mod render_target;
 pub mod renderable;
 pub mod runner;
 
+pub mod blend;
+pub use blend::*;
+
+pub mod pass;
+pub use pass::*;
+
+pub mod readback;
+pub use readback::*;
+
+pub mod stats;
+pub use stats::*;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct RenderTargetView {
     pub(crate) target: RenderTarget,
@@ -44,6 +60,23 @@ pub struct RenderTargetView {
     pub(crate) screen_size: Size<u32>,
 }
 
+impl From<&TextureSource<RenderTarget>> for RenderTargetView {
+    fn from(source: &TextureSource<RenderTarget>) -> Self {
+        Self {
+            target: source.source().clone(),
+            pixel_size: source.pixel_size(),
+            screen_size: source.size(),
+        }
+    }
+}
+
+impl RenderTargetView {
+    /// Physical pixels per scene unit
+    pub fn resolution(&self) -> f32 {
+        self.pixel_size.width as f32 / self.screen_size.width.max(1) as f32
+    }
+}
+
 pub struct RenderTo {
     pub target: ViewTarget,
     pub config: ViewConfig,
@@ -61,6 +94,10 @@ impl Default for RenderTo {
 #[derive(Default)]
 pub struct RendererSpecification {
     pub render_to: RenderTo,
+    /// atlas glyphs are rasterized into, a new one when none
+    pub texture_atlas: Option<Arc<AraAtlas>>,
+    /// fonts used to shape text, a new one when none
+    pub text_system: Option<Arc<TextSystem>>,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -100,8 +137,14 @@ pub struct Renderer {
 
 impl Renderer {
     fn set_default_configuration(this: &mut Self, specs: RendererSpecification) {
+        let atlas = specs
+            .texture_atlas
+            .unwrap_or_else(|| Arc::new(AraAtlas::new(this.gpu.clone())));
+        let text_system = specs.text_system.unwrap_or_default();
+
         this.add_plugins(DefaultPlugins)
             .add_system(|cx| ViewSystem::new(cx, specs.render_to.target, specs.render_to.config))
+            .add_system(|_| GlyphSystem::new(text_system, atlas))
             .add_plugins(ScenePlugin);
     }
 
@@ -194,6 +237,29 @@ impl Renderer {
         &self.gpu
     }
 
+    /// Creates an offscreen target next to the primary view, render into it with `RenderOptions::view`
+    pub fn create_texture_target(
+        &mut self,
+        descriptor: &TextureSourceDescriptor,
+    ) -> TextureSource<RenderTarget> {
+        let source = TextureSource::empty(descriptor);
+        let image = self
+            .context
+            .new_item(|cx| RenderImage::new(&cx.gpu, &source));
+        source.replace(RenderTarget::from(ImageHandle(image)))
+    }
+
+    fn primary_view(&self) -> RenderTargetView {
+        self.context.read_system(|sys: &ViewSystem, _| {
+            let view = sys.view();
+            RenderTargetView {
+                target: view.source.clone(),
+                pixel_size: view.pixel_size(),
+                screen_size: view.size(),
+            }
+        })
+    }
+
     pub fn add_system<S: System + 'static>(
         &mut self,
         build: impl FnOnce(&mut ItemContext<S>) -> S,
@@ -234,7 +300,8 @@ impl Renderer {
         self.context.add_runner(runner, callback)
     }
 
-    pub fn render<R>(&mut self, root: &R, options: impl Into<RenderOptions>)
+    /// Renders the root and reports how much of the scene had to be rebuilt
+    pub fn render<R>(&mut self, root: &R, options: impl Into<RenderOptions>) -> RenderStats
     where
         R: RenderRoot + DisplayObject,
     {
@@ -246,16 +313,7 @@ impl Renderer {
         let options: RenderOptions = options.into();
         let renderable = RootRenderable { root };
 
-        let view = options.view.unwrap_or_else(|| {
-            self.context.read_system(|sys: &ViewSystem, _| {
-                let view = sys.view();
-                RenderTargetView {
-                    target: view.source.clone(),
-                    pixel_size: view.pixel_size(),
-                    screen_size: view.size(),
-                }
-            })
-        });
+        let view = options.view.unwrap_or_else(|| self.primary_view());
         let clear_color = options.clear_color.unwrap_or(self.last_clear_color);
         self.last_clear_color = clear_color;
 
@@ -328,6 +386,9 @@ impl Renderer {
                 log::error!("Error in finish callback: {}", err);
             }
         });
+
+        self.context
+            .read_system(|stats: &RenderStatsSystem, _| stats.stats())
     }
 }
 
@@ -524,18 +585,54 @@ impl ItemManager for RenderContext {
 
 #[derive(Debug, Clone)]
 pub enum RenderCommand {
+    /// clips the following draws to a rect in the space of the node,
+    /// with the rect drawn as a mask when the node does not keep it axis aligned
     SetScissor {
         rect: crate::Rect<f32>,
+        mask: Option<(GeometryHandle, RenderBufferRange)>,
+    },
+    SetBlendMode {
+        mode: BlendMode,
     },
     DrawIndexed {
         geometry_handle: GeometryHandle,
         render_buffer_slice: RenderBufferRange,
     },
+    /// draws the geometry as a mask, see `PaintContext::push_mask`
+    PushMask {
+        geometry_handle: GeometryHandle,
+        render_buffer_slice: RenderBufferRange,
+    },
+    /// removes a mask pushed with the same geometry
+    PopMask {
+        geometry_handle: GeometryHandle,
+        render_buffer_slice: RenderBufferRange,
+    },
+    /// the paint following draws are filled with, see `PaintUniform`
+    SetPaint {
+        bind_group: wgpu::BindGroup,
+    },
+    /// the texture following draws sample, see `TextureSystem`
+    SetTexture {
+        bind_group: wgpu::BindGroup,
+    },
 }
 
 impl RenderCommand {
     pub fn set_scissor(rect: crate::Rect<f32>) -> Self {
-        Self::SetScissor { rect }
+        Self::SetScissor { rect, mask: None }
+    }
+
+    pub fn set_blend_mode(mode: BlendMode) -> Self {
+        Self::SetBlendMode { mode }
+    }
+
+    pub fn set_paint(bind_group: wgpu::BindGroup) -> Self {
+        Self::SetPaint { bind_group }
+    }
+
+    pub fn set_texture(bind_group: wgpu::BindGroup) -> Self {
+        Self::SetTexture { bind_group }
     }
 
     pub fn draw_indexed(
@@ -547,6 +644,26 @@ impl RenderCommand {
             render_buffer_slice,
         }
     }
+
+    pub fn push_mask(
+        geometry_handle: GeometryHandle,
+        render_buffer_slice: RenderBufferRange,
+    ) -> Self {
+        Self::PushMask {
+            geometry_handle,
+            render_buffer_slice,
+        }
+    }
+
+    pub fn pop_mask(
+        geometry_handle: GeometryHandle,
+        render_buffer_slice: RenderBufferRange,
+    ) -> Self {
+        Self::PopMask {
+            geometry_handle,
+            render_buffer_slice,
+        }
+    }
 }
 
 struct PlaceholderPlugin;
@@ -572,13 +689,8 @@ where
         self.root.node().prepare(render_context);
     }
 
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: ara_math::Size<u32>,
-        render_context: &mut crate::render::RenderContext,
-    ) {
-        self.root.node().paint(pass, viewport, render_context);
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut crate::render::RenderContext) {
+        self.root.node().paint(paint, render_context);
     }
 }
 
@@ -611,6 +723,10 @@ where
         self.root.get_rotation()
     }
 
+    fn get_pivot(&self) -> crate::Point {
+        self.root.get_pivot()
+    }
+
     fn renderable(&self) -> bool {
         self.root.renderable()
     }
@@ -622,20 +738,31 @@ where
     fn alpha(&self) -> f32 {
         self.root.alpha()
     }
+
+    fn get_z_index(&self) -> i32 {
+        self.root.get_z_index()
+    }
 }
 
 pub(crate) struct DefaultPlugins;
 impl Plugin for DefaultPlugins {
     fn setup(&self, renderer: &mut Renderer) {
         use renderable::RenderableSystem;
-        use systems::{GeometrySystem, GlobalUniformSystem, HelloSystem};
+        use systems::{
+            GeometrySystem, GlobalUniformSystem, HelloSystem, LayerSystem, PipelineSystem,
+            TextureSystem,
+        };
 
         renderer
             .add_system(|_| HelloSystem)
             .add_system(EncoderSystem::new)
             .add_system(GlobalUniformSystem::new)
             .add_system(GeometrySystem::new)
+            .add_system(PipelineSystem::new)
+            .add_system(LayerSystem::new)
+            .add_system(TextureSystem::new)
             .add_system(RenderTargetSystem::new)
+            .add_system(RenderStatsSystem::new)
             .add_system(RenderableSystem::new);
     }
 }
@@ -0,0 +1,451 @@
+use std::any::Any;
+
+use ara_math::Size;
+
+use crate::Color;
+
+use super::{
+    render_target::TargetFormat,
+    systems::{LayerSystem, PipelineSystem},
+    texture::RenderImage,
+    BlendMode, RenderContext, RenderStatsSystem,
+};
+
+/// Attachments of a texture drawn into by a render pass
+#[derive(Debug, Clone)]
+pub struct PassTarget {
+    /// single sampled texture holding the result once the pass ends
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    /// multisampled attachment resolved into `view`
+    pub msaa: Option<wgpu::TextureView>,
+}
+
+impl PassTarget {
+    pub fn from_image(image: &RenderImage) -> Self {
+        Self {
+            texture: image.texture().clone(),
+            view: image.view().clone(),
+            msaa: image.msaa().map(|msaa| msaa.view().clone()),
+        }
+    }
+}
+
+/// Format of the stencil attachment every paint pass has, masks are drawn into it
+pub const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+
+/// How draws interact with the stencil attachment of the pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StencilMode {
+    /// draws only where every pushed mask was drawn
+    Test,
+    /// draws a mask, covered pixels are moved one mask deeper
+    Increment,
+    /// erases a mask drawn with `Increment`
+    Decrement,
+}
+
+impl StencilMode {
+    pub fn depth_stencil_state(&self) -> wgpu::DepthStencilState {
+        let (pass_op, write_mask) = match self {
+            StencilMode::Test => (wgpu::StencilOperation::Keep, 0),
+            StencilMode::Increment => (wgpu::StencilOperation::IncrementClamp, 0xff),
+            StencilMode::Decrement => (wgpu::StencilOperation::DecrementClamp, 0xff),
+        };
+
+        let face = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Equal,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op,
+        };
+
+        wgpu::DepthStencilState {
+            format: STENCIL_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState {
+                front: face,
+                back: face,
+                read_mask: 0xff,
+                write_mask,
+            },
+            bias: Default::default(),
+        }
+    }
+
+    /// Masks only touch the stencil attachment
+    pub fn color_writes(&self) -> wgpu::ColorWrites {
+        match self {
+            StencilMode::Test => wgpu::ColorWrites::ALL,
+            StencilMode::Increment | StencilMode::Decrement => wgpu::ColorWrites::empty(),
+        }
+    }
+
+    /// Whether draws change the stencil instead of the color
+    pub fn is_mask(&self) -> bool {
+        *self != StencilMode::Test
+    }
+}
+
+/// A draw the paint context holds back so the draws following it can be merged into it.
+/// It is issued before anything else is drawn, a mask or layer is pushed or the pass ends
+pub trait PendingDraw: Any {
+    /// Appends `next` to this draw, false when the two can not be drawn with a single call
+    fn merge(&mut self, next: &mut dyn Any) -> bool;
+
+    fn draw(self: Box<Self>, pass: &mut wgpu::RenderPass<'static>);
+}
+
+/// A target drawn into by the paint context with its own stencil
+struct PaintLevel {
+    target: PassTarget,
+    /// the layer backing the target, none for the root
+    image: Option<RenderImage>,
+    stencil: wgpu::Texture,
+    /// number of masks pushed while drawing into the target
+    mask_depth: u32,
+}
+
+/// Passed to nodes while painting.
+/// Owns the render pass of the current target and splits it when layers are pushed and popped
+pub struct PaintContext<'encoder> {
+    encoder: &'encoder mut wgpu::CommandEncoder,
+    pass: Option<wgpu::RenderPass<'static>>,
+    pending: Option<Box<dyn PendingDraw>>,
+    levels: Vec<PaintLevel>,
+    stencil_mode: StencilMode,
+    target: TargetFormat,
+    viewport: Size<u32>,
+    resolution: f32,
+}
+
+impl<'encoder> PaintContext<'encoder> {
+    /// Begins a pass clearing the root target
+    pub fn new(
+        encoder: &'encoder mut wgpu::CommandEncoder,
+        cx: &mut RenderContext,
+        root: PassTarget,
+        target: TargetFormat,
+        viewport: Size<u32>,
+        resolution: f32,
+        clear_color: Color,
+    ) -> Self {
+        let stencil = cx.update_system(|layers: &mut LayerSystem, _| {
+            layers.acquire_stencil(viewport, target.sample_count)
+        });
+
+        let mut this = Self {
+            encoder,
+            pass: None,
+            pending: None,
+            levels: vec![PaintLevel {
+                target: root,
+                image: None,
+                stencil,
+                mask_depth: 0,
+            }],
+            stencil_mode: StencilMode::Test,
+            target,
+            viewport,
+            resolution,
+        };
+        this.begin_pass(wgpu::LoadOp::Clear(clear_color.into()));
+        this
+    }
+
+    /// Size of the target in physical pixels
+    pub fn viewport(&self) -> Size<u32> {
+        self.viewport
+    }
+
+    /// Physical pixels per scene unit
+    pub fn resolution(&self) -> f32 {
+        self.resolution
+    }
+
+    /// Format of the target being drawn into, layers share the format of the root target
+    pub fn target_format(&self) -> TargetFormat {
+        self.target
+    }
+
+    /// Number of layers pushed on top of the root target
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    /// Number of masks clipping the current target
+    pub fn mask_depth(&self) -> u32 {
+        self.level().mask_depth
+    }
+
+    /// Pipelines have to be created with the depth stencil state and color writes of the mode
+    pub fn stencil_mode(&self) -> StencilMode {
+        self.stencil_mode
+    }
+
+    /// The pass of the current target, the pipeline and bind groups have to be set again
+    /// after pushing or popping a layer
+    pub fn pass(&mut self) -> &mut wgpu::RenderPass<'static> {
+        self.flush();
+        self.active_pass()
+    }
+
+    /// Holds the draw back until something else is drawn,
+    /// returns true if it was merged into the draw held back before it
+    pub fn defer(&mut self, mut draw: impl PendingDraw) -> bool {
+        if let Some(pending) = &mut self.pending {
+            if pending.merge(&mut draw) {
+                return true;
+            }
+        }
+
+        self.flush();
+        self.pending = Some(Box::new(draw));
+        false
+    }
+
+    /// Issues the draw held back by `defer`
+    pub fn flush(&mut self) {
+        if let Some(draw) = self.pending.take() {
+            draw.draw(self.active_pass());
+        }
+    }
+
+    /// Clips everything drawn until the matching `pop_mask` to what `draw` covers,
+    /// masks nest and only the area covered by all of them is drawn
+    pub fn push_mask(&mut self, draw: impl FnOnce(&mut Self)) {
+        self.flush();
+        self.stencil_mode = StencilMode::Increment;
+        draw(self);
+        self.flush();
+
+        self.stencil_mode = StencilMode::Test;
+        self.level_mut().mask_depth += 1;
+        self.set_stencil_reference();
+    }
+
+    /// Removes the last mask, `draw` has to cover the same area it did when pushing
+    pub fn pop_mask(&mut self, draw: impl FnOnce(&mut Self)) {
+        if self.mask_depth() == 0 {
+            log::warn!("pop_mask called without a mask");
+            return;
+        }
+
+        self.flush();
+        self.stencil_mode = StencilMode::Decrement;
+        draw(self);
+        self.flush();
+
+        self.stencil_mode = StencilMode::Test;
+        self.level_mut().mask_depth -= 1;
+        self.set_stencil_reference();
+    }
+
+    /// Redirects drawing into a transparent layer until `pop_layer`.
+    /// The layer starts without masks, those of the parent are applied when compositing it
+    pub fn push_layer(&mut self, cx: &mut RenderContext) {
+        self.end_pass();
+
+        let (size, target) = (self.viewport, self.target);
+        let (image, stencil) = cx.update_system(|layers: &mut LayerSystem, _| {
+            (
+                layers.acquire(size, target),
+                layers.acquire_stencil(size, target.sample_count),
+            )
+        });
+
+        self.levels.push(PaintLevel {
+            target: PassTarget::from_image(&image),
+            image: Some(image),
+            stencil,
+            mask_depth: 0,
+        });
+
+        self.begin_pass(wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT));
+    }
+
+    /// Draws the current layer into its parent with the blend mode and alpha
+    pub fn pop_layer(&mut self, cx: &mut RenderContext, blend_mode: BlendMode, alpha: f32) {
+        if self.levels.len() == 1 {
+            log::warn!("pop_layer called without a layer");
+            return;
+        }
+
+        self.end_pass();
+
+        let level = self.levels.pop().expect("checked above");
+        if level.mask_depth > 0 {
+            log::warn!("{} masks were not popped", level.mask_depth);
+        }
+        let layer = level.image.expect("layers are backed by an image");
+
+        let mut blend_mode = blend_mode;
+        let backdrop = if blend_mode.needs_backdrop() {
+            let backdrop = self.copy_backdrop(cx);
+            if backdrop.is_none() {
+                blend_mode = BlendMode::Normal;
+            }
+            backdrop
+        } else {
+            None
+        };
+
+        self.begin_pass(wgpu::LoadOp::Load);
+
+        let target = self.target;
+        let Some((key, bind_group)) = cx.update_system(|layers: &mut LayerSystem, _| {
+            let key = layers.composite_key(target, blend_mode)?;
+            let backdrop = backdrop.as_ref().unwrap_or(&layer);
+            let bind_group =
+                layers.composite_bind_group(layer.view(), backdrop.view(), blend_mode, alpha);
+            Some((key, bind_group))
+        }) else {
+            log::warn!("LayerSystem is not initialized, dropping layer");
+            return;
+        };
+
+        let pipeline = cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.get_or_create(&key).clone()
+        });
+
+        let pass = self.pass();
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+
+        cx.update_system(|layers: &mut LayerSystem, _| {
+            layers.release(layer);
+            layers.release_stencil(level.stencil);
+            if let Some(backdrop) = backdrop {
+                layers.release(backdrop);
+            }
+        });
+        cx.update_system(|stats: &mut RenderStatsSystem, _| stats.stats.layers_composited += 1);
+    }
+
+    /// Ends the pass, layers which were not popped are discarded
+    pub fn finish(mut self, cx: &mut RenderContext) {
+        self.end_pass();
+
+        if self.levels.len() > 1 {
+            log::warn!("{} layers were not popped", self.levels.len() - 1);
+        }
+
+        let levels = std::mem::take(&mut self.levels);
+        cx.update_system(|pool: &mut LayerSystem, _| {
+            for level in levels {
+                if let Some(image) = level.image {
+                    pool.release(image);
+                }
+                pool.release_stencil(level.stencil);
+            }
+        });
+    }
+
+    /// Copies what was drawn into the current target so far
+    fn copy_backdrop(&mut self, cx: &mut RenderContext) -> Option<RenderImage> {
+        let source = self.current_target().texture;
+
+        if !source.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            log::warn!("The target can not be copied, blending with BlendMode::Normal instead");
+            return None;
+        }
+
+        let size = self.viewport;
+        let target = TargetFormat {
+            format: self.target.format,
+            sample_count: 1,
+        };
+        let backdrop = cx.update_system(|layers: &mut LayerSystem, _| layers.acquire(size, target));
+
+        self.encoder.copy_texture_to_texture(
+            source.as_image_copy(),
+            backdrop.texture().as_image_copy(),
+            wgpu::Extent3d {
+                width: size.width.min(source.width()),
+                height: size.height.min(source.height()),
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Some(backdrop)
+    }
+
+    fn level(&self) -> &PaintLevel {
+        self.levels
+            .last()
+            .expect("PaintContext always has a root level")
+    }
+
+    fn level_mut(&mut self) -> &mut PaintLevel {
+        self.levels
+            .last_mut()
+            .expect("PaintContext always has a root level")
+    }
+
+    fn current_target(&self) -> PassTarget {
+        self.level().target.clone()
+    }
+
+    fn active_pass(&mut self) -> &mut wgpu::RenderPass<'static> {
+        self.pass
+            .as_mut()
+            .expect("PaintContext always has an active pass")
+    }
+
+    fn set_stencil_reference(&mut self) {
+        let reference = self.mask_depth();
+        self.pass().set_stencil_reference(reference);
+    }
+
+    fn begin_pass(&mut self, load: wgpu::LoadOp<wgpu::Color>) {
+        let level = self.level();
+        let target = level.target.clone();
+        let stencil = level.stencil.create_view(&Default::default());
+        let (view, resolve_target) = match &target.msaa {
+            Some(msaa) => (msaa, Some(&target.view)),
+            None => (&target.view, None),
+        };
+        // the stencil is fresh whenever the color is
+        let stencil_load = match load {
+            wgpu::LoadOp::Clear(_) => wgpu::LoadOp::Clear(0),
+            wgpu::LoadOp::Load => wgpu::LoadOp::Load,
+        };
+
+        let pass = self
+            .encoder
+            .begin_render_pass(
+                &(wgpu::RenderPassDescriptor {
+                    label: Some("ara_render::PaintContext::RenderPass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target,
+                        ops: wgpu::Operations {
+                            load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &stencil,
+                        depth_ops: None,
+                        stencil_ops: Some(wgpu::Operations {
+                            load: stencil_load,
+                            store: wgpu::StoreOp::Store,
+                        }),
+                    }),
+                    ..Default::default()
+                }),
+            )
+            .forget_lifetime();
+
+        self.pass = Some(pass);
+        self.set_stencil_reference();
+    }
+
+    fn end_pass(&mut self) {
+        self.flush();
+        // dropping the pass ends it and releases the encoder
+        self.pass = None;
+    }
+}
@@ -0,0 +1,403 @@
+use anyhow::{bail, Result};
+use ara_math::Size;
+use futures::channel::oneshot;
+use wgpu::{BufferAsyncError, PollType, TextureUsages};
+
+use crate::Context;
+
+use super::{RenderTargetView, Renderer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// values are gamma encoded, what image files usually store
+    Srgb,
+    /// values are proportional to light intensity
+    Linear,
+}
+
+impl ColorSpace {
+    pub fn of_format(format: wgpu::TextureFormat) -> Self {
+        if format.is_srgb() {
+            Self::Srgb
+        } else {
+            Self::Linear
+        }
+    }
+}
+
+/// Pixels read back from a render target as tightly packed rows of RGBA8
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSnapshot {
+    /// size in physical pixels
+    pub size: Size<u32>,
+    /// the logical size of the view the pixels were read from
+    pub screen_size: Size<u32>,
+    /// the color space of the texture the pixels were read from.
+    /// sRGB textures are encoded by the gpu on write, linear textures store what the shaders wrote
+    pub color_space: ColorSpace,
+    /// rows of RGBA8 with premultiplied alpha, as every pipeline writes them.
+    /// Image encoders expect straight alpha, see `unpremultiply`
+    pub data: Vec<u8>,
+}
+
+impl RenderSnapshot {
+    pub fn width(&self) -> u32 {
+        self.size.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size.height
+    }
+
+    /// Physical pixels per logical pixel
+    pub fn resolution(&self) -> f32 {
+        self.size.width as f32 / self.screen_size.width.max(1) as f32
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.size.width || y >= self.size.height {
+            return None;
+        }
+        let index = ((y * self.size.width + x) * 4) as usize;
+        self.data[index..index + 4].try_into().ok()
+    }
+
+    /// Divides the color channels by alpha, what image files usually store.
+    /// Transparent pixels become transparent black
+    pub fn unpremultiply(mut self) -> Self {
+        for pixel in self.data.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            for channel in &mut pixel[..3] {
+                *channel = match alpha {
+                    0 => 0,
+                    _ => ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8,
+                };
+            }
+        }
+        self
+    }
+
+    /// Re-encodes the color channels, alpha is always linear
+    pub fn convert(mut self, color_space: ColorSpace) -> Self {
+        let encode: fn(f32) -> f32 = match (self.color_space, color_space) {
+            (ColorSpace::Linear, ColorSpace::Srgb) => linear_to_srgb,
+            (ColorSpace::Srgb, ColorSpace::Linear) => srgb_to_linear,
+            _ => return self,
+        };
+
+        let table: Vec<u8> = (0..=255u8)
+            .map(|v| (encode(v as f32 / 255.0) * 255.0).round() as u8)
+            .collect();
+
+        for pixel in self.data.chunks_exact_mut(4) {
+            for channel in &mut pixel[..3] {
+                *channel = table[*channel as usize];
+            }
+        }
+
+        self.color_space = color_space;
+        self
+    }
+}
+
+/// Returns pending once so the executor runs other tasks before polling again
+#[cfg(not(target_arch = "wasm32"))]
+async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return std::task::Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    })
+    .await
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Layout of a texture copy, rows in the staging buffer are padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ReadbackLayout {
+    pub bytes_per_texel: u32,
+    pub unpadded_bytes_per_row: u32,
+    pub padded_bytes_per_row: u32,
+    pub size: Size<u32>,
+}
+
+impl ReadbackLayout {
+    pub fn new(size: Size<u32>, bytes_per_texel: u32) -> Self {
+        let unpadded_bytes_per_row = size.width * bytes_per_texel;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        Self {
+            bytes_per_texel,
+            unpadded_bytes_per_row,
+            padded_bytes_per_row,
+            size,
+        }
+    }
+
+    pub fn buffer_size(&self) -> u64 {
+        self.padded_bytes_per_row as u64 * self.size.height as u64
+    }
+
+    /// Removes the row padding
+    pub fn unpad(&self, padded: &[u8]) -> Vec<u8> {
+        let mut data =
+            Vec::with_capacity((self.unpadded_bytes_per_row * self.size.height) as usize);
+        for row in padded
+            .chunks(self.padded_bytes_per_row as usize)
+            .take(self.size.height as usize)
+        {
+            data.extend_from_slice(&row[..self.unpadded_bytes_per_row as usize]);
+        }
+        data
+    }
+}
+
+/// Converts tightly packed texels of a supported format to RGBA8
+fn to_rgba8(format: wgpu::TextureFormat, mut data: Vec<u8>) -> Result<Vec<u8>> {
+    use wgpu::TextureFormat::*;
+
+    match format {
+        Rgba8Unorm | Rgba8UnormSrgb => Ok(data),
+        Bgra8Unorm | Bgra8UnormSrgb => {
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            Ok(data)
+        }
+        format => bail!("Reading pixels of {format:?} textures is not supported"),
+    }
+}
+
+/// Copies the top left `size` texels of the texture into a buffer and calls `read` with the
+/// tightly packed texels once the buffer is mapped. The texture needs `TextureUsages::COPY_SRC`
+pub fn read_texture_async(
+    gpu: &Context,
+    src: &wgpu::Texture,
+    size: Size<u32>,
+    read: impl FnOnce(Result<Vec<u8>, BufferAsyncError>) + Send + 'static,
+) -> Result<()> {
+    if !src.usage().contains(TextureUsages::COPY_SRC) {
+        bail!("required TextureUsages::COPY_SRC in source texture");
+    }
+
+    let bytes_per_texel = src
+        .format()
+        .block_copy_size(None)
+        .ok_or(anyhow::anyhow!("Invalid format unable to get texel size"))?;
+
+    let size = Size::new(size.width.min(src.width()), size.height.min(src.height()));
+    if size.width == 0 || size.height == 0 {
+        bail!("Nothing to read from an empty texture");
+    }
+
+    let layout = ReadbackLayout::new(size, bytes_per_texel);
+
+    let output_buffer = gpu.device.create_buffer(
+        &(wgpu::BufferDescriptor {
+            label: Some("ara::readback::Buffer"),
+            size: layout.buffer_size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }),
+    );
+
+    let mut encoder = gpu.create_command_encoder(Some("ara::readback::CommandEncoder"));
+
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture: src,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &output_buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(layout.padded_bytes_per_row),
+                rows_per_image: Some(size.height),
+            },
+        },
+        wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    gpu.queue.submit(Some(encoder.finish()));
+
+    output_buffer.slice(..).map_async(wgpu::MapMode::Read, {
+        let buffer = output_buffer.clone();
+        move |res| {
+            // a buffer that failed to map must not be unmapped
+            let res = res.map(|_| {
+                let data = layout.unpad(&buffer.slice(..).get_mapped_range());
+                buffer.unmap();
+                data
+            });
+            read(res)
+        }
+    });
+
+    Ok(())
+}
+
+impl Renderer {
+    /// Reads the pixels of the primary view, see `read_view_pixels`
+    pub fn read_pixels(&self) -> Result<RenderSnapshot> {
+        let view = self.primary_view();
+        self.read_view_pixels(&view)
+    }
+
+    /// Reads back what was last rendered to the view. Blocks until the gpu is done.
+    /// Only offscreen targets can be read as surface textures are gone once presented
+    pub fn read_view_pixels(&self, view: &RenderTargetView) -> Result<RenderSnapshot> {
+        let receiver = self.request_pixels(view)?;
+        self.gpu.device.poll(PollType::Wait)?;
+        futures::executor::block_on(receiver)?
+    }
+
+    /// Reads back what was last rendered to the view without blocking the executor,
+    /// the gpu is polled in between yielding to other tasks.
+    /// On the web the browser maps the buffer on its own
+    pub async fn read_view_pixels_async(&self, view: &RenderTargetView) -> Result<RenderSnapshot> {
+        #[allow(unused_mut)]
+        let mut receiver = self.request_pixels(view)?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        loop {
+            if let Some(res) = receiver.try_recv()? {
+                return res;
+            }
+            self.gpu.device.poll(PollType::Poll)?;
+            yield_now().await;
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        receiver.await?
+    }
+
+    fn request_pixels(
+        &self,
+        view: &RenderTargetView,
+    ) -> Result<oneshot::Receiver<Result<RenderSnapshot>>> {
+        let Some(texture) = view.target.get_texture(&self.context) else {
+            bail!(
+                "Pixels can only be read from offscreen targets, got {:?}",
+                view.target
+            );
+        };
+
+        let format = texture.format();
+        let screen_size = view.screen_size;
+        let size = Size::new(
+            view.pixel_size.width.min(texture.width()),
+            view.pixel_size.height.min(texture.height()),
+        );
+
+        let (sender, receiver) = oneshot::channel();
+
+        read_texture_async(&self.gpu, &texture, size, move |res| {
+            let res = res
+                .map_err(|err| anyhow::anyhow!("Error reading pixels {:#?}", err))
+                .and_then(|data| {
+                    Ok(RenderSnapshot {
+                        size,
+                        screen_size,
+                        color_space: ColorSpace::of_format(format),
+                        data: to_rgba8(format, data)?,
+                    })
+                });
+
+            if sender.send(res).is_err() {
+                log::error!("Error reading pixels: failed at sending async data");
+            }
+        })?;
+
+        Ok(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_to_copy_alignment() {
+        let layout = ReadbackLayout::new(Size::new(3, 2), 4);
+        assert_eq!(layout.unpadded_bytes_per_row, 12);
+        assert_eq!(layout.padded_bytes_per_row, 256);
+        assert_eq!(layout.buffer_size(), 512);
+
+        let mut padded = vec![0u8; 512];
+        padded[..12].copy_from_slice(&[1; 12]);
+        padded[256..268].copy_from_slice(&[2; 12]);
+
+        let data = layout.unpad(&padded);
+        assert_eq!(data.len(), 24);
+        assert!(data[..12].iter().all(|&v| v == 1));
+        assert!(data[12..].iter().all(|&v| v == 2));
+
+        let exact = ReadbackLayout::new(Size::new(64, 1), 4);
+        assert_eq!(exact.padded_bytes_per_row, 256);
+    }
+
+    #[test]
+    fn converts_formats_and_color_spaces() {
+        let bgra = to_rgba8(wgpu::TextureFormat::Bgra8Unorm, vec![1, 2, 3, 4]).unwrap();
+        assert_eq!(bgra, [3, 2, 1, 4]);
+        assert!(to_rgba8(wgpu::TextureFormat::R8Unorm, vec![0]).is_err());
+
+        let snapshot = RenderSnapshot {
+            size: Size::new(1, 1),
+            screen_size: Size::new(1, 1),
+            color_space: ColorSpace::Linear,
+            data: vec![0, 255, 55, 128],
+        };
+
+        let srgb = snapshot.clone().convert(ColorSpace::Srgb);
+        assert_eq!(srgb.color_space, ColorSpace::Srgb);
+        assert_eq!(srgb.data[..2], [0, 255]);
+        assert!(srgb.data[2] > 55);
+        assert_eq!(srgb.data[3], 128);
+
+        let linear = srgb.convert(ColorSpace::Linear);
+        assert!(linear.data[2].abs_diff(55) <= 1);
+    }
+
+    #[test]
+    fn unpremultiplies_alpha() {
+        let snapshot = RenderSnapshot {
+            size: Size::new(3, 1),
+            screen_size: Size::new(3, 1),
+            color_space: ColorSpace::Linear,
+            data: vec![64, 0, 128, 128, 255, 255, 255, 255, 10, 20, 30, 0],
+        };
+
+        assert_eq!(
+            snapshot.unpremultiply().data,
+            [128, 0, 255, 128, 255, 255, 255, 255, 0, 0, 0, 0]
+        );
+    }
+}
@@ -1,9 +1,13 @@
+use ara_math::Size;
 use derive_more::derive::Deref;
 use wgpu::SurfaceTexture;
 
 use crate::{
     gpu,
-    render::{texture::TextureSource, Item},
+    render::{
+        texture::{sample_count_for, MultisampleTexture, TextureSource},
+        Item, PassTarget,
+    },
 };
 
 use super::RenderTargetAdapter;
@@ -20,6 +24,7 @@ impl From<BackendRenderTargetHandle> for super::RenderTarget {
 pub struct BackendRenderTarget {
     pub surface: wgpu::Surface<'static>,
     pub config: wgpu::SurfaceConfiguration,
+    msaa: Option<MultisampleTexture>,
 }
 
 impl BackendRenderTarget {
@@ -37,9 +42,12 @@ impl BackendRenderTarget {
 
         let size = texture_source.pixel_size();
 
+        // blend modes reading the backdrop copy the surface texture
+        let copy_usage = capabilities.usages & wgpu::TextureUsages::COPY_SRC;
+
         let surface_config: wgpu::wgt::SurfaceConfiguration<Vec<wgpu::TextureFormat>> =
             wgpu::SurfaceConfiguration {
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | texture_source.usage,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | copy_usage | texture_source.usage,
                 format: texture_source.format,
                 width: size.width,
                 height: size.height,
@@ -51,17 +59,36 @@ impl BackendRenderTarget {
 
         surface.configure(context, &surface_config);
 
+        let sample_count =
+            sample_count_for(context, texture_source.format, texture_source.antialias);
+        let msaa = MultisampleTexture::new(context, size, texture_source.format, sample_count);
+
         BackendRenderTarget {
             surface,
             config: surface_config,
+            msaa,
         }
     }
 
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.msaa.as_ref().map_or(1, |msaa| msaa.sample_count())
+    }
+
     pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
         if self.config.width != width || self.config.height != height {
             self.config.width = width;
             self.config.height = height;
             self.surface.configure(device, &self.config);
+            self.msaa = MultisampleTexture::new(
+                device,
+                Size::new(width, height),
+                self.config.format,
+                self.sample_count(),
+            );
         }
     }
 }
@@ -74,37 +101,24 @@ pub struct BackendRenderTargetAdapter {
 impl RenderTargetAdapter for BackendRenderTargetAdapter {
     type Target = BackendRenderTarget;
 
-    fn begin_pass<'encoder>(
+    fn pass_target(
         &mut self,
         target: &mut Self::Target,
-        clear_color: crate::Color,
-        encoder: &'encoder mut wgpu::CommandEncoder,
         _cx: &mut crate::render::RenderContext,
-    ) -> Option<wgpu::RenderPass<'encoder>> {
+    ) -> Option<PassTarget> {
         let current_texture = target.surface.get_current_texture().ok()?;
 
-        let view = current_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        let pass = encoder.begin_render_pass(
-            &(wgpu::RenderPassDescriptor {
-                label: Some("ara_render::backend_target::RenderPass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(clear_color.into()),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                ..Default::default()
-            }),
-        );
+        let pass_target = PassTarget {
+            texture: current_texture.texture.clone(),
+            view: current_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            msaa: target.msaa.as_ref().map(|msaa| msaa.view().clone()),
+        };
 
         self.current_texture = Some(current_texture);
 
-        Some(pass)
+        Some(pass_target)
     }
 
     fn render_complete(&mut self) {
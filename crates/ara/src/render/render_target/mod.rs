@@ -9,9 +9,15 @@ pub use system::RenderTargetSystem;
 mod backend;
 pub use backend::{BackendRenderTarget, BackendRenderTargetHandle};
 
+mod texture;
+pub use texture::TextureRenderTargetAdapter;
+
 use crate::gpu::{self};
 
-use super::{texture::RenderTexture, ItemManager};
+use super::{
+    texture::{ImageHandle, RenderTexture},
+    ItemManager, PassTarget,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RenderTargetConfig {
@@ -66,9 +72,18 @@ impl Default for RenderTargetConfig {
     }
 }
 
+/// What pipelines drawing into a render target have to be created with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetFormat {
+    pub format: gpu::TextureFormat,
+    pub sample_count: u32,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum RenderTarget {
     Backend(BackendRenderTargetHandle),
+    /// offscreen texture owned by the renderer
+    Texture(ImageHandle),
     Noop,
 }
 
@@ -80,6 +95,7 @@ impl RenderTexture for RenderTarget {
                     target.resize(&cx.gpu.device, physical_size.width, physical_size.height);
                 });
             }
+            RenderTarget::Texture(handle) => handle.resize(cx, physical_size),
             RenderTarget::Noop => {
                 // No operation for noop targets
             }
@@ -92,20 +108,51 @@ impl RenderTarget {
         Self::Backend(handle)
     }
 
+    pub fn texture(handle: ImageHandle) -> Self {
+        Self::Texture(handle)
+    }
+
     pub fn noop() -> Self {
         Self::Noop
     }
+
+    /// The texture rendered to, only available for offscreen targets
+    pub fn get_texture(&self, cx: &impl ItemManager) -> Option<wgpu::Texture> {
+        match self {
+            RenderTarget::Texture(handle) => {
+                handle.read(cx, |image, _| image.texture().clone()).ok()
+            }
+            RenderTarget::Backend(_) | RenderTarget::Noop => None,
+        }
+    }
+
+    pub fn target_format(&self, cx: &impl ItemManager) -> Option<TargetFormat> {
+        match self {
+            RenderTarget::Backend(handle) => handle
+                .read(cx, |target, _| TargetFormat {
+                    format: target.format(),
+                    sample_count: target.sample_count(),
+                })
+                .ok(),
+            RenderTarget::Texture(handle) => handle
+                .read(cx, |image, _| TargetFormat {
+                    format: image.format(),
+                    sample_count: image.sample_count(),
+                })
+                .ok(),
+            RenderTarget::Noop => None,
+        }
+    }
 }
 
 pub trait RenderTargetAdapter {
     type Target;
-    fn begin_pass<'encoder>(
+    /// The texture to draw into for this frame
+    fn pass_target(
         &mut self,
         target: &mut Self::Target,
-        clear_color: crate::Color,
-        encoder: &'encoder mut wgpu::CommandEncoder,
         cx: &mut crate::render::RenderContext,
-    ) -> Option<wgpu::RenderPass<'encoder>>;
+    ) -> Option<PassTarget>;
 
     fn render_complete(&mut self);
 }
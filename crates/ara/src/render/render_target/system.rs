@@ -1,15 +1,16 @@
-use wgpu::CommandEncoder;
-
 use crate::{
     render::{
         renderable::Renderable,
         systems::{EncoderSystem, System},
-        ItemContext, RenderContext, RenderRunner, RenderTargetView,
+        ItemContext, PaintContext, PassTarget, RenderContext, RenderRunner, RenderTargetView,
     },
     Color, Subscription,
 };
 
-use super::{backend::BackendRenderTargetAdapter, RenderTarget, RenderTargetAdapter};
+use super::{
+    backend::BackendRenderTargetAdapter, texture::TextureRenderTargetAdapter, RenderTarget,
+    RenderTargetAdapter,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct RenderTargetEntry {
@@ -98,17 +99,30 @@ impl RenderTargetSystem {
             return Ok(());
         };
 
+        let target = &entry.view.target;
+        let (Some(pass_target), Some(format)) = (
+            self.adapter.pass_target(target, cx),
+            target.target_format(cx),
+        ) else {
+            log::warn!("Error creating pass for target: {:?}", target);
+            return Ok(());
+        };
+
+        let viewport = entry.view.pixel_size;
+        let resolution = entry.view.resolution();
         cx.update_system(|encoder: &mut EncoderSystem, cx| {
             encoder.with(|encoder| {
-                let Some(mut pass) =
-                    self.adapter
-                        .begin_pass(&entry.view.target, clear_color, encoder, cx)
-                else {
-                    log::warn!("Error creating pass for target: {:?}", &entry.view.target);
-                    return;
-                };
-                let viewport = entry.view.pixel_size;
-                renderable.paint(&mut pass, viewport, cx);
+                let mut paint = PaintContext::new(
+                    encoder,
+                    cx,
+                    pass_target,
+                    format,
+                    viewport,
+                    resolution,
+                    clear_color,
+                );
+                renderable.paint(&mut paint, cx);
+                paint.finish(cx);
             });
         });
 
@@ -119,21 +133,21 @@ impl RenderTargetSystem {
 #[derive(Default)]
 struct RenderTargetAdapters {
     backend_adapter: BackendRenderTargetAdapter,
+    texture_adapter: TextureRenderTargetAdapter,
 }
 
 impl RenderTargetAdapters {
-    fn begin_pass<'encoder>(
-        &mut self,
-        target: &RenderTarget,
-        clear_color: Color,
-        encoder: &'encoder mut CommandEncoder,
-        cx: &mut RenderContext,
-    ) -> Option<wgpu::RenderPass<'encoder>> {
+    fn pass_target(&mut self, target: &RenderTarget, cx: &mut RenderContext) -> Option<PassTarget> {
         match target {
             RenderTarget::Backend(handle) => handle
                 .update(cx, |target, cx| {
-                    self.backend_adapter
-                        .begin_pass(target, clear_color, encoder, cx)
+                    self.backend_adapter.pass_target(target, cx)
+                })
+                .ok()
+                .flatten(),
+            RenderTarget::Texture(handle) => handle
+                .update(cx, |target, cx| {
+                    self.texture_adapter.pass_target(target, cx)
                 })
                 .ok()
                 .flatten(),
@@ -146,6 +160,9 @@ impl RenderTargetAdapters {
             RenderTarget::Backend(_) => {
                 self.backend_adapter.render_complete();
             }
+            RenderTarget::Texture(_) => {
+                self.texture_adapter.render_complete();
+            }
             RenderTarget::Noop => {
                 // noop
             }
@@ -0,0 +1,31 @@
+use crate::render::{
+    texture::{ImageHandle, RenderImage},
+    PassTarget,
+};
+
+use super::RenderTargetAdapter;
+
+impl From<ImageHandle> for super::RenderTarget {
+    fn from(handle: ImageHandle) -> Self {
+        Self::Texture(handle)
+    }
+}
+
+#[derive(Default)]
+pub struct TextureRenderTargetAdapter;
+
+impl RenderTargetAdapter for TextureRenderTargetAdapter {
+    type Target = RenderImage;
+
+    fn pass_target(
+        &mut self,
+        target: &mut Self::Target,
+        _cx: &mut crate::render::RenderContext,
+    ) -> Option<PassTarget> {
+        Some(PassTarget::from_image(target))
+    }
+
+    fn render_complete(&mut self) {
+        // nothing to present, the texture keeps the result
+    }
+}
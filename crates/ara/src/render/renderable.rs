@@ -1,6 +1,7 @@
 mod system;
 pub use system::RenderableSystem;
 
+use crate::render::PaintContext;
 use crate::scene::IntoSceneNode;
 use crate::scene::RenderRoot;
 use crate::scene::SceneNode;
@@ -11,12 +12,7 @@ use crate::Rect;
 
 pub trait Renderable: DisplayObject {
     fn prepare(&self, render_context: &mut crate::render::RenderContext);
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: ara_math::Size<u32>,
-        render_context: &mut crate::render::RenderContext,
-    );
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut crate::render::RenderContext);
 }
 
 pub trait View {
@@ -28,20 +24,25 @@ pub trait DisplayObject: View {
     fn get_position(&self) -> Point;
     fn get_scale(&self) -> Point;
     fn get_rotation(&self) -> f32;
+    fn get_pivot(&self) -> Point;
     fn renderable(&self) -> bool;
     fn visible(&self) -> bool;
     fn alpha(&self) -> f32;
+    fn get_z_index(&self) -> i32;
 }
 
 pub trait DisplayObjectMut: DisplayObject {
     fn set_position(&mut self, position: Point);
     fn set_scale(&mut self, scale: Point);
     fn set_rotation(&mut self, rotation: f32);
+    fn set_pivot(&mut self, pivot: Point);
     fn set_visible(&mut self, visible: bool);
     fn set_alpha(&mut self, alpha: f32);
     fn set_renderable(&mut self, renderable: bool);
+    fn set_z_index(&mut self, z_index: i32);
 }
 
+#[allow(unused)]
 pub(crate) struct EmptyElement;
 
 impl RenderRoot for EmptyElement {
@@ -55,13 +56,7 @@ impl RenderRoot for EmptyElement {
 impl SceneNode for EmptyElement {
     fn prepare(&self, _render_context: &mut super::RenderContext) {}
 
-    fn paint<'encoder>(
-        &self,
-        _pass: &mut wgpu::RenderPass<'encoder>,
-        _viewport: ara_math::Size<u32>,
-        _render_context: &mut super::RenderContext,
-    ) {
-    }
+    fn paint(&self, _paint: &mut PaintContext, _render_context: &mut super::RenderContext) {}
 }
 
 impl SceneNodeIdentifier for EmptyElement {
@@ -101,6 +96,10 @@ impl DisplayObject for EmptyElement {
         0.0
     }
 
+    fn get_pivot(&self) -> Point {
+        Point::default()
+    }
+
     fn renderable(&self) -> bool {
         false
     }
@@ -112,4 +111,8 @@ impl DisplayObject for EmptyElement {
     fn alpha(&self) -> f32 {
         1.0
     }
+
+    fn get_z_index(&self) -> i32 {
+        0
+    }
 }
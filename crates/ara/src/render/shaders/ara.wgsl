@@ -3,11 +3,29 @@ struct Globals {
     _pad: vec2f, // webgl requirement
 };
 
+struct Node {
+    transform: mat3x3f,
+    alpha: f32,
+};
+
+// how texels are read, see TextureUniform
+struct TextureInfo {
+    kind: u32,
+};
+
+const TEXTURE_MASK: u32 = 0u;
+const TEXTURE_STRAIGHT: u32 = 1u;
+
 fn to_device_coordinate(point: vec2f) -> vec2f {
     return (point / globals.viewport_size) * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0);
 }
 
 @group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var<uniform> node: Node;
+// texture of a textured fill, a white texel otherwise
+@group(3) @binding(0) var graphics_texture: texture_2d<f32>;
+@group(3) @binding(1) var graphics_sampler: sampler;
+@group(3) @binding(2) var<uniform> texture_info: TextureInfo;
 
 struct VertexIn {
     @location(0) position: vec2f,
@@ -19,17 +37,49 @@ struct VertexOut {
     @builtin(position) position: vec4f,
     @location(1) uv: vec2f,
     @location(0) color: vec4f,
+    // alpha of the geometry before the node alpha is applied
+    @location(2) coverage: f32,
+    // position before the node transform, gradients are evaluated with it
+    @location(3) local: vec2f,
 };
 
 
 @vertex fn vs(in: VertexIn) -> VertexOut {
     var out: VertexOut;
-    out.position = vec4f(to_device_coordinate(in.position), 0.0, 1.0);
+    let position = (node.transform * vec3f(in.position, 1.0)).xy;
+    out.position = vec4f(to_device_coordinate(position), 0.0, 1.0);
     out.uv = in.uv;
-    out.color = in.color;
+    out.color = vec4f(in.color.rgb, in.color.a * node.alpha);
+    out.coverage = in.color.a;
+    out.local = in.position;
     return out;
 }
 
+// the texel with premultiplied alpha, masks are white with the coverage as alpha
+fn texel(uv: vec2f) -> vec4f {
+    let texel = textureSample(graphics_texture, graphics_sampler, uv);
+    switch texture_info.kind {
+        case TEXTURE_MASK: {
+            return vec4f(texel.r);
+        }
+        case TEXTURE_STRAIGHT: {
+            return vec4f(texel.rgb * texel.a, texel.a);
+        }
+        default: {
+            return texel;
+        }
+    }
+}
+
+// outputs premultiplied colors, see BlendMode
 @fragment fn fs_main(in: VertexOut)-> @location(0) vec4f {
-    return in.color;
+    return paint_color(in.local) * texel(in.uv) * vec4f(in.color.rgb * in.color.a, in.color.a);
+}
+
+// draws into the stencil only, antialiased edges are cut at half coverage.
+// the alpha of the node is ignored so faded nodes still mask
+@fragment fn fs_mask(in: VertexOut) {
+    if in.coverage * texel(in.uv).a <= 0.5 {
+        discard;
+    }
 }
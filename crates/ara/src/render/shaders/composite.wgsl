@@ -0,0 +1,83 @@
+// Draws a layer into its parent target.
+// Layers hold premultiplied colors and are the same size as the target so texels are loaded by position
+
+struct Composite {
+    alpha: f32,
+    // index of the blend function, see BlendMode::shader_index
+    mode: u32,
+    _pad: vec2f, // webgl requirement
+};
+
+@group(0) @binding(0) var<uniform> composite: Composite;
+@group(0) @binding(1) var source: texture_2d<f32>;
+@group(0) @binding(2) var backdrop: texture_2d<f32>;
+
+// a triangle covering the whole target
+@vertex fn vs(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// blended by the fixed function blend state of the pipeline
+@fragment fn fs_copy(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    return textureLoad(source, vec2i(position.xy), 0) * composite.alpha;
+}
+
+fn unpremultiply(color: vec4f) -> vec3f {
+    if color.a <= 0.0 {
+        return vec3f(0.0);
+    }
+    return color.rgb / color.a;
+}
+
+fn hard_light(cb: vec3f, cs: vec3f) -> vec3f {
+    let multiply = cb * 2.0 * cs;
+    let screen = 1.0 - (1.0 - cb) * (1.0 - (2.0 * cs - 1.0));
+    return select(screen, multiply, cs <= vec3f(0.5));
+}
+
+fn color_dodge(cb: vec3f, cs: vec3f) -> vec3f {
+    let dodged = min(vec3f(1.0), cb / max(1.0 - cs, vec3f(1e-6)));
+    return select(select(dodged, vec3f(1.0), cs >= vec3f(1.0)), vec3f(0.0), cb <= vec3f(0.0));
+}
+
+fn color_burn(cb: vec3f, cs: vec3f) -> vec3f {
+    let burned = 1.0 - min(vec3f(1.0), (1.0 - cb) / max(cs, vec3f(1e-6)));
+    return select(select(burned, vec3f(0.0), cs <= vec3f(0.0)), vec3f(1.0), cb >= vec3f(1.0));
+}
+
+fn soft_light(cb: vec3f, cs: vec3f) -> vec3f {
+    let d = select(sqrt(cb), ((16.0 * cb - 12.0) * cb + 4.0) * cb, cb <= vec3f(0.25));
+    let darker = cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb);
+    let lighter = cb + (2.0 * cs - 1.0) * (d - cb);
+    return select(lighter, darker, cs <= vec3f(0.5));
+}
+
+// separable blend functions of the W3C compositing spec
+fn blend(cb: vec3f, cs: vec3f) -> vec3f {
+    var result = cs;
+    switch composite.mode {
+        case 1u: { result = min(cb, cs); }
+        case 2u: { result = max(cb, cs); }
+        case 3u: { result = hard_light(cs, cb); }
+        case 4u: { result = color_dodge(cb, cs); }
+        case 5u: { result = color_burn(cb, cs); }
+        case 6u: { result = hard_light(cb, cs); }
+        case 7u: { result = soft_light(cb, cs); }
+        case 8u: { result = abs(cb - cs); }
+        case 9u: { result = cb + cs - 2.0 * cb * cs; }
+        default: {}
+    }
+    return result;
+}
+
+// the result replaces the target, it already contains the backdrop
+@fragment fn fs_blend(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    let texel = vec2i(position.xy);
+    let src = textureLoad(source, texel, 0) * composite.alpha;
+    let dst = textureLoad(backdrop, texel, 0);
+
+    let mixed = blend(unpremultiply(dst), unpremultiply(src));
+    let color = src.rgb * (1.0 - dst.a) + dst.rgb * (1.0 - src.a) + src.a * dst.a * mixed;
+    return vec4f(color, src.a + dst.a * (1.0 - src.a));
+}
@@ -0,0 +1,92 @@
+// Gradient and pattern paints, see PaintUniform.
+// Included before the shaders using it, which pass the untransformed vertex position to paint_color
+
+struct Paint {
+    // maps a vertex position into the space of the paint
+    transform: mat3x3f,
+    // 0 solid, 1 linear, 2 radial, 3 conic, 4 pattern
+    kind: u32,
+    // 0 pad, 1 repeat, 2 reflect
+    spread: u32,
+    stop_count: u32,
+    _pad: u32,
+    // linear: start and end, radial: center and focal, conic: center
+    points: vec4f,
+    // radial: radius, conic: start angle, pattern: size of the image
+    params: vec4f,
+    offsets: array<vec4f, 4>,
+    // premultiplied
+    colors: array<vec4f, 16>,
+};
+
+@group(2) @binding(0) var<uniform> paint: Paint;
+// image of a pattern with premultiplied alpha, white otherwise
+@group(2) @binding(1) var paint_texture: texture_2d<f32>;
+@group(2) @binding(2) var paint_sampler: sampler;
+
+const TAU: f32 = 6.28318530718;
+
+fn gradient_offset(p: vec2f) -> f32 {
+    switch paint.kind {
+        case 1u: {
+            let dir = paint.points.zw - paint.points.xy;
+            return dot(p - paint.points.xy, dir) / max(dot(dir, dir), 1e-6);
+        }
+        case 2u: {
+            let d = paint.points.xy - paint.points.zw;
+            let q = p - paint.points.zw;
+            let r = paint.params.x;
+            let a = dot(d, d) - r * r;
+            let b = dot(q, d);
+            let c = dot(q, q);
+            return (b - sqrt(max(b * b - a * c, 0.0))) / a;
+        }
+        default: {
+            let q = p - paint.points.xy;
+            let angle = atan2(q.y, q.x) - paint.params.x;
+            return fract(angle / TAU);
+        }
+    }
+}
+
+fn spread_offset(t: f32) -> f32 {
+    switch paint.spread {
+        case 1u: { return fract(t); }
+        case 2u: { return 1.0 - abs(fract(t * 0.5) * 2.0 - 1.0); }
+        default: { return clamp(t, 0.0, 1.0); }
+    }
+}
+
+fn stop_offset(i: u32) -> f32 {
+    return paint.offsets[i / 4u][i % 4u];
+}
+
+// premultiplied color of the paint at a vertex position, white for solid colors
+fn paint_color(position: vec2f) -> vec4f {
+    if paint.kind == 0u {
+        return vec4f(1.0);
+    }
+
+    let p = (paint.transform * vec3f(position, 1.0)).xy;
+    if paint.kind == 4u {
+        return textureSampleLevel(paint_texture, paint_sampler, p / paint.params.xy, 0.0);
+    }
+
+    let t = spread_offset(gradient_offset(p));
+
+    var color = paint.colors[0];
+    for (var i = 1u; i < paint.stop_count; i++) {
+        let start = stop_offset(i - 1u);
+        let end = stop_offset(i);
+        if t >= end {
+            color = paint.colors[i];
+            continue;
+        }
+        if t > start {
+            color = mix(paint.colors[i - 1u], paint.colors[i], (t - start) / max(end - start, 1e-6));
+        }
+        break;
+    }
+    return color;
+}
+
@@ -0,0 +1,70 @@
+struct Globals {
+    viewport_size: vec2f,
+    _pad: vec2f, // webgl requirement
+};
+
+// how texels are read, see TextureUniform
+struct TextureInfo {
+    kind: u32,
+};
+
+const TEXTURE_MASK: u32 = 0u;
+const TEXTURE_STRAIGHT: u32 = 1u;
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var sprite_texture: texture_2d<f32>;
+@group(1) @binding(1) var sprite_sampler: sampler;
+@group(1) @binding(2) var<uniform> texture_info: TextureInfo;
+
+fn to_device_coordinate(point: vec2f) -> vec2f {
+    return (point / globals.viewport_size) * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0);
+}
+
+struct VertexIn {
+    // sprites are batched in the space of the scene, so there is no node transform
+    @location(0) position: vec2f,
+    @location(1) uv: vec2f,
+    @location(2) color: vec4f,
+};
+
+struct VertexOut {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
+    @location(1) uv: vec2f,
+};
+
+@vertex fn vs(in: VertexIn) -> VertexOut {
+    var out: VertexOut;
+    out.position = vec4f(to_device_coordinate(in.position), 0.0, 1.0);
+    out.uv = in.uv;
+    out.color = in.color;
+    return out;
+}
+
+// the texel with premultiplied alpha, masks are white with the coverage as alpha
+fn texel(uv: vec2f) -> vec4f {
+    let texel = textureSample(sprite_texture, sprite_sampler, uv);
+    switch texture_info.kind {
+        case TEXTURE_MASK: {
+            return vec4f(texel.r);
+        }
+        case TEXTURE_STRAIGHT: {
+            return vec4f(texel.rgb * texel.a, texel.a);
+        }
+        default: {
+            return texel;
+        }
+    }
+}
+
+// outputs premultiplied colors, see BlendMode
+@fragment fn fs_main(in: VertexOut) -> @location(0) vec4f {
+    return texel(in.uv) * vec4f(in.color.rgb * in.color.a, in.color.a);
+}
+
+// draws into the stencil only where the texture is at least half covered
+@fragment fn fs_mask(in: VertexOut) {
+    if texel(in.uv).a <= 0.5 {
+        discard;
+    }
+}
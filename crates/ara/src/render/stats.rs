@@ -0,0 +1,50 @@
+use crate::Subscription;
+
+use super::{systems::System, ItemContext, RenderRunner};
+
+/// Work done by a single `Renderer::render` call
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    /// nodes whose world state was recomputed
+    pub nodes_prepared: usize,
+    /// nodes skipped because nothing changed in their subtree since they were last prepared
+    pub nodes_reused: usize,
+    /// graphics contexts whose geometry was rebuilt
+    pub contexts_rebuilt: usize,
+    /// graphics contexts drawn with the geometry of a previous frame
+    pub contexts_reused: usize,
+    /// draw calls issued for sprites and text, consecutive quads sharing a texture are drawn with one
+    pub sprite_batches: usize,
+    /// text nodes shaped, a text keeps its layout until its content, style or width changes
+    pub texts_shaped: usize,
+    /// offscreen layers composited, consecutive draws with the same advanced blend mode share one
+    pub layers_composited: usize,
+}
+
+/// Collects the `RenderStats` of the current render call
+pub struct RenderStatsSystem {
+    pub(crate) stats: RenderStats,
+    _sub: Option<Subscription>,
+}
+
+impl RenderStatsSystem {
+    pub fn new(cx: &mut ItemContext<Self>) -> Self {
+        let sub = cx.add_runner(RenderRunner::Start, |runner| {
+            runner.update_system(|this: &mut Self, _| this.stats = RenderStats::default());
+            Ok(())
+        });
+
+        Self {
+            stats: RenderStats::default(),
+            _sub: Some(sub),
+        }
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+}
+
+impl System for RenderStatsSystem {
+    fn init(&mut self, _cx: &mut super::RenderContext) {}
+}
@@ -1,6 +1,10 @@
 mod encoder;
 mod geometry;
 mod global_uniform;
+mod glyph;
+mod layer;
+mod pipeline;
+mod texture;
 
 use std::{
     any::{Any, TypeId},
@@ -10,6 +14,10 @@ use std::{
 pub use encoder::*;
 pub use geometry::*;
 pub use global_uniform::*;
+pub use glyph::*;
+pub use layer::*;
+pub use pipeline::*;
+pub use texture::*;
 
 use super::{AnyItem, Item, ItemManager, RenderContext};
 
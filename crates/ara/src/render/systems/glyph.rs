@@ -0,0 +1,85 @@
+use std::{borrow::Cow, sync::Arc};
+
+use ara_math::Size;
+use cosmic_text::{CacheKey, SwashContent};
+
+use crate::{render::RenderContext, AraAtlas, AtlasKey, GlyphImage, IsZero, TextSystem};
+
+use super::System;
+
+/// Fonts of the scene and the atlas their glyphs are rasterized into,
+/// both can be shared with a `Canvas` through `RendererSpecification`
+pub struct GlyphSystem {
+    text_system: Arc<TextSystem>,
+    atlas: Arc<AraAtlas>,
+    glyphs: ahash::HashMap<CacheKey, Option<RasterizedGlyph>>,
+}
+
+/// A glyph in the atlas, the placement is in physical pixels relative to the glyph's origin
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RasterizedGlyph {
+    pub key: AtlasKey,
+    pub left: i32,
+    pub top: i32,
+    pub size: Size<i32>,
+}
+
+impl System for GlyphSystem {
+    fn init(&mut self, _: &mut RenderContext) {}
+}
+
+impl GlyphSystem {
+    pub fn new(text_system: Arc<TextSystem>, atlas: Arc<AraAtlas>) -> Self {
+        Self {
+            text_system,
+            atlas,
+            glyphs: Default::default(),
+        }
+    }
+
+    pub fn text_system(&self) -> &Arc<TextSystem> {
+        &self.text_system
+    }
+
+    pub fn atlas(&self) -> &Arc<AraAtlas> {
+        &self.atlas
+    }
+
+    /// Uploads the glyph to the atlas the first time it is seen,
+    /// none for glyphs without an image like spaces
+    pub(crate) fn rasterize(&mut self, cache_key: CacheKey) -> Option<RasterizedGlyph> {
+        if let Some(glyph) = self.glyphs.get(&cache_key) {
+            return glyph.clone();
+        }
+
+        let glyph = self.text_system.write(|state| {
+            let image = state
+                .swash_cache
+                .get_image(&mut state.font_system, cache_key)
+                .as_ref()?;
+
+            let size = Size::new(image.placement.width as i32, image.placement.height as i32);
+            if size.is_zero() {
+                return None;
+            }
+
+            let key = AtlasKey::from(GlyphImage {
+                key: cache_key,
+                // subpixel masks are not supported and drawn as masks
+                is_emoji: matches!(image.content, SwashContent::Color),
+            });
+            self.atlas
+                .get_or_insert(&key, || (size, Cow::Borrowed(&image.data)));
+
+            Some(RasterizedGlyph {
+                key,
+                left: image.placement.left,
+                top: image.placement.top,
+                size,
+            })
+        });
+
+        self.glyphs.insert(cache_key, glyph.clone());
+        glyph
+    }
+}
@@ -0,0 +1,293 @@
+use ara_math::Size;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    render::{
+        render_target::TargetFormat, texture::RenderImage, BlendMode, ItemContext, RenderContext,
+        RenderRunner, StencilMode, STENCIL_FORMAT,
+    },
+    Subscription,
+};
+
+use super::{PipelineKey, PipelineLayoutId, PipelineSystem, ShaderId, System};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeUniform {
+    alpha: f32,
+    mode: u32,
+    _pad: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CompositeKey {
+    source: wgpu::TextureView,
+    backdrop: wgpu::TextureView,
+    blend_mode: BlendMode,
+    alpha: u32,
+}
+
+/// Offscreen textures nodes are drawn into before being composited into their parent target,
+/// used for group effects and blend modes which need to read the backdrop
+pub struct LayerSystem {
+    device: wgpu::Device,
+    bind_group_layout: wgpu::BindGroupLayout,
+    resources: Option<(ShaderId, PipelineLayoutId)>,
+    /// released images and whether they were used during the current frame
+    pool: Vec<(RenderImage, bool)>,
+    /// released stencil attachments, see `PaintContext::push_mask`
+    stencils: Vec<(wgpu::Texture, bool)>,
+    /// composite bind groups and whether they were used during the current frame,
+    /// layers are pooled so the same ones are composited every frame
+    bind_groups: ahash::HashMap<CompositeKey, (wgpu::BindGroup, bool)>,
+    /// uniforms of the bind groups by blend mode and alpha
+    uniforms: ahash::HashMap<(BlendMode, u32), (wgpu::Buffer, bool)>,
+    _sub: Subscription,
+}
+
+impl System for LayerSystem {
+    fn init(&mut self, cx: &mut RenderContext) {
+        let layout = cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.add_layout("Composite Pipeline Layout", &[&self.bind_group_layout])
+        });
+        let shader = cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.add_shader(
+                "Composite Shader",
+                include_str!("../shaders/composite.wgsl"),
+            )
+        });
+        self.resources = Some((shader, layout));
+    }
+}
+
+impl LayerSystem {
+    pub fn new(cx: &mut ItemContext<Self>) -> Self {
+        let sub = cx.add_runner(RenderRunner::Finish, |runner| {
+            runner.update_system(|this: &mut Self, _| this.trim());
+            Ok(())
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let bind_group_layout =
+            cx.gpu
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("ara_render::LayerSystem::BindGroupLayout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        texture_entry(1),
+                        texture_entry(2),
+                    ],
+                });
+
+        Self {
+            device: cx.gpu.device.clone(),
+            bind_group_layout,
+            resources: None,
+            pool: Default::default(),
+            stencils: Default::default(),
+            bind_groups: Default::default(),
+            uniforms: Default::default(),
+            _sub: sub,
+        }
+    }
+
+    /// Returns a texture matching the target, its contents are undefined
+    pub fn acquire(&mut self, size: Size<u32>, target: TargetFormat) -> RenderImage {
+        if let Some(index) = self
+            .pool
+            .iter()
+            .position(|(image, _)| image.size() == size && image.target_format() == target)
+        {
+            return self.pool.swap_remove(index).0;
+        }
+
+        log::debug!(
+            "Creating layer {}x{} {:?} x{}",
+            size.width,
+            size.height,
+            target.format,
+            target.sample_count
+        );
+
+        RenderImage::with_target(
+            &self.device,
+            size,
+            target,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        )
+    }
+
+    /// Returns the texture to the pool, it can be reused by commands recorded afterwards
+    pub fn release(&mut self, image: RenderImage) {
+        self.pool.push((image, true));
+    }
+
+    /// Returns a stencil attachment for targets of the size and sample count,
+    /// its contents are undefined
+    pub fn acquire_stencil(&mut self, size: Size<u32>, sample_count: u32) -> wgpu::Texture {
+        if let Some(index) = self.stencils.iter().position(|(texture, _)| {
+            texture.width() == size.width
+                && texture.height() == size.height
+                && texture.sample_count() == sample_count
+        }) {
+            return self.stencils.swap_remove(index).0;
+        }
+
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("ara_render::LayerSystem::Stencil"),
+            size: wgpu::Extent3d {
+                width: size.width.max(1),
+                height: size.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: STENCIL_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+    }
+
+    pub fn release_stencil(&mut self, stencil: wgpu::Texture) {
+        self.stencils.push((stencil, true));
+    }
+
+    /// Number of textures waiting to be reused
+    pub fn pooled(&self) -> usize {
+        self.pool.len() + self.stencils.len()
+    }
+
+    /// Drops textures and bind groups which were not used during the frame
+    fn trim(&mut self) {
+        self.bind_groups.retain(|_, (_, used)| *used);
+        for (_, used) in self.bind_groups.values_mut() {
+            *used = false;
+        }
+
+        self.uniforms.retain(|_, (_, used)| *used);
+        for (_, used) in self.uniforms.values_mut() {
+            *used = false;
+        }
+
+        self.pool.retain(|(_, used)| *used);
+        for (_, used) in &mut self.pool {
+            *used = false;
+        }
+
+        self.stencils.retain(|(_, used)| *used);
+        for (_, used) in &mut self.stencils {
+            *used = false;
+        }
+    }
+
+    pub(crate) fn composite_key(
+        &self,
+        target: TargetFormat,
+        blend_mode: BlendMode,
+    ) -> Option<PipelineKey> {
+        let (shader, layout) = self.resources?;
+        let blend = blend_mode.blend_state();
+
+        Some(PipelineKey {
+            label: "Composite Pipeline",
+            shader,
+            layout,
+            vertex_entry: "vs",
+            // without a fixed function blend state the shader blends with the backdrop
+            fragment_entry: if blend.is_some() {
+                "fs_copy"
+            } else {
+                "fs_blend"
+            },
+            vertex_layouts: &[],
+            target,
+            blend,
+            color_writes: wgpu::ColorWrites::ALL,
+            // layers are clipped by the masks of their parent
+            depth_stencil: Some(StencilMode::Test.depth_stencil_state()),
+        })
+    }
+
+    pub(crate) fn composite_bind_group(
+        &mut self,
+        source: &wgpu::TextureView,
+        backdrop: &wgpu::TextureView,
+        blend_mode: BlendMode,
+        alpha: f32,
+    ) -> wgpu::BindGroup {
+        // adding zero turns -0.0 into 0.0
+        let alpha = alpha.clamp(0.0, 1.0) + 0.0;
+        let key = CompositeKey {
+            source: source.clone(),
+            backdrop: backdrop.clone(),
+            blend_mode,
+            alpha: alpha.to_bits(),
+        };
+        if let Some((bind_group, used)) = self.bind_groups.get_mut(&key) {
+            *used = true;
+            return bind_group.clone();
+        }
+
+        let device = &self.device;
+        let (buffer, used) = self
+            .uniforms
+            .entry((blend_mode, key.alpha))
+            .or_insert_with(|| {
+                let uniform = CompositeUniform {
+                    alpha,
+                    mode: blend_mode.shader_index(),
+                    _pad: [0.0; 2],
+                };
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("ara_render::LayerSystem::Composite"),
+                    contents: bytemuck::bytes_of(&uniform),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+                (buffer, true)
+            });
+        *used = true;
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ara_render::LayerSystem::BindGroup"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(backdrop),
+                },
+            ],
+        });
+
+        self.bind_groups.insert(key, (bind_group.clone(), true));
+        bind_group
+    }
+}
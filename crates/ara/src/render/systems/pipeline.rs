@@ -0,0 +1,194 @@
+use crate::render::{render_target::TargetFormat, ItemContext, RenderContext};
+
+use super::System;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PipelineLayoutId(usize);
+
+/// Everything a render pipeline is created from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub label: &'static str,
+    pub shader: ShaderId,
+    pub layout: PipelineLayoutId,
+    pub vertex_entry: &'static str,
+    pub fragment_entry: &'static str,
+    pub vertex_layouts: &'static [wgpu::VertexBufferLayout<'static>],
+    pub target: TargetFormat,
+    pub blend: Option<wgpu::BlendState>,
+    pub color_writes: wgpu::ColorWrites,
+    pub depth_stencil: Option<wgpu::DepthStencilState>,
+}
+
+/// Creates render pipelines on demand and caches them,
+/// so pipes can draw into targets of any format and sample count
+pub struct PipelineSystem {
+    device: wgpu::Device,
+    shaders: Vec<CachedShader>,
+    layouts: Vec<CachedLayout>,
+    pipelines: ahash::HashMap<PipelineKey, wgpu::RenderPipeline>,
+}
+
+struct CachedShader {
+    label: &'static str,
+    source: &'static str,
+    module: wgpu::ShaderModule,
+}
+
+struct CachedLayout {
+    label: &'static str,
+    bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+    layout: wgpu::PipelineLayout,
+}
+
+impl System for PipelineSystem {
+    fn init(&mut self, _cx: &mut RenderContext) {}
+}
+
+impl PipelineSystem {
+    pub fn new(cx: &mut ItemContext<Self>) -> Self {
+        Self {
+            device: cx.gpu.device.clone(),
+            shaders: Default::default(),
+            layouts: Default::default(),
+            pipelines: Default::default(),
+        }
+    }
+
+    /// Compiles a wgsl shader, shaders are identified by their label and source
+    /// and only compiled once
+    pub fn add_shader(&mut self, label: &'static str, source: &'static str) -> ShaderId {
+        if let Some(index) = self
+            .shaders
+            .iter()
+            .position(|shader| shader.label == label && shader.source == source)
+        {
+            return ShaderId(index);
+        }
+
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+
+        self.shaders.push(CachedShader {
+            label,
+            source,
+            module,
+        });
+        ShaderId(self.shaders.len() - 1)
+    }
+
+    /// Creates a pipeline layout, layouts are identified by their label and bind group layouts
+    /// and only created once
+    pub fn add_layout(
+        &mut self,
+        label: &'static str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> PipelineLayoutId {
+        if let Some(index) = self.layouts.iter().position(|layout| {
+            layout.label == label
+                && layout
+                    .bind_group_layouts
+                    .iter()
+                    .eq(bind_group_layouts.iter().copied())
+        }) {
+            return PipelineLayoutId(index);
+        }
+
+        let layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+
+        self.layouts.push(CachedLayout {
+            label,
+            bind_group_layouts: bind_group_layouts
+                .iter()
+                .map(|&layout| layout.clone())
+                .collect(),
+            layout,
+        });
+        PipelineLayoutId(self.layouts.len() - 1)
+    }
+
+    pub fn get(&self, key: &PipelineKey) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(key)
+    }
+
+    pub fn get_or_create(&mut self, key: &PipelineKey) -> &wgpu::RenderPipeline {
+        if !self.pipelines.contains_key(key) {
+            let pipeline = self.create_pipeline(key);
+            self.pipelines.insert(key.clone(), pipeline);
+        }
+        &self.pipelines[key]
+    }
+
+    /// Number of pipelines created so far
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    fn create_pipeline(&self, key: &PipelineKey) -> wgpu::RenderPipeline {
+        let shader = &self.shaders[key.shader.0].module;
+        let layout = &self.layouts[key.layout.0].layout;
+
+        log::debug!(
+            "Creating pipeline {} for {:?} x{}",
+            key.label,
+            key.target.format,
+            key.target.sample_count
+        );
+
+        self.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(key.label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: Some(key.vertex_entry),
+                    buffers: key.vertex_layouts,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: Some(key.fragment_entry),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: key.target.format,
+                        blend: key.blend,
+                        write_mask: key.color_writes,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::default(),
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: key.depth_stencil.clone(),
+                multisample: wgpu::MultisampleState {
+                    count: key.target.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            })
+    }
+}
@@ -0,0 +1,285 @@
+use std::sync::{Arc, Weak};
+
+use ara_math::{Mat3, Rect, Size};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    gpu,
+    paint::{AtlasTextureId, PaintBindings, PaintShader, PatternTextures},
+    render::{ItemContext, RenderContext, RenderRunner},
+    AraAtlas, AtlasKey, AtlasKeySource, Image, ImageId, Subscription, TextureKind,
+};
+
+use super::System;
+
+/// Textures sampled while drawing, uploaded the first time they are used
+pub struct TextureSystem {
+    gpu: gpu::Context,
+    paints: PaintBindings,
+    patterns: PatternTextures,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    white: wgpu::BindGroup,
+    textures: ahash::HashMap<TextureKey, (TextureOwner, wgpu::BindGroup)>,
+    _sub: Subscription,
+}
+
+/// Identifies a texture bound for drawing, draws with the same key can be merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum TextureKey {
+    Image(ImageId),
+    /// a page of the atlas at the address
+    Atlas(usize, AtlasTextureId),
+}
+
+/// Keeps a bind group alive while what it was created from exists
+enum TextureOwner {
+    Image(Weak<[u8]>),
+    Atlas(Weak<AraAtlas>),
+}
+
+impl TextureOwner {
+    fn is_alive(&self) -> bool {
+        match self {
+            TextureOwner::Image(pixels) => pixels.strong_count() > 0,
+            TextureOwner::Atlas(atlas) => atlas.strong_count() > 0,
+        }
+    }
+}
+
+/// A texture ready to be drawn with and the area of it an image covers
+#[derive(Debug, Clone)]
+pub(crate) struct BoundTexture {
+    pub key: TextureKey,
+    pub bind_group: wgpu::BindGroup,
+    /// area of the image in uv space
+    pub uv: Rect<f32>,
+    /// size of the image in pixels
+    pub size: Size<f32>,
+}
+
+/// How the shaders read the texels of a texture, see `render/shaders/sprite.wgsl`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextureUniform {
+    kind: u32,
+    _pad: [u32; 3],
+}
+
+impl TextureUniform {
+    /// coverage in the red channel
+    const MASK: u32 = 0;
+    const STRAIGHT: u32 = 1;
+    const PREMULTIPLIED: u32 = 2;
+
+    fn new(kind: u32) -> Self {
+        Self { kind, _pad: [0; 3] }
+    }
+}
+
+impl System for TextureSystem {
+    fn init(&mut self, _: &mut RenderContext) {}
+}
+
+impl TextureSystem {
+    pub fn new(cx: &mut ItemContext<Self>) -> Self {
+        let sub = cx.add_runner(RenderRunner::Finish, |runner| {
+            runner.update_system(|this: &mut Self, _| this.trim());
+            Ok(())
+        });
+
+        let device = &cx.gpu.device;
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ara::Texture::BindGroupLayout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ara::Texture::Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let paints = PaintBindings::new(&cx.gpu);
+        let white = create_texture_bind_group(
+            device,
+            &texture_layout,
+            &sampler,
+            paints.white(),
+            TextureUniform::PREMULTIPLIED,
+        );
+
+        Self {
+            gpu: cx.gpu.clone(),
+            paints,
+            patterns: Default::default(),
+            texture_layout,
+            sampler,
+            white,
+            textures: Default::default(),
+            _sub: sub,
+        }
+    }
+
+    /// Layout of the bind group paints are drawn with, see `render/shaders/paint.wgsl`
+    pub fn paint_layout(&self) -> &wgpu::BindGroupLayout {
+        self.paints.layout()
+    }
+
+    /// Paint bind group of solid colors
+    pub fn solid_paint(&self) -> &wgpu::BindGroup {
+        self.paints.solid()
+    }
+
+    /// Paint bind group drawing `shader` on geometry tessellated with `transform`
+    pub fn paint(&mut self, shader: &PaintShader, transform: &Mat3) -> wgpu::BindGroup {
+        self.patterns
+            .bind_group(&self.gpu, &self.paints, shader, transform)
+    }
+
+    /// Layout of the bind group textures are drawn with, see `render/shaders/sprite.wgsl`
+    pub fn texture_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_layout
+    }
+
+    /// Texture bind group of draws which are not textured, every texel is white
+    pub fn white_texture(&self) -> &wgpu::BindGroup {
+        &self.white
+    }
+
+    /// Binds an image, it is uploaded with premultiplied alpha
+    pub(crate) fn image(&mut self, image: &Image) -> BoundTexture {
+        let key = TextureKey::Image(image.id());
+        let bind_group = match self.textures.get(&key) {
+            Some((_, bind_group)) => bind_group.clone(),
+            None => {
+                let view = self.patterns.image(&self.gpu, image);
+                let bind_group = self.create_bind_group(&view, TextureUniform::PREMULTIPLIED);
+                let owner = TextureOwner::Image(image.downgrade());
+                self.textures.insert(key, (owner, bind_group.clone()));
+                bind_group
+            }
+        };
+
+        let size = image.size();
+        BoundTexture {
+            key,
+            bind_group,
+            uv: Rect::xywh(0.0, 0.0, 1.0, 1.0),
+            size: Size::new(size.width as f32, size.height as f32),
+        }
+    }
+
+    /// Binds the atlas page holding the key, none if the key is not in the atlas.
+    /// Color pages hold straight alpha
+    pub(crate) fn atlas_tile(
+        &mut self,
+        atlas: &Arc<AraAtlas>,
+        atlas_key: &AtlasKey,
+    ) -> Option<BoundTexture> {
+        let info = atlas.get_texture_info(atlas_key)?;
+        let key = TextureKey::Atlas(Arc::as_ptr(atlas) as usize, info.tile.texture);
+
+        let bind_group = match self.textures.get(&key) {
+            Some((_, bind_group)) => bind_group.clone(),
+            None => {
+                let view = atlas.get_texture_for_tile(&info.tile, |page| page.view().clone())?;
+                let kind = match atlas_key.texture_kind() {
+                    TextureKind::Mask => TextureUniform::MASK,
+                    TextureKind::Color => TextureUniform::STRAIGHT,
+                };
+                let bind_group = self.create_bind_group(&view, kind);
+                let owner = TextureOwner::Atlas(Arc::downgrade(atlas));
+                self.textures.insert(key, (owner, bind_group.clone()));
+                bind_group
+            }
+        };
+
+        let min = info.uv_to_atlas_space(0.0, 0.0);
+        let max = info.uv_to_atlas_space(1.0, 1.0);
+        let size = info.tile.bounds.size;
+        Some(BoundTexture {
+            key,
+            bind_group,
+            uv: Rect::xywh(min.x, min.y, max.x - min.x, max.y - min.y),
+            size: Size::new(size.width as f32, size.height as f32),
+        })
+    }
+
+    fn create_bind_group(&self, view: &wgpu::TextureView, kind: u32) -> wgpu::BindGroup {
+        create_texture_bind_group(
+            &self.gpu.device,
+            &self.texture_layout,
+            &self.sampler,
+            view,
+            kind,
+        )
+    }
+
+    /// Drops the uploads and bind groups of images and atlases which no longer exist
+    fn trim(&mut self) {
+        self.patterns.trim();
+        self.textures.retain(|_, (owner, _)| owner.is_alive());
+    }
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    view: &wgpu::TextureView,
+    kind: u32,
+) -> wgpu::BindGroup {
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("ara::Texture::Uniform"),
+        contents: bytemuck::bytes_of(&TextureUniform::new(kind)),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("ara::Texture::BindGroup"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
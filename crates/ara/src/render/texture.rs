@@ -2,3 +2,5 @@ pub mod source;
 pub use source::*;
 pub mod image;
 pub use image::*;
+pub mod multisample;
+pub use multisample::*;
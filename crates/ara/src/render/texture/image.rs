@@ -1,21 +1,138 @@
 use ara_math::Size;
+use derive_more::derive::Deref;
 
-use crate::render::ItemManager;
+use crate::{
+    gpu,
+    render::{Item, ItemManager},
+};
 
-use super::RenderTexture;
+use crate::render::render_target::TargetFormat;
 
-pub struct Image {
-    pub data: Option<Vec<u8>>,
-}
+use super::{sample_count_for, MultisampleTexture, RenderTexture, TextureSource};
+
+#[derive(Deref, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ImageHandle(pub(crate) Item<RenderImage>);
 
-impl Image {}
+impl RenderTexture for ImageHandle {
+    fn resize(&self, cx: &mut impl ItemManager, physical_size: Size<u32>) {
+        let _ = self.update(cx, |image, cx| image.resize(&cx.gpu.device, physical_size));
+    }
+}
 
-pub struct ImageHandle(pub usize);
 // a image which can be used as a render target
+// the texture is created with COPY_SRC so its contents can be read back after rendering
+// when antialiasing, rendering happens in a multisampled texture which is resolved into this one
+#[derive(Debug)]
 pub struct RenderImage {
-    pub handle: ImageHandle,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    msaa: Option<MultisampleTexture>,
 }
 
-impl RenderTexture for RenderImage {
-    fn resize(&self, _cx: &mut impl ItemManager, _physical_size: Size<u32>) {}
+impl RenderImage {
+    pub fn new(gpu: &gpu::Context, texture_source: &TextureSource<()>) -> Self {
+        let size = texture_source.pixel_size();
+        let format = texture_source.format();
+        let (texture, view) =
+            Self::create_texture(&gpu.device, size, format, texture_source.usage());
+
+        let sample_count = sample_count_for(gpu, format, texture_source.antialias());
+        let msaa = MultisampleTexture::new(&gpu.device, size, format, sample_count);
+
+        Self {
+            texture,
+            view,
+            msaa,
+        }
+    }
+
+    /// Creates an image matching the format and sample count of a render target
+    pub(crate) fn with_target(
+        device: &wgpu::Device,
+        size: Size<u32>,
+        target: TargetFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        let (texture, view) = Self::create_texture(device, size, target.format, usage);
+        let msaa = MultisampleTexture::new(device, size, target.format, target.sample_count);
+
+        Self {
+            texture,
+            view,
+            msaa,
+        }
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        size: Size<u32>,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                label: Some("ara_render::RenderImage"),
+                size: wgpu::Extent3d {
+                    width: size.width,
+                    height: size.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::COPY_SRC
+                    | usage,
+                view_formats: &[],
+            }),
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn size(&self) -> Size<u32> {
+        Size::new(self.texture.width(), self.texture.height())
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.texture.format()
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.msaa.as_ref().map_or(1, |msaa| msaa.sample_count())
+    }
+
+    pub fn target_format(&self) -> TargetFormat {
+        TargetFormat {
+            format: self.format(),
+            sample_count: self.sample_count(),
+        }
+    }
+
+    pub(crate) fn msaa(&self) -> Option<&MultisampleTexture> {
+        self.msaa.as_ref()
+    }
+
+    /// Recreates the texture, the previous contents are discarded
+    pub fn resize(&mut self, device: &wgpu::Device, size: Size<u32>) {
+        if self.size() == size {
+            return;
+        }
+
+        let (texture, view) =
+            Self::create_texture(device, size, self.texture.format(), self.texture.usage());
+        self.msaa = MultisampleTexture::new(device, size, texture.format(), self.sample_count());
+        self.texture = texture;
+        self.view = view;
+    }
 }
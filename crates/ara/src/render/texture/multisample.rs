@@ -0,0 +1,70 @@
+use ara_math::Size;
+
+use crate::gpu;
+
+/// Sample count of the color attachment when antialiasing is enabled
+pub const MSAA_SAMPLE_COUNT: u32 = 4;
+
+/// The sample count to render into a texture of `format` with.
+/// Falls back to 1 when the adapter can't multisample the format
+pub fn sample_count_for(gpu: &gpu::Context, format: wgpu::TextureFormat, antialias: bool) -> u32 {
+    if !antialias {
+        return 1;
+    }
+
+    let features = gpu.adapter.get_texture_format_features(format);
+    if features.flags.sample_count_supported(MSAA_SAMPLE_COUNT) {
+        MSAA_SAMPLE_COUNT
+    } else {
+        log::warn!("{format:?} does not support {MSAA_SAMPLE_COUNT}x multisampling, antialiasing is disabled");
+        1
+    }
+}
+
+/// Multisampled color attachment, resolved into the target texture at the end of the pass
+#[derive(Debug)]
+pub struct MultisampleTexture {
+    view: wgpu::TextureView,
+    sample_count: u32,
+}
+
+impl MultisampleTexture {
+    /// Returns `None` if `sample_count` is 1
+    pub fn new(
+        device: &wgpu::Device,
+        size: Size<u32>,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Option<Self> {
+        (sample_count > 1).then(|| {
+            let view = device
+                .create_texture(
+                    &(wgpu::TextureDescriptor {
+                        label: Some("ara_render::MultisampleTexture"),
+                        size: wgpu::Extent3d {
+                            width: size.width,
+                            height: size.height,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                        view_formats: &[],
+                    }),
+                )
+                .create_view(&wgpu::TextureViewDescriptor::default());
+
+            Self { view, sample_count }
+        })
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+}
@@ -27,8 +27,9 @@ impl Default for ViewConfig {
 #[derive(Default)]
 pub enum ViewTarget {
     Surface(wgpu::SurfaceTarget<'static>),
-    // todo
-    // Image(ImageHandle),
+    /// Renders offscreen into a texture owned by the view,
+    /// the texture is created with COPY_SRC so the result can be copied out
+    Texture,
     #[default]
     Empty,
 }
@@ -4,7 +4,7 @@ use derive_more::derive::{Deref, DerefMut};
 use crate::render::{
     render_target::{BackendRenderTarget, BackendRenderTargetHandle, RenderTarget},
     systems::System,
-    texture::{TextureSource, TextureSourceDescriptor},
+    texture::{ImageHandle, RenderImage, TextureSource, TextureSourceDescriptor},
     ItemContext, ItemManager, RenderContext, WithRenderContext,
 };
 
@@ -51,6 +51,14 @@ impl ViewSystem {
                 ViewSource(source.replace(RenderTarget::from(handle)))
             }
 
+            ViewTarget::Texture => {
+                let item = cx.new_item(|cx| RenderImage::new(&cx.gpu, &source));
+
+                let handle = ImageHandle(item);
+
+                ViewSource(source.replace(RenderTarget::from(handle)))
+            }
+
             ViewTarget::Empty => ViewSource(source.replace(RenderTarget::Noop)),
         }
     }
@@ -64,6 +72,11 @@ impl ViewSystem {
         &self.view
     }
 
+    /// The texture of the view when rendering offscreen with `ViewTarget::Texture`
+    pub fn texture(&self, cx: &RenderContext) -> Option<wgpu::Texture> {
+        self.view.source().get_texture(cx)
+    }
+
     pub fn replace_view(
         &mut self,
         cx: &mut ItemContext<Self>,
@@ -138,6 +151,12 @@ pub trait ViewSystemExt: WithRenderContext {
         self.view_system(|view, _| view.view().pixel_height())
     }
 
+    /// get the texture of the primary screen when it renders offscreen
+    #[inline(always)]
+    fn view_texture(&self) -> Option<wgpu::Texture> {
+        self.view_system(|view, cx| view.texture(cx))
+    }
+
     /// get the size of the primary screen
     #[inline(always)]
     fn screen_size(&self) -> Size<u32> {
@@ -1,8 +1,10 @@
 use std::{borrow::Cow, cell::Cell, num::NonZeroU64, ops::Range};
 
 use crate::{
-    gpu::CommandEncoder, paint::Vertex, AraAtlas, AtlasKey, AtlasKeySource, Context,
-    GpuTextureView, Mat3, Mesh, Rect, Size, TextureAtlas, TextureId, TextureKind, TextureOptions,
+    gpu::CommandEncoder,
+    paint::{PaintBindings, PatternTextures, Vertex},
+    AraAtlas, AtlasKey, AtlasKeySource, Context, GpuTextureView, Mat3, Mesh, PaintShader, Rect,
+    Size, TextureAtlas, TextureId, TextureKind, TextureOptions,
 };
 
 use wgpu::util::DeviceExt;
@@ -14,6 +16,8 @@ static INITIAL_INDEX_BUFFER_SIZE: u64 = (std::mem::size_of::<u32>() * 1024 * 3)
 pub struct Renderable {
     pub clip_rect: Rect<f32>,
     pub mesh: Mesh,
+    /// shader of the mesh and the transform it was tessellated with
+    pub shader: Option<(PaintShader, Mat3)>,
 }
 
 #[derive(Default, Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
@@ -133,6 +137,13 @@ pub struct Renderer2D {
     index_buffer: BatchBuffer,
 
     texture_bindgroup_layout: wgpu::BindGroupLayout,
+
+    paint_bindings: PaintBindings,
+
+    pattern_textures: PatternTextures,
+
+    /// paint of each prepared renderable
+    paints: Vec<wgpu::BindGroup>,
 }
 
 fn get_proj_matrix(width: u32, height: u32) -> Mat3 {
@@ -172,12 +183,15 @@ impl Renderer2D {
             }),
         );
 
+        let paint_bindings = PaintBindings::new(&gpu);
+
         let scene_pipe = GeometryPipes::new(
             &gpu,
             specs.msaa_sample_count,
             &[
                 &global_uniforms.bing_group_layout,
                 &texture_bindgroup_layout,
+                paint_bindings.layout(),
             ],
         );
 
@@ -201,6 +215,9 @@ impl Renderer2D {
             vertex_buffer,
             index_buffer,
             texture_bindgroup_layout,
+            paint_bindings,
+            pattern_textures: Default::default(),
+            paints: Vec::new(),
             size: Size {
                 width: specs.width,
                 height: specs.height,
@@ -343,6 +360,21 @@ impl Renderer2D {
             return;
         }
 
+        self.paints.clear();
+        for renderable in renderables {
+            let paint = match &renderable.shader {
+                Some((shader, transform)) => self.pattern_textures.bind_group(
+                    &self.gpu,
+                    &self.paint_bindings,
+                    shader,
+                    transform,
+                ),
+                None => self.paint_bindings.solid().clone(),
+            };
+            self.paints.push(paint);
+        }
+        self.pattern_textures.trim();
+
         let (vertex_count, index_count): (usize, usize) =
             renderables.iter().fold((0, 0), |res, renderable| {
                 (
@@ -431,6 +463,7 @@ impl Renderer2D {
 
         let mut vb_slices = self.vertex_buffer.slices.iter();
         let mut ib_slices = self.index_buffer.slices.iter();
+        let mut paints = self.paints.iter();
 
         render_pass.set_bind_group(0, &self.global_uniforms.bind_group, &[]);
 
@@ -442,6 +475,7 @@ impl Renderer2D {
             render_pass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
 
             let texture = &renderable.mesh.texture;
+            let paint = paints.next().expect("No next paint");
             if let Some(RendererTexture { bindgroup, kind }) = self.textures.get(texture) {
                 let vb_slice = vb_slices.next().expect("No next vb_slice");
                 let ib_slice = ib_slices.next().expect("No next ib_slice");
@@ -453,6 +487,7 @@ impl Renderer2D {
                 }
 
                 render_pass.set_bind_group(1, bindgroup, &[]);
+                render_pass.set_bind_group(2, paint, &[]);
                 render_pass.set_vertex_buffer(
                     0,
                     self.vertex_buffer
@@ -479,6 +514,7 @@ impl Renderer2D {
     pub fn end(&mut self) {
         self.vertex_buffer.slices.clear();
         self.index_buffer.slices.clear();
+        self.paints.clear();
     }
 }
 
@@ -522,8 +558,13 @@ impl GeometryPipes {
         msaa_sample_count: u32,
         bind_group_layouts: &[&wgpu::BindGroupLayout],
     ) -> Self {
-        let shader =
-            gpu.create_shader_labeled(include_str!("./resources/shader.wgsl"), "Scene Shader");
+        let shader = gpu.create_shader_labeled(
+            concat!(
+                include_str!("./render/shaders/paint.wgsl"),
+                include_str!("./resources/shader.wgsl")
+            ),
+            "Scene Shader",
+        );
 
         let layout = gpu.device.create_pipeline_layout(
             &(wgpu::PipelineLayoutDescriptor {
@@ -555,7 +596,7 @@ impl GeometryPipes {
                 vertex: wgpu::VertexState {
                     module: &shader,
                     entry_point: Some("vs"),
-                    buffers: &[vbo_layout.clone()],
+                    buffers: std::slice::from_ref(&vbo_layout),
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 },
                 fragment: Some(wgpu::FragmentState {
@@ -15,6 +15,7 @@ struct VertexOut {
     @builtin(position) position: vec4f,
     @location(1) uv: vec2f,
     @location(0) color: vec4f,
+    @location(2) local: vec2f,
 };
 
 @vertex fn vs(in: VertexIn) -> VertexOut {
@@ -22,23 +23,33 @@ struct VertexOut {
     out.position = globals.proj * vec4f(in.position, 1.0, 1.0);
     out.uv = in.uv;
     out.color = in.color;
+    out.local = in.position;
     return out;
 }
 
 @group(1) @binding(0) var tex: texture_2d<f32>;
 @group(1) @binding(1) var tex_sampler: sampler;
 
+// the canvas blends colors which are not premultiplied
+fn straight_paint_color(position: vec2f) -> vec4f {
+    let color = paint_color(position);
+    if color.a <= 0.0 {
+        return vec4f(0.0);
+    }
+    return vec4f(color.rgb / color.a, color.a);
+}
+
 @fragment fn fs_poly(in: VertexOut)-> @location(0) vec4f {
     let tex_color = textureSample(tex, tex_sampler, in.uv);
-    return in.color * tex_color;
+    return in.color * tex_color * straight_paint_color(in.local);
 }
 
 // mainly used for rendering text
 @fragment
 fn fs_mono(in: VertexOut) -> @location(0) vec4f {
     let tex_color = textureSample(tex, tex_sampler, in.uv);
-    var color = in.color; 
-    color.a = tex_color.r * in.color.a;
+    var color = in.color * straight_paint_color(in.local);
+    color.a = tex_color.r * color.a;
     return color;
 }
 
@@ -1,11 +1,16 @@
+use ara_math::Mat3;
 use parking_lot::RwLock;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
-use crate::render::renderable::{DisplayObject, View};
+use crate::render::{
+    renderable::{DisplayObject, DisplayObjectMut, View},
+    BlendMode, PaintContext, RenderStatsSystem,
+};
 
 use super::{
-    AnyNode, ChildrenAccessMut, ChildrenStore, IntoSceneNode, ParentNode, RenderRoot, SceneNode,
-    SceneNodeId, SceneNodeIdentifier, SceneNodeLike,
+    reuse_subtree, AnyNode, ChildrenAccessMut, ChildrenStore, DisplayState, IntoSceneNode,
+    NodeState, ParentNode, RenderRoot, SceneNode, SceneNodeId, SceneNodeIdentifier, SceneNodeLike,
+    WeakNode, WorldTransformSystem,
 };
 
 #[derive(Clone)]
@@ -46,8 +51,21 @@ impl ChildrenAccessMut for Container {
 impl Default for Container {
     fn default() -> Self {
         let id = SceneNodeId::new();
-        let inner = Arc::new(RwLock::new(ContainerInner::default()));
-        let node = ContainerNode::new(id, inner);
+        let state = Arc::new(NodeState::default());
+        let weak_state = Arc::downgrade(&state);
+        let inner = Arc::new_cyclic(|weak: &Weak<RwLock<ContainerInner>>| {
+            let weak = weak.clone();
+            let owner = WeakNode::new(id, move || {
+                let node = ContainerNode::new(id, weak.upgrade()?, weak_state.upgrade()?);
+                Some(node.into_any_node())
+            });
+            RwLock::new(ContainerInner {
+                display: Default::default(),
+                children: ChildrenStore::with_owner(owner),
+                mask: None,
+            })
+        });
+        let node = ContainerNode::new(id, inner, state);
         Self { node }
     }
 }
@@ -56,15 +74,69 @@ impl Container {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.node.inner.read().display.blend_mode
+    }
+
+    /// Blends the children as a group with what is behind the container
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.node
+            .update_display(|display| display.blend_mode = blend_mode);
+    }
+
+    pub fn is_isolated(&self) -> bool {
+        self.node.inner.read().display.isolated
+    }
+
+    /// Draws the children into a layer which is composited with the alpha of the container.
+    /// Without it the alpha is applied to each child, so overlapping children show through
+    pub fn set_isolated(&mut self, isolated: bool) {
+        self.node
+            .update_display(|display| display.isolated = isolated);
+    }
+
+    pub fn mask(&self) -> Option<AnyNode> {
+        self.node.inner.read().mask.clone()
+    }
+
+    /// Only draws the children where the mask node draws, in the local space of the container.
+    /// The mask itself is not drawn and should not be added as a child elsewhere,
+    /// bounds and hit testing ignore it
+    pub fn set_mask(&mut self, mask: impl IntoSceneNode) {
+        let mask = mask.into_any_node();
+        let mut inner = self.node.inner.write();
+
+        // changes to the mask have to reach the container
+        if let Some(state) = mask.0.state() {
+            if state.parent().is_none() {
+                state.set_parent(inner.children.owner().cloned());
+            }
+        }
+
+        if let Some(previous) = inner.mask.replace(mask) {
+            release_mask(&previous, self.node.id);
+        }
+        drop(inner);
+        self.node.state.mark_dirty();
+    }
+
+    pub fn clear_mask(&mut self) {
+        let previous = self.node.inner.write().mask.take();
+        if let Some(previous) = previous {
+            release_mask(&previous, self.node.id);
+            self.node.state.mark_dirty();
+        }
+    }
 }
 
 impl View for Container {
     fn bounds(&self) -> ara_math::Rect<f32> {
-        todo!()
+        self.node.bounds(&Mat3::IDENTITY).unwrap_or_default()
     }
 
-    fn contains_point(&self, _point: crate::Point) -> bool {
-        todo!()
+    fn contains_point(&self, point: crate::Point) -> bool {
+        self.node.hit_test(point).is_some()
     }
 }
 
@@ -78,27 +150,74 @@ impl RenderRoot for Container {
 
 impl DisplayObject for Container {
     fn get_position(&self) -> crate::Point {
-        todo!()
+        self.node.inner.read().display.position
     }
 
     fn get_scale(&self) -> crate::Point {
-        todo!()
+        self.node.inner.read().display.scale
     }
 
     fn get_rotation(&self) -> f32 {
-        todo!()
+        self.node.inner.read().display.rotation
+    }
+
+    fn get_pivot(&self) -> crate::Point {
+        self.node.inner.read().display.pivot
     }
 
     fn renderable(&self) -> bool {
-        todo!()
+        self.node.inner.read().display.renderable
     }
 
     fn visible(&self) -> bool {
-        todo!()
+        self.node.inner.read().display.visible
     }
 
     fn alpha(&self) -> f32 {
-        todo!()
+        self.node.inner.read().display.alpha
+    }
+
+    fn get_z_index(&self) -> i32 {
+        self.node.inner.read().display.z_index
+    }
+}
+
+impl DisplayObjectMut for Container {
+    fn set_position(&mut self, position: crate::Point) {
+        self.node
+            .update_display(|display| display.position = position);
+    }
+
+    fn set_scale(&mut self, scale: crate::Point) {
+        self.node.update_display(|display| display.scale = scale);
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.node
+            .update_display(|display| display.rotation = rotation);
+    }
+
+    fn set_pivot(&mut self, pivot: crate::Point) {
+        self.node.update_display(|display| display.pivot = pivot);
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.node
+            .update_display(|display| display.visible = visible);
+    }
+
+    fn set_alpha(&mut self, alpha: f32) {
+        self.node.update_display(|display| display.alpha = alpha);
+    }
+
+    fn set_renderable(&mut self, renderable: bool) {
+        self.node
+            .update_display(|display| display.renderable = renderable);
+    }
+
+    fn set_z_index(&mut self, z_index: i32) {
+        self.node
+            .update_display(|display| display.z_index = z_index);
     }
 }
 
@@ -117,9 +236,24 @@ impl IntoSceneNode for ContainerNode {
     }
 }
 
+/// Unlinks a mask that is no longer used by the container
+fn release_mask(mask: &AnyNode, container: SceneNodeId) {
+    if let Some(state) = mask.0.state() {
+        if state
+            .parent()
+            .is_some_and(|parent| parent.id() == container)
+        {
+            state.set_parent(None);
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct ContainerInner {
+    pub(crate) display: DisplayState,
     pub(crate) children: ChildrenStore,
+    /// clips the children, see `Container::set_mask`
+    pub(crate) mask: Option<AnyNode>,
 }
 unsafe impl Send for ContainerInner {}
 unsafe impl Sync for ContainerInner {}
@@ -146,6 +280,7 @@ impl ChildrenAccessMut for ContainerInner {
 pub struct ContainerNode {
     pub(crate) id: SceneNodeId,
     pub(crate) inner: Arc<RwLock<ContainerInner>>,
+    pub(crate) state: Arc<NodeState>,
 }
 
 impl SceneNodeIdentifier for ContainerNode {
@@ -156,27 +291,115 @@ impl SceneNodeIdentifier for ContainerNode {
 
 impl SceneNode for ContainerNode {
     fn prepare(&self, render_context: &mut crate::render::RenderContext) {
+        if render_context
+            .read_system(|world: &WorldTransformSystem, _| world.can_reuse(self.id, &self.state))
+        {
+            self.reuse(render_context);
+            return;
+        }
+
+        self.inner.write().children.sort_if_needed();
+
+        let inner = self.inner.read();
+        render_context.update_system(|world: &mut WorldTransformSystem, _| {
+            world.push(self.id, &inner.display);
+        });
+        render_context
+            .update_system(|stats: &mut RenderStatsSystem, _| stats.stats.nodes_prepared += 1);
+
+        // hidden nodes keep their own entry so they are not prepared every frame
+        if inner.display.visible {
+            if let Some(mask) = &inner.mask {
+                mask.prepare(render_context);
+            }
+            for child in &inner.children.nodes {
+                child.prepare(render_context);
+            }
+        }
+
+        render_context.update_system(|world: &mut WorldTransformSystem, _| {
+            world.pop();
+        });
+    }
+
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut crate::render::RenderContext) {
         let inner = self.inner.read();
-        for child in &inner.children.0 {
-            child.prepare(render_context);
+        if !inner.display.visible || !inner.display.renderable {
+            return;
+        }
+
+        let display = inner.display;
+        let layer_alpha = render_context
+            .read_system(|world: &WorldTransformSystem, _| world.get(self.id))
+            .map_or(1.0, |world| world.layer_alpha);
+
+        // an isolated layer drawn fully opaque with normal blending looks the same as no layer
+        let layered =
+            display.blend_mode != BlendMode::Normal || (display.needs_layer() && layer_alpha < 1.0);
+
+        if layered && layer_alpha <= 0.0 {
+            return;
+        }
+
+        // while drawing a mask only the coverage of the children matters
+        let masking = paint.stencil_mode().is_mask();
+        let layered = layered && !masking;
+        let mask = inner.mask.as_ref().filter(|_| !masking);
+
+        if let Some(mask) = mask {
+            paint.push_mask(|paint| mask.paint(paint, render_context));
+        }
+
+        if layered {
+            paint.push_layer(render_context);
+        }
+
+        for child in &inner.children.nodes {
+            child.paint(paint, render_context);
+        }
+
+        if layered {
+            paint.pop_layer(render_context, display.blend_mode, layer_alpha);
+        }
+
+        if let Some(mask) = mask {
+            paint.pop_mask(|paint| mask.paint(paint, render_context));
         }
     }
 
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: ara_math::Size<u32>,
-        render_context: &mut crate::render::RenderContext,
-    ) {
+    fn reuse(&self, render_context: &mut crate::render::RenderContext) {
+        reuse_subtree(self, render_context);
+
         let inner = self.inner.read();
-        for child in &inner.children.0 {
-            child.paint(pass, viewport, render_context);
+        if let (true, Some(mask)) = (inner.display.visible, &inner.mask) {
+            mask.reuse(render_context);
         }
     }
+
+    fn display(&self) -> DisplayState {
+        self.inner.read().display
+    }
+
+    fn with_children(&self, f: &mut dyn FnMut(&ChildrenStore)) {
+        f(&self.inner.read().children)
+    }
+
+    fn update_children(&self, f: &mut dyn FnMut(&mut ChildrenStore)) {
+        f(&mut self.inner.write().children)
+    }
+
+    fn state(&self) -> Option<&NodeState> {
+        Some(&self.state)
+    }
 }
 
 impl ContainerNode {
-    fn new(id: SceneNodeId, inner: Arc<RwLock<ContainerInner>>) -> Self {
-        Self { id, inner }
+    fn new(id: SceneNodeId, inner: Arc<RwLock<ContainerInner>>, state: Arc<NodeState>) -> Self {
+        Self { id, inner, state }
+    }
+
+    fn update_display(&self, f: impl FnOnce(&mut DisplayState)) {
+        f(&mut self.inner.write().display);
+        self.state.mark_dirty();
     }
 }
@@ -1,15 +1,18 @@
 use crate::{
     math::{Corners, Rect},
-    render::renderable::{DisplayObject, View},
-    Color, LineCap, LineJoin, PathEvent, Point,
+    render::{
+        renderable::{DisplayObject, DisplayObjectMut, View},
+        BlendMode, PaintContext, RenderStatsSystem,
+    },
+    Color, FillRule, LineCap, LineJoin, Paint, PathEvent, Point,
 };
-use ara_math::Size;
+use ara_math::Mat3;
 use parking_lot::RwLock;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 use super::{
-    ChildrenAccessMut, ChildrenStore, ParentNode, RenderRoot, SceneNodeId, SceneNodeIdentifier,
-    SceneNodeLike,
+    ChildrenAccessMut, ChildrenStore, DisplayState, NodeState, ParentNode, RenderRoot, SceneNodeId,
+    SceneNodeIdentifier, SceneNodeLike, SpriteTexture, WeakNode, WorldTransformSystem,
 };
 
 pub(crate) mod context;
@@ -21,7 +24,7 @@ pub(crate) use context::GraphicsContext;
 pub(crate) use context_system::{GpuGraphicsContext, GraphicsContextSystem};
 use pipe::GraphicsPipe;
 
-use super::{IntoSceneNode, SceneNode};
+use super::{reuse_subtree, IntoSceneNode, SceneNode};
 
 #[derive(Clone)]
 pub struct Graphics {
@@ -37,10 +40,32 @@ impl Default for Graphics {
     fn default() -> Self {
         let id = SceneNodeId::new();
 
+        let context = Arc::new(RwLock::new(GraphicsContext::new()));
+        let weak_context = Arc::downgrade(&context);
+        let state = Arc::new(NodeState::default());
+        let weak_state = Arc::downgrade(&state);
+        let inner = Arc::new_cyclic(|weak: &Weak<RwLock<GraphicsInner>>| {
+            let weak = weak.clone();
+            let owner = WeakNode::new(id, move || {
+                let node = GraphicsNode {
+                    id,
+                    context: weak_context.upgrade()?,
+                    inner: weak.upgrade()?,
+                    state: weak_state.upgrade()?,
+                };
+                Some(node.into_any_node())
+            });
+            RwLock::new(GraphicsInner {
+                display: Default::default(),
+                children: ChildrenStore::with_owner(owner),
+            })
+        });
+
         let node = GraphicsNode {
             id,
-            context: Arc::new(RwLock::new(GraphicsContext::new())),
-            inner: Arc::new(RwLock::new(GraphicsInner::default())),
+            context,
+            inner,
+            state,
         };
 
         Self { node }
@@ -83,37 +108,84 @@ impl RenderRoot for Graphics {
 
 impl View for Graphics {
     fn bounds(&self) -> Rect<f32> {
-        todo!()
+        self.node.bounds(&Mat3::IDENTITY).unwrap_or_default()
     }
 
-    fn contains_point(&self, _point: Point) -> bool {
-        todo!()
+    fn contains_point(&self, point: Point) -> bool {
+        self.node.hit_test(point).is_some()
     }
 }
 
 impl DisplayObject for Graphics {
     fn get_position(&self) -> Point {
-        todo!()
+        self.node.inner.read().display.position
     }
 
     fn get_scale(&self) -> Point {
-        todo!()
+        self.node.inner.read().display.scale
     }
 
     fn get_rotation(&self) -> f32 {
-        todo!()
+        self.node.inner.read().display.rotation
+    }
+
+    fn get_pivot(&self) -> Point {
+        self.node.inner.read().display.pivot
     }
 
     fn renderable(&self) -> bool {
-        todo!()
+        self.node.inner.read().display.renderable
     }
 
     fn visible(&self) -> bool {
-        todo!()
+        self.node.inner.read().display.visible
     }
 
     fn alpha(&self) -> f32 {
-        todo!()
+        self.node.inner.read().display.alpha
+    }
+
+    fn get_z_index(&self) -> i32 {
+        self.node.inner.read().display.z_index
+    }
+}
+
+impl DisplayObjectMut for Graphics {
+    fn set_position(&mut self, position: Point) {
+        self.node
+            .update_display(|display| display.position = position);
+    }
+
+    fn set_scale(&mut self, scale: Point) {
+        self.node.update_display(|display| display.scale = scale);
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.node
+            .update_display(|display| display.rotation = rotation);
+    }
+
+    fn set_pivot(&mut self, pivot: Point) {
+        self.node.update_display(|display| display.pivot = pivot);
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.node
+            .update_display(|display| display.visible = visible);
+    }
+
+    fn set_alpha(&mut self, alpha: f32) {
+        self.node.update_display(|display| display.alpha = alpha);
+    }
+
+    fn set_renderable(&mut self, renderable: bool) {
+        self.node
+            .update_display(|display| display.renderable = renderable);
+    }
+
+    fn set_z_index(&mut self, z_index: i32) {
+        self.node
+            .update_display(|display| display.z_index = z_index);
     }
 }
 
@@ -131,7 +203,7 @@ impl Graphics {
     }
 
     pub fn cx_mut<T>(&self, f: impl FnOnce(&mut GraphicsContext) -> T) -> T {
-        f(&mut self.node.context.write())
+        self.node.update_context(f)
     }
 }
 
@@ -140,15 +212,28 @@ impl Graphics {
         Self::default()
     }
 
+    /// Clips the following fills and strokes to the rect in the current transform, in addition to the current clip
     pub fn clip(&mut self, rect: Rect<f32>) -> &mut Self {
         self.node.context.write().set_clip(rect);
         self
     }
 
+    /// The clip rect in the local space of the graphics
     pub fn get_clip(&self) -> Rect<f32> {
         self.node.context.read().get_clip()
     }
 
+    /// Clips the following fills and strokes to the area the path fills, in addition to the current clip.
+    /// The path is drawn into a stencil unless it is a rect which stays axis aligned
+    pub fn clip_path<T>(&mut self, path: T) -> &mut Self
+    where
+        T: IntoIterator<Item = PathEvent>,
+    {
+        self.node.context.write().clip_path(path);
+        self
+    }
+
+    /// Removes the clip rect and clip paths
     pub fn reset_clip(&mut self) -> &mut Self {
         self.node.context.write().reset_clip();
         self
@@ -165,15 +250,37 @@ impl Graphics {
         self
     }
 
-    pub fn get_line_width(&self) -> u32 {
+    pub fn get_fill_rule(&self) -> FillRule {
+        self.node.context.read().get_fill_rule()
+    }
+
+    pub fn fill_rule(&mut self, fill_rule: FillRule) -> &mut Self {
+        self.node.context.write().set_fill_rule(fill_rule);
+        self
+    }
+
+    pub fn get_line_width(&self) -> f32 {
         self.node.context.read().get_line_width()
     }
 
-    pub fn line_width(&mut self, line_width: u32) -> &mut Self {
+    pub fn line_width(&mut self, line_width: f32) -> &mut Self {
         self.node.context.write().set_line_width(line_width);
         self
     }
 
+    pub fn get_non_scaling_stroke(&self) -> bool {
+        self.node.context.read().get_non_scaling_stroke()
+    }
+
+    /// Keeps the width of the following strokes in pixels when the graphics or its parents are scaled
+    pub fn non_scaling_stroke(&mut self, non_scaling: bool) -> &mut Self {
+        self.node
+            .context
+            .write()
+            .set_non_scaling_stroke(non_scaling);
+        self
+    }
+
     pub fn get_line_join(&self) -> LineJoin {
         self.node.context.read().get_line_join()
     }
@@ -183,6 +290,15 @@ impl Graphics {
         self
     }
 
+    pub fn get_miter_limit(&self) -> f32 {
+        self.node.context.read().get_miter_limit()
+    }
+
+    pub fn miter_limit(&mut self, miter_limit: f32) -> &mut Self {
+        self.node.context.write().set_miter_limit(miter_limit);
+        self
+    }
+
     pub fn get_line_cap(&self) -> LineCap {
         self.node.context.read().get_line_cap()
     }
@@ -192,6 +308,35 @@ impl Graphics {
         self
     }
 
+    pub fn get_line_dash(&self) -> Vec<f32> {
+        self.node.context.read().get_line_dash().to_vec()
+    }
+
+    /// Lengths of the dashes and gaps of the following strokes, solid when empty
+    pub fn line_dash(&mut self, segments: impl Into<Vec<f32>>) -> &mut Self {
+        self.node.context.write().set_line_dash(segments);
+        self
+    }
+
+    pub fn get_line_dash_offset(&self) -> f32 {
+        self.node.context.read().get_line_dash_offset()
+    }
+
+    pub fn line_dash_offset(&mut self, offset: f32) -> &mut Self {
+        self.node.context.write().set_line_dash_offset(offset);
+        self
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.node.context.read().get_blend_mode()
+    }
+
+    /// Blend mode of the following fills and strokes
+    pub fn blend_mode(&mut self, blend_mode: BlendMode) -> &mut Self {
+        self.node.context.write().set_blend_mode(blend_mode);
+        self
+    }
+
     pub fn save(&mut self) -> &mut Self {
         self.node.context.write().save();
         self
@@ -251,24 +396,45 @@ impl Graphics {
     }
 
     pub fn clear(&self) -> &Self {
-        self.node.context.write().clear();
+        self.node.update_context(|cx| {
+            cx.clear();
+        });
         self
     }
 
-    pub fn fill(&mut self, color: impl Into<Color>) -> &mut Self {
-        self.node.context.write().fill(color);
+    /// Fills the current path with a color, a gradient or a pattern,
+    /// gradients and patterns are in the space of the current transform
+    pub fn fill(&mut self, paint: impl Into<Paint>) -> &mut Self {
+        self.node.update_context(|cx| {
+            cx.fill(paint);
+        });
         self
     }
 
-    pub fn stroke(&mut self, color: impl Into<Color>) -> &mut Self {
-        self.node.context.write().stroke(color);
+    pub fn stroke(&mut self, paint: impl Into<Paint>) -> &mut Self {
+        self.node.update_context(|cx| {
+            cx.stroke(paint);
+        });
+        self
+    }
+
+    /// Fills the current path with an image or a tile of an atlas stretched over the bounds of the path,
+    /// the texels are multiplied with the tint
+    pub fn fill_texture(
+        &mut self,
+        texture: impl Into<SpriteTexture>,
+        tint: impl Into<Color>,
+    ) -> &mut Self {
+        self.node.update_context(|cx| {
+            cx.fill_texture(texture, tint);
+        });
         self
     }
 }
 
 #[derive(Default)]
 pub(crate) struct GraphicsInner {
-    // todo view stuff
+    pub(crate) display: DisplayState,
     pub(crate) children: ChildrenStore,
 }
 
@@ -298,6 +464,24 @@ pub struct GraphicsNode {
     pub(crate) id: SceneNodeId,
     pub(crate) context: Arc<RwLock<GraphicsContext>>,
     pub(crate) inner: Arc<RwLock<GraphicsInner>>,
+    pub(crate) state: Arc<NodeState>,
+}
+
+impl GraphicsNode {
+    fn update_display(&self, f: impl FnOnce(&mut DisplayState)) {
+        f(&mut self.inner.write().display);
+        self.state.mark_dirty();
+    }
+
+    /// Marks the node dirty when the update adds or removes instructions
+    fn update_context<R>(&self, f: impl FnOnce(&mut GraphicsContext) -> R) -> R {
+        let mut context = self.context.write();
+        let result = f(&mut context);
+        if context.dirty.get() {
+            self.state.mark_dirty();
+        }
+        result
+    }
 }
 
 unsafe impl Send for GraphicsNode {}
@@ -311,34 +495,92 @@ impl SceneNodeIdentifier for GraphicsNode {
 
 impl SceneNode for GraphicsNode {
     fn prepare(&self, render_context: &mut crate::render::RenderContext) {
+        if render_context
+            .read_system(|world: &WorldTransformSystem, _| world.can_reuse(self.id, &self.state))
         {
-            let context = self.context.read();
-            render_context.update_pipe(|pipe: &mut GraphicsPipe, cx| {
-                pipe.prepare(cx, &context);
-            });
+            self.reuse(render_context);
+            return;
         }
+
+        self.inner.write().children.sort_if_needed();
+
         let inner = self.inner.read();
-        for child in &inner.children.0 {
-            child.prepare(render_context);
+        let (world, resolution) =
+            render_context.update_system(|world: &mut WorldTransformSystem, _| {
+                (world.push(self.id, &inner.display), world.resolution())
+            });
+        render_context
+            .update_system(|stats: &mut RenderStatsSystem, _| stats.stats.nodes_prepared += 1);
+
+        // hidden nodes keep their own entry so they are not prepared every frame
+        if inner.display.visible {
+            {
+                let context = self.context.read();
+                // strokes are measured in physical pixels of the view
+                let pixel_scale = world.transform.average_scale() * resolution;
+                render_context.update_pipe(|pipe: &mut GraphicsPipe, cx| {
+                    pipe.prepare(cx, &context, pixel_scale);
+                });
+            }
+
+            for child in &inner.children.nodes {
+                child.prepare(render_context);
+            }
         }
+
+        render_context.update_system(|world: &mut WorldTransformSystem, _| {
+            world.pop();
+        });
     }
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: Size<u32>,
-        render_context: &mut crate::render::RenderContext,
-    ) {
+
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut crate::render::RenderContext) {
+        let inner = self.inner.read();
+        if !inner.display.visible || !inner.display.renderable {
+            return;
+        }
+
         {
             let context = self.context.read();
             render_context.update_pipe(|pipe: &mut GraphicsPipe, cx| {
-                pipe.execute(pass, viewport, cx, &context);
+                pipe.execute(paint, cx, self.id, &context);
             });
         }
 
-        let inner = self.inner.read();
-        for child in &inner.children.0 {
-            child.paint(pass, viewport, render_context);
+        for child in &inner.children.nodes {
+            child.paint(paint, render_context);
+        }
+    }
+
+    fn display(&self) -> DisplayState {
+        self.inner.read().display
+    }
+
+    fn content_bounds(&self) -> Option<Rect<f32>> {
+        self.context.read().bounds()
+    }
+
+    fn content_contains_point(&self, point: Point) -> bool {
+        self.context.read().contains_point(point)
+    }
+
+    fn with_children(&self, f: &mut dyn FnMut(&ChildrenStore)) {
+        f(&self.inner.read().children)
+    }
+
+    fn update_children(&self, f: &mut dyn FnMut(&mut ChildrenStore)) {
+        f(&mut self.inner.write().children)
+    }
+
+    fn state(&self) -> Option<&NodeState> {
+        Some(&self.state)
+    }
+
+    fn reuse(&self, render_context: &mut crate::render::RenderContext) {
+        if self.inner.read().display.visible {
+            render_context
+                .update_system(|stats: &mut RenderStatsSystem, _| stats.stats.contexts_reused += 1);
         }
+        reuse_subtree(self, render_context);
     }
 }
 
@@ -1,14 +1,17 @@
 use std::cell::Cell;
 use std::sync::atomic::AtomicUsize;
 
-use crate::{Color, PathEvent};
+use crate::render::BlendMode;
+use crate::{get_path_bounds, point_in_contours, FillRule, PathBuilder, PathEvent};
 use ara_math::{Corners, Mat3, Point};
 
 use crate::{math::Rect, StrokeStyle};
 
-use crate::{FillStyle, LineCap, LineJoin};
+use crate::paint::{resolve_stroke, stroke_outline};
+use crate::{Color, FillStyle, LineCap, LineDash, LineJoin, Paint, PaintShader};
 
 use super::path::{GfxPathEntry, GfxPathInstruction, GraphicsPath};
+use crate::scene::SpriteTexture;
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub enum GraphicsInstructionKind {
@@ -20,9 +23,12 @@ pub enum GraphicsInstructionKind {
         stroke_style: StrokeStyle,
         path: GfxPathEntry,
     },
-    // Texture {
-    //     id: TextureId,
-    // },
+    /// fills the path with the texture stretched over the bounds of the path
+    Texture {
+        texture: SpriteTexture,
+        tint: Color,
+        path: GfxPathEntry,
+    },
 }
 
 impl GraphicsInstructionKind {
@@ -30,6 +36,15 @@ impl GraphicsInstructionKind {
         match self {
             GraphicsInstructionKind::Fill { path, .. } => *path,
             GraphicsInstructionKind::Stroke { path, .. } => *path,
+            GraphicsInstructionKind::Texture { path, .. } => *path,
+        }
+    }
+
+    pub fn shader(&self) -> Option<&PaintShader> {
+        match self {
+            GraphicsInstructionKind::Fill { fill_style, .. } => fill_style.shader.as_deref(),
+            GraphicsInstructionKind::Stroke { stroke_style, .. } => stroke_style.shader.as_deref(),
+            GraphicsInstructionKind::Texture { .. } => None,
         }
     }
 }
@@ -39,15 +54,33 @@ pub struct GraphicsInstruction {
     pub kind: GraphicsInstructionKind,
     pub transform: Mat3,
     pub clip_rect: Rect<f32>,
+    /// index of the innermost clip path in `GraphicsContext::clips`
+    pub clip_path: Option<usize>,
+    pub blend_mode: BlendMode,
+}
+
+/// A path everything drawn after it is clipped to, stored in `GraphicsContext::clip_paths`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphicsClip {
+    pub path: GfxPathEntry,
+    pub transform: Mat3,
+    /// the clip this one is nested in
+    pub parent: Option<usize>,
 }
 
 #[derive(Clone)]
 struct State {
     pub transform: Mat3,
     pub clip_rect: Rect<f32>,
-    pub line_width: u32,
+    pub clip_path: Option<usize>,
+    pub fill_rule: FillRule,
+    pub line_width: f32,
+    pub non_scaling_stroke: bool,
     pub line_join: LineJoin,
+    pub miter_limit: f32,
     pub line_cap: LineCap,
+    pub line_dash: LineDash,
+    pub blend_mode: BlendMode,
 }
 
 impl Default for State {
@@ -55,9 +88,15 @@ impl Default for State {
         Self {
             transform: Default::default(),
             clip_rect: Rect::EVERYTHING,
-            line_width: 2,
+            clip_path: None,
+            fill_rule: FillRule::NonZero,
+            line_width: 2.0,
+            non_scaling_stroke: false,
             line_join: LineJoin::Miter,
+            miter_limit: 10.0,
             line_cap: LineCap::Butt,
+            line_dash: LineDash::default(),
+            blend_mode: BlendMode::Normal,
         }
     }
 }
@@ -77,6 +116,9 @@ pub struct GraphicsContext {
     pub(crate) id: GraphicsContextId,
     pub(crate) path: GraphicsPath,
     pub(crate) instructions: Vec<GraphicsInstruction>,
+    /// kept apart from `path` so clipping does not end the path being built
+    pub(crate) clip_paths: GraphicsPath,
+    pub(crate) clips: Vec<GraphicsClip>,
 
     pub(crate) dirty: Cell<bool>,
 
@@ -101,6 +143,8 @@ impl Clone for GraphicsContext {
             dirty: self.dirty.clone(),
             path: self.path.clone(),
             instructions: self.instructions.clone(),
+            clip_paths: self.clip_paths.clone(),
+            clips: self.clips.clone(),
             stack: self.stack.clone(),
             cur_state: self.cur_state.clone(),
         }
@@ -125,6 +169,8 @@ impl GraphicsContext {
             cur_state,
             path,
             instructions,
+            clip_paths: GraphicsPath::default(),
+            clips: Vec::new(),
         }
     }
 }
@@ -134,6 +180,13 @@ impl GraphicsContext {
         self.dirty.set(true);
         self.path.clear();
         self.instructions.clear();
+        self.clip_paths.clear();
+        self.clips.clear();
+        // clips of the saved states are gone
+        self.cur_state.clip_path = None;
+        for state in &mut self.stack {
+            state.clip_path = None;
+        }
         self
     }
 
@@ -149,20 +202,65 @@ impl GraphicsContext {
         self
     }
 
+    /// Clips everything drawn afterwards to the rect in the current transform, nested in the current clip.
+    /// A rect that does not stay axis aligned is clipped to like a path
     pub fn set_clip(&mut self, rect: Rect<f32>) -> &mut Self {
-        self.cur_state.clip_rect = self.cur_state.clip_rect.intersect(&rect);
-        self
+        if rect == Rect::EVERYTHING {
+            return self;
+        }
+
+        let mut builder = PathBuilder::default();
+        builder.rect(&rect);
+        self.clip_path(&builder.build())
     }
 
+    /// The clip rect in the space of the context, clip paths are not included
     pub fn get_clip(&self) -> Rect<f32> {
         self.cur_state.clip_rect.clone()
     }
 
+    /// Clips everything drawn afterwards to the filled area of the path, nested in the current clip.
+    /// Rectangles which stay axis aligned under the current transform become a clip rect
+    pub fn clip_path<T>(&mut self, path: T) -> &mut Self
+    where
+        T: IntoIterator<Item = PathEvent>,
+    {
+        let events: Vec<PathEvent> = path.into_iter().collect();
+        let transform = self.cur_state.transform;
+
+        if let Some(rect) = axis_aligned_rect(&events, &transform) {
+            self.cur_state.clip_rect = self.cur_state.clip_rect.intersect(&rect);
+            return self;
+        }
+
+        self.clip_paths.path2d(events);
+        let Some(path) = self.clip_paths.push() else {
+            return self;
+        };
+
+        self.clips.push(GraphicsClip {
+            path,
+            transform,
+            parent: self.cur_state.clip_path,
+        });
+        self.cur_state.clip_path = Some(self.clips.len() - 1);
+        self
+    }
+
     pub fn reset_clip(&mut self) -> &mut Self {
         self.cur_state.clip_rect = Rect::EVERYTHING;
+        self.cur_state.clip_path = None;
         self
     }
 
+    /// Clips from the outermost to `clip`
+    pub(crate) fn clip_chain(&self, clip: Option<usize>) -> Vec<usize> {
+        let mut chain: Vec<usize> =
+            std::iter::successors(clip, |&index| self.clips[index].parent).collect();
+        chain.reverse();
+        chain
+    }
+
     /// Reset the current state to default values
     pub fn reset(&mut self) -> &mut Self {
         self.cur_state = State::default();
@@ -174,15 +272,38 @@ impl GraphicsContext {
         self
     }
 
-    pub fn set_line_width(&mut self, line_width: u32) -> &mut Self {
-        self.cur_state.line_width = line_width;
+    /// Which areas of overlapping and self intersecting contours the following fills cover
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) -> &mut Self {
+        self.cur_state.fill_rule = fill_rule;
+        self
+    }
+
+    pub fn get_fill_rule(&self) -> FillRule {
+        self.cur_state.fill_rule
+    }
+
+    /// Width of the following strokes, like a canvas widths which are not positive and finite are ignored
+    pub fn set_line_width(&mut self, line_width: f32) -> &mut Self {
+        if line_width.is_finite() && line_width > 0.0 {
+            self.cur_state.line_width = line_width;
+        }
         self
     }
 
-    pub fn get_line_width(&self) -> u32 {
+    pub fn get_line_width(&self) -> f32 {
         self.cur_state.line_width
     }
 
+    /// Keeps the width of the following strokes in pixels when they are transformed
+    pub fn set_non_scaling_stroke(&mut self, non_scaling: bool) -> &mut Self {
+        self.cur_state.non_scaling_stroke = non_scaling;
+        self
+    }
+
+    pub fn get_non_scaling_stroke(&self) -> bool {
+        self.cur_state.non_scaling_stroke
+    }
+
     pub fn set_line_join(&mut self, line_join: LineJoin) -> &mut Self {
         self.cur_state.line_join = line_join;
         self
@@ -192,6 +313,19 @@ impl GraphicsContext {
         self.cur_state.line_join
     }
 
+    /// Ratio of the miter length to half the line width above which miter joins are beveled,
+    /// or clipped for `LineJoin::MiterClip`. Like a canvas limits which are not positive and finite are ignored
+    pub fn set_miter_limit(&mut self, miter_limit: f32) -> &mut Self {
+        if miter_limit.is_finite() && miter_limit > 0.0 {
+            self.cur_state.miter_limit = miter_limit;
+        }
+        self
+    }
+
+    pub fn get_miter_limit(&self) -> f32 {
+        self.cur_state.miter_limit
+    }
+
     pub fn set_line_cap(&mut self, line_cap: LineCap) -> &mut Self {
         self.cur_state.line_cap = line_cap;
         self
@@ -201,6 +335,42 @@ impl GraphicsContext {
         self.cur_state.line_cap
     }
 
+    /// Lengths of the dashes and gaps of the following strokes, solid when empty.
+    /// Like a canvas the segments are ignored if any of them is negative or not finite
+    pub fn set_line_dash(&mut self, segments: impl Into<Vec<f32>>) -> &mut Self {
+        let segments = segments.into();
+        if LineDash::is_valid(&segments) {
+            self.cur_state.line_dash.segments = segments;
+        }
+        self
+    }
+
+    pub fn get_line_dash(&self) -> &[f32] {
+        &self.cur_state.line_dash.segments
+    }
+
+    /// Distance into the dash pattern the following strokes start at
+    pub fn set_line_dash_offset(&mut self, offset: f32) -> &mut Self {
+        if offset.is_finite() {
+            self.cur_state.line_dash.offset = offset;
+        }
+        self
+    }
+
+    pub fn get_line_dash_offset(&self) -> f32 {
+        self.cur_state.line_dash.offset
+    }
+
+    /// Blend mode of the following fills and strokes
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) -> &mut Self {
+        self.cur_state.blend_mode = blend_mode;
+        self
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.cur_state.blend_mode
+    }
+
     pub fn translate(&mut self, dx: f32, dy: f32) -> &mut Self {
         self.cur_state.transform.translate(dx, dy);
         self
@@ -239,13 +409,13 @@ impl GraphicsContext {
         self
     }
 
-    pub fn fill(&mut self, color: impl Into<Color>) -> &mut Self {
+    pub fn fill(&mut self, paint: impl Into<Paint>) -> &mut Self {
         if let Some(path) = self.path.push() {
             self.dirty.set(true);
             let state = &self.cur_state;
 
             let kind = GraphicsInstructionKind::Fill {
-                fill_style: FillStyle::default().color(color.into()),
+                fill_style: FillStyle::default().paint(paint).fill_rule(state.fill_rule),
                 path,
             };
 
@@ -253,6 +423,8 @@ impl GraphicsContext {
                 kind,
                 transform: state.transform,
                 clip_rect: state.clip_rect.clone(),
+                clip_path: state.clip_path,
+                blend_mode: state.blend_mode,
             };
 
             if Some(&ins) != self.instructions.last() {
@@ -262,25 +434,60 @@ impl GraphicsContext {
         self
     }
 
-    pub fn stroke(&mut self, color: impl Into<Color>) -> &mut Self {
+    pub fn stroke(&mut self, paint: impl Into<Paint>) -> &mut Self {
         if let Some(path) = self.path.push() {
             self.dirty.set(true);
             let state = &self.cur_state;
 
             let kind = GraphicsInstructionKind::Stroke {
                 stroke_style: StrokeStyle {
-                    color: color.into(),
                     line_width: state.line_width,
+                    non_scaling: state.non_scaling_stroke,
                     line_join: state.line_join,
+                    miter_limit: state.miter_limit,
                     line_cap: state.line_cap,
+                    line_dash: state.line_dash.clone(),
                     ..Default::default()
-                },
+                }
+                .paint(paint),
                 path,
             };
             let ins = GraphicsInstruction {
                 kind,
                 transform: state.transform,
                 clip_rect: state.clip_rect.clone(),
+                clip_path: state.clip_path,
+                blend_mode: state.blend_mode,
+            };
+
+            if Some(&ins) != self.instructions.last() {
+                self.instructions.push(ins);
+            }
+        }
+        self
+    }
+
+    /// Fills the current path with the texture stretched over the bounds of the path,
+    /// the texels are multiplied with the tint
+    pub fn fill_texture(
+        &mut self,
+        texture: impl Into<SpriteTexture>,
+        tint: impl Into<Color>,
+    ) -> &mut Self {
+        if let Some(path) = self.path.push() {
+            self.dirty.set(true);
+            let state = &self.cur_state;
+
+            let ins = GraphicsInstruction {
+                kind: GraphicsInstructionKind::Texture {
+                    texture: texture.into(),
+                    tint: tint.into(),
+                    path,
+                },
+                transform: state.transform,
+                clip_rect: state.clip_rect.clone(),
+                clip_path: state.clip_path,
+                blend_mode: state.blend_mode,
             };
 
             if Some(&ins) != self.instructions.last() {
@@ -291,12 +498,208 @@ impl GraphicsContext {
     }
 }
 
+impl GraphicsContext {
+    /// Bounds of everything drawn by this context in its local space.
+    /// Strokes are measured by their outline with its joins and caps
+    pub fn bounds(&self) -> Option<Rect<f32>> {
+        let mut bounds: Option<Rect<f32>> = None;
+
+        for instruction in &self.instructions {
+            let instruction_bounds = match &instruction.kind {
+                GraphicsInstructionKind::Stroke { stroke_style, path } => self
+                    .stroke_outline(*path, stroke_style, &instruction.transform)
+                    .iter()
+                    .map(|polygon| get_path_bounds(polygon))
+                    .reduce(|a, b| a.union(&b)),
+                kind => self
+                    .path
+                    .get_entry(kind.path())
+                    .iter()
+                    .map(|ins| ins.bounds(&self.path.builder))
+                    .reduce(|a, b| a.union(&b))
+                    .map(|path_bounds| instruction.transform.transform_rect(&path_bounds)),
+            };
+            let Some(instruction_bounds) = instruction_bounds else {
+                continue;
+            };
+
+            let mut instruction_bounds = instruction_bounds.intersect(&instruction.clip_rect);
+            for clip in self.clip_chain(instruction.clip_path) {
+                let clip = &self.clips[clip];
+                if let Some(clip_bounds) = self
+                    .clip_paths
+                    .get_entry(clip.path)
+                    .iter()
+                    .map(|ins| ins.bounds(&self.clip_paths.builder))
+                    .reduce(|a, b| a.union(&b))
+                {
+                    instruction_bounds =
+                        instruction_bounds.intersect(&clip.transform.transform_rect(&clip_bounds));
+                }
+            }
+
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union(&instruction_bounds),
+                None => instruction_bounds,
+            });
+        }
+
+        bounds
+    }
+
+    /// Checks if the point in local space hits the filled or stroked geometry of this context
+    pub fn contains_point(&self, point: Point) -> bool {
+        let mut points = Vec::new();
+        let mut contours = Vec::new();
+
+        self.instructions.iter().rev().any(|instruction| {
+            if !instruction.clip_rect.contains_point(&point) {
+                return false;
+            }
+
+            for clip in self.clip_chain(instruction.clip_path) {
+                let clip = &self.clips[clip];
+                points.clear();
+                contours.clear();
+                self.clip_paths
+                    .flatten(clip.path, &clip.transform, &mut points, &mut contours);
+
+                let contours = contours.iter().map(|range| &points[range.clone()]);
+                if !point_in_contours(point, contours, FillRule::NonZero) {
+                    return false;
+                }
+            }
+
+            if let GraphicsInstructionKind::Stroke { stroke_style, path } = &instruction.kind {
+                let outline = self.stroke_outline(*path, stroke_style, &instruction.transform);
+                let polygons = outline.iter().map(|polygon| &polygon[..]);
+                return point_in_contours(point, polygons, FillRule::NonZero);
+            }
+
+            points.clear();
+            contours.clear();
+            self.path.flatten(
+                instruction.kind.path(),
+                &instruction.transform,
+                &mut points,
+                &mut contours,
+            );
+
+            let contours = contours.iter().map(|range| &points[range.clone()]);
+            let fill_rule = match &instruction.kind {
+                GraphicsInstructionKind::Fill { fill_style, .. } => fill_style.fill_rule,
+                _ => FillRule::NonZero,
+            };
+            point_in_contours(point, contours, fill_rule)
+        })
+    }
+
+    /// Polygons outlining a stroke in the space the transform maps into, as wide as it is drawn.
+    /// Like the tessellated stroke it is outlined before the transform unless it is non scaling
+    fn stroke_outline(
+        &self,
+        path: GfxPathEntry,
+        stroke_style: &StrokeStyle,
+        transform: &Mat3,
+    ) -> Vec<Vec<Point>> {
+        let (space, outline_space, pixel_scale) = if stroke_style.non_scaling {
+            (*transform, Mat3::IDENTITY, 1.0)
+        } else {
+            (Mat3::IDENTITY, *transform, transform.average_scale())
+        };
+        let Some(stroke_style) = resolve_stroke(stroke_style, pixel_scale) else {
+            return Vec::new();
+        };
+
+        let mut points = Vec::new();
+        let mut contours = Vec::new();
+        self.path.flatten(path, &space, &mut points, &mut contours);
+
+        let mut outline = Vec::new();
+        for range in contours {
+            stroke_outline(&points[range], &stroke_style, |polygon| {
+                outline.push(polygon.iter().map(|&point| outline_space * point).collect());
+            });
+        }
+        outline
+    }
+}
+
+impl GraphicsContext {
+    /// Checks if any stroke is tessellated differently at the two pixel scales,
+    /// only non scaling strokes and strokes thinner than a pixel depend on it
+    pub(crate) fn strokes_differ_at(&self, a: f32, b: f32) -> bool {
+        if a == b {
+            return false;
+        }
+
+        self.instructions.iter().any(|instruction| {
+            let GraphicsInstructionKind::Stroke { stroke_style, .. } = &instruction.kind else {
+                return false;
+            };
+            let width = stroke_style.line_width * instruction.transform.average_scale();
+            stroke_style.non_scaling || width * a < 1.0 || width * b < 1.0
+        })
+    }
+}
+
+/// The rect drawn by a single closed contour of four straight edges,
+/// none if the path is anything else or the transform does not keep its edges axis aligned
+fn axis_aligned_rect(events: &[PathEvent], transform: &Mat3) -> Option<Rect<f32>> {
+    let mut corners = Vec::with_capacity(5);
+
+    for (i, event) in events.iter().enumerate() {
+        match *event {
+            PathEvent::Begin { at } if i == 0 => corners.push(at),
+            PathEvent::Line { to, .. } if i > 0 => corners.push(to),
+            PathEvent::End { .. } if i == events.len() - 1 => {}
+            _ => return None,
+        }
+    }
+
+    if corners.len() == 5 && corners.first() == corners.last() {
+        corners.pop();
+    }
+    if corners.len() != 4 {
+        return None;
+    }
+
+    let corners: Vec<Point> = corners.into_iter().map(|p| *transform * p).collect();
+    let rect = Rect::from_corners(
+        corners.iter().fold(corners[0], |min, p| min.min(p)),
+        corners.iter().fold(corners[0], |max, p| max.max(p)),
+    );
+
+    let min = rect.min();
+    let max = rect.max();
+    let is_corner = |p: &Point| (p.x == min.x || p.x == max.x) && (p.y == min.y || p.y == max.y);
+    let axis_aligned = (0..4).all(|i| {
+        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+        is_corner(&a) && (a.x == b.x) != (a.y == b.y)
+    });
+
+    axis_aligned.then_some(rect)
+}
+
 pub(crate) struct BatchedGraphicsInstruction<'a> {
     pub path_instructions: &'a [GfxPathInstruction],
     pub transform: &'a Mat3,
     pub clip_rect: &'a Rect<f32>,
+    pub clip_path: Option<usize>,
+    pub blend_mode: BlendMode,
     pub fill: Option<&'a FillStyle>,
     pub stroke: Option<&'a StrokeStyle>,
+    /// a texture is drawn on its own
+    pub texture: Option<(&'a SpriteTexture, Color)>,
+}
+
+impl<'a> BatchedGraphicsInstruction<'a> {
+    /// Shader the batch is drawn with, a fill and a stroke are only batched when they share it
+    pub fn shader(&self) -> Option<&'a PaintShader> {
+        self.fill
+            .and_then(|fill| fill.shader.as_deref())
+            .or_else(|| self.stroke.and_then(|stroke| stroke.shader.as_deref()))
+    }
 }
 
 pub(crate) struct BatchedGraphicsContextIter<'a> {
@@ -332,6 +735,7 @@ impl<'a> Iterator for BatchedGraphicsContextIter<'a> {
         let next = self.instructions.next();
         let mut fill = None;
         let mut stroke = None;
+        let mut texture = None;
 
         // Process current instruction
         match &current.kind {
@@ -341,15 +745,25 @@ impl<'a> Iterator for BatchedGraphicsContextIter<'a> {
             GraphicsInstructionKind::Stroke { stroke_style, .. } => {
                 stroke = Some(stroke_style);
             }
+            GraphicsInstructionKind::Texture {
+                texture: source,
+                tint,
+                ..
+            } => {
+                texture = Some((source, *tint));
+            }
         }
 
-        // If next instruction uses same path, transform, and clip, combine it
-        if let Some(next_inst) = next {
+        // If next instruction uses same path, transform, clips, blend mode and shader, combine it
+        if let Some(next_inst) = next.filter(|_| texture.is_none()) {
             let next_path = next_inst.kind.path();
 
             let same_geometry = next_path == current_path
                 && next_inst.transform == *current_transform
-                && next_inst.clip_rect == *current_clip_rect;
+                && next_inst.clip_rect == *current_clip_rect
+                && next_inst.clip_path == current.clip_path
+                && next_inst.blend_mode == current.blend_mode
+                && next_inst.kind.shader() == current.kind.shader();
 
             if same_geometry {
                 // Add the operation from next instruction
@@ -374,14 +788,17 @@ impl<'a> Iterator for BatchedGraphicsContextIter<'a> {
                             self.peeked = Some(next_inst);
                         }
                     }
+                    GraphicsInstructionKind::Texture { .. } => {
+                        self.peeked = Some(next_inst);
+                    }
                 }
             } else {
                 // Can't batch - different geometry
                 self.peeked = Some(next_inst);
             }
         } else {
-            // No more instructions
-            self.peeked = None;
+            // No more instructions, or a texture which is never combined
+            self.peeked = next;
         }
 
         // Get path instructions for the current entry
@@ -391,8 +808,11 @@ impl<'a> Iterator for BatchedGraphicsContextIter<'a> {
             path_instructions,
             transform: current_transform,
             clip_rect: current_clip_rect,
+            clip_path: current.clip_path,
+            blend_mode: current.blend_mode,
             fill,
             stroke,
+            texture,
         })
     }
 }
@@ -402,6 +822,7 @@ mod tests {
     use ara_math::vec2;
 
     use super::*;
+    use crate::Color;
 
     #[test]
     fn test_graphic_context_push_instruction() {
@@ -447,4 +868,325 @@ mod tests {
             }
         ));
     }
+
+    #[test]
+    fn test_graphic_context_batches_by_blend_mode() {
+        let mut cx = GraphicsContext::new();
+
+        cx.rect(Rect::xywh(0.0, 0.0, 100.0, 100.0));
+        cx.fill(Color::WHITE);
+        cx.set_blend_mode(BlendMode::Multiply);
+        cx.stroke(Color::BLACK);
+
+        cx.save();
+        cx.set_blend_mode(BlendMode::Screen);
+        cx.circle(vec2(100.0, 100.0), 50.0);
+        cx.fill(Color::WHITE);
+        cx.stroke(Color::BLACK);
+        cx.restore();
+        assert_eq!(cx.get_blend_mode(), BlendMode::Multiply);
+
+        let batches: Vec<_> = BatchedGraphicsContextIter::new(&cx)
+            .map(|batch| {
+                (
+                    batch.blend_mode,
+                    batch.fill.is_some(),
+                    batch.stroke.is_some(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            batches,
+            [
+                (BlendMode::Normal, true, false),
+                (BlendMode::Multiply, false, true),
+                (BlendMode::Screen, true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_graphic_context_batches_by_shader() {
+        let gradient = crate::Gradient::linear((0.0, 0.0), (100.0, 0.0))
+            .add_stop(0.0, Color::RED)
+            .add_stop(1.0, Color::BLUE);
+
+        let mut cx = GraphicsContext::new();
+        cx.rect(Rect::xywh(0.0, 0.0, 100.0, 100.0));
+        cx.fill(gradient.clone());
+        cx.stroke(Color::BLACK);
+
+        cx.circle(vec2(100.0, 100.0), 50.0);
+        cx.fill(gradient.clone());
+        cx.stroke(gradient.clone());
+
+        let batches: Vec<_> = BatchedGraphicsContextIter::new(&cx)
+            .map(|batch| {
+                (
+                    batch.shader().is_some(),
+                    batch.fill.is_some(),
+                    batch.stroke.is_some(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            batches,
+            [
+                (true, true, false),
+                (false, false, true),
+                (true, true, true)
+            ]
+        );
+
+        // shaders are drawn with white vertices which carry the coverage
+        let GraphicsInstructionKind::Fill { fill_style, .. } = &cx.instructions[0].kind else {
+            panic!("expected a fill");
+        };
+        assert_eq!(fill_style.color, Color::WHITE);
+    }
+
+    #[test]
+    fn test_graphic_context_batches_textures_alone() {
+        let image = crate::Image::from_rgba((1, 1), vec![255, 0, 0, 255]).unwrap();
+
+        let mut cx = GraphicsContext::new();
+        cx.rect(Rect::xywh(0.0, 0.0, 100.0, 100.0));
+        cx.fill(Color::WHITE);
+        cx.fill_texture(image.clone(), Color::RED);
+        cx.fill_texture(image, Color::WHITE);
+        cx.stroke(Color::BLACK);
+
+        let batches: Vec<_> = BatchedGraphicsContextIter::new(&cx)
+            .map(|batch| {
+                (
+                    batch.texture.map(|(_, tint)| tint),
+                    batch.fill.is_some(),
+                    batch.stroke.is_some(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            batches,
+            [
+                (None, true, false),
+                (Some(Color::RED), false, false),
+                (Some(Color::WHITE), false, false),
+                (None, false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_graphic_context_clip_path() {
+        let square = |rect: Rect<f32>| {
+            let mut builder = crate::PathBuilder::default();
+            builder.rect(&rect);
+            builder.build()
+        };
+        let triangle = {
+            let mut builder = crate::PathBuilder::default();
+            builder.begin(vec2(50.0, 0.0));
+            builder.line_to(vec2(100.0, 100.0));
+            builder.line_to(vec2(0.0, 100.0));
+            builder.close();
+            builder.build()
+        };
+
+        // an untransformed rect only narrows the clip rect
+        let mut cx = GraphicsContext::new();
+        cx.clip_path(&square(Rect::xywh(0.0, 0.0, 100.0, 100.0)));
+        assert!(cx.clips.is_empty());
+        assert_eq!(cx.get_clip(), Rect::xywh(0.0, 0.0, 100.0, 100.0));
+
+        cx.rect(Rect::xywh(0.0, 0.0, 200.0, 200.0));
+        cx.fill(Color::WHITE);
+
+        cx.save();
+        cx.rotate(std::f32::consts::FRAC_PI_4);
+        cx.clip_path(&square(Rect::xywh(0.0, 0.0, 50.0, 50.0)));
+        cx.clip_path(&triangle);
+        assert_eq!(cx.clip_chain(cx.cur_state.clip_path), [0, 1]);
+        cx.fill(Color::BLACK);
+        cx.restore();
+        cx.stroke(Color::BLACK);
+
+        let clips: Vec<_> = BatchedGraphicsContextIter::new(&cx)
+            .map(|batch| batch.clip_path)
+            .collect();
+        assert_eq!(clips, [None, Some(1), None]);
+
+        cx.clear();
+        assert!(cx.clips.is_empty());
+        assert_eq!(cx.cur_state.clip_path, None);
+
+        // only the rotated square is hit
+        let mut cx = GraphicsContext::new();
+        cx.rotate(std::f32::consts::FRAC_PI_4);
+        cx.clip_path(&square(Rect::xywh(0.0, 0.0, 50.0, 50.0)));
+        let center = cx.cur_state.transform * vec2(25.0, 25.0);
+        let corner = cx.cur_state.transform * vec2(-5.0, -5.0);
+        cx.reset_transform();
+        cx.rect(Rect::xywh(-100.0, -100.0, 200.0, 200.0));
+        cx.fill(Color::WHITE);
+
+        assert!(cx.contains_point(center));
+        assert!(!cx.contains_point(corner));
+        assert!(cx.bounds().unwrap().contains_point(&center));
+        assert!(!cx.bounds().unwrap().contains_point(&vec2(-90.0, -90.0)));
+    }
+
+    #[test]
+    fn test_graphic_context_clip_rect_follows_transform() {
+        let mut cx = GraphicsContext::new();
+
+        cx.save();
+        cx.translate(10.0, 20.0);
+        cx.scale(2.0, 2.0);
+        cx.set_clip(Rect::xywh(0.0, 0.0, 50.0, 50.0));
+        assert_eq!(cx.get_clip(), Rect::xywh(10.0, 20.0, 100.0, 100.0));
+
+        // nested clips intersect in the space they were set in
+        cx.save();
+        cx.translate(25.0, 25.0);
+        cx.set_clip(Rect::xywh(0.0, 0.0, 50.0, 50.0));
+        assert_eq!(cx.get_clip(), Rect::xywh(60.0, 70.0, 50.0, 50.0));
+
+        cx.rotate(0.5);
+        cx.set_clip(Rect::xywh(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(cx.get_clip(), Rect::xywh(60.0, 70.0, 50.0, 50.0));
+        assert_eq!(cx.clips.len(), 1);
+        assert!(cx.cur_state.clip_path.is_some());
+
+        cx.restore();
+        assert_eq!(cx.get_clip(), Rect::xywh(10.0, 20.0, 100.0, 100.0));
+        assert_eq!(cx.cur_state.clip_path, None);
+
+        cx.restore();
+        assert_eq!(cx.get_clip(), Rect::EVERYTHING);
+    }
+
+    #[test]
+    fn test_graphic_context_bounds() {
+        let mut cx = GraphicsContext::new();
+        assert_eq!(cx.bounds(), None);
+
+        cx.rect(Rect::xywh(0.0, 0.0, 100.0, 50.0));
+        cx.fill(Color::WHITE);
+
+        cx.translate(200.0, 0.0);
+        cx.set_line_width(10.0);
+        cx.circle(vec2(0.0, 0.0), 20.0);
+        cx.stroke(Color::WHITE);
+
+        // the flattened circle is mitered a little past its radius
+        let bounds = cx.bounds().unwrap();
+        assert!((bounds.min() - vec2(0.0, -25.0)).magnitude() < 0.1);
+        assert!((bounds.max() - vec2(225.0, 50.0)).magnitude() < 0.1);
+
+        // the outer corner of a stroke reaches past half of its width
+        let mut cx = GraphicsContext::new();
+        let mut builder = PathBuilder::default();
+        builder.begin(vec2(0.0, 10.0));
+        builder.line_to(vec2(10.0, 0.0));
+        builder.line_to(vec2(20.0, 10.0));
+        builder.end(false);
+        cx.set_line_width(4.0);
+        cx.path(&builder.build());
+        cx.stroke(Color::WHITE);
+
+        let bounds = cx.bounds().unwrap();
+        assert!((bounds.min().y + 2.0 * 2f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_graphic_context_contains_point() {
+        let mut cx = GraphicsContext::new();
+
+        cx.circle(vec2(50.0, 50.0), 50.0);
+        cx.fill(Color::WHITE);
+
+        cx.set_line_width(4.0);
+        cx.rect(Rect::xywh(200.0, 0.0, 100.0, 100.0));
+        cx.stroke(Color::WHITE);
+
+        // inside the circle but outside of its bounding box corners
+        assert!(cx.contains_point(vec2(50.0, 50.0)));
+        assert!(!cx.contains_point(vec2(3.0, 3.0)));
+
+        // only the outline of the stroked rect is hit
+        assert!(cx.contains_point(vec2(201.0, 50.0)));
+        assert!(cx.contains_point(vec2(250.0, 98.5)));
+        assert!(!cx.contains_point(vec2(250.0, 50.0)));
+
+        cx.set_clip(Rect::xywh(0.0, 0.0, 10.0, 10.0));
+        cx.rect(Rect::xywh(0.0, 0.0, 400.0, 400.0));
+        cx.fill(Color::WHITE);
+        assert!(cx.contains_point(vec2(5.0, 5.0)));
+        assert!(!cx.contains_point(vec2(350.0, 350.0)));
+    }
+
+    #[test]
+    fn test_graphic_context_line_dash() {
+        let mut cx = GraphicsContext::new();
+
+        cx.set_line_dash([10.0, 10.0]);
+        cx.set_line_dash_offset(5.0);
+        // ignored like in a canvas
+        cx.set_line_dash([10.0, f32::NAN]);
+        cx.set_line_dash_offset(f32::INFINITY);
+        assert_eq!(cx.get_line_dash(), [10.0, 10.0]);
+        assert_eq!(cx.get_line_dash_offset(), 5.0);
+
+        cx.set_line_width(4.0);
+        cx.rect(Rect::xywh(0.0, 0.0, 100.0, 100.0));
+        cx.stroke(Color::WHITE);
+
+        cx.save();
+        cx.set_line_dash([]);
+        assert_eq!(cx.get_line_dash(), []);
+        cx.restore();
+        assert_eq!(cx.get_line_dash(), [10.0, 10.0]);
+
+        let GraphicsInstructionKind::Stroke { stroke_style, .. } = &cx.instructions[0].kind else {
+            panic!("expected a stroke");
+        };
+        assert_eq!(
+            stroke_style.line_dash,
+            LineDash::new([10.0, 10.0]).offset(5.0)
+        );
+
+        // only the dashes are hit, the pattern starts 5 into the first dash
+        assert!(cx.contains_point(vec2(2.0, 0.0)));
+        assert!(!cx.contains_point(vec2(10.0, 0.0)));
+        assert!(cx.contains_point(vec2(20.0, 1.0)));
+    }
+
+    #[test]
+    fn test_graphic_context_fill_rule() {
+        let mut cx = GraphicsContext::new();
+        assert_eq!(cx.get_fill_rule(), FillRule::NonZero);
+
+        // the inner rect winds the same way as the outer one
+        cx.set_fill_rule(FillRule::EvenOdd);
+        cx.rect(Rect::xywh(0.0, 0.0, 30.0, 30.0));
+        cx.rect(Rect::xywh(10.0, 10.0, 10.0, 10.0));
+        cx.fill(Color::WHITE);
+
+        let GraphicsInstructionKind::Fill { fill_style, .. } = &cx.instructions[0].kind else {
+            panic!("expected a fill");
+        };
+        assert_eq!(fill_style.fill_rule, FillRule::EvenOdd);
+        assert!(cx.contains_point(vec2(5.0, 5.0)));
+        assert!(!cx.contains_point(vec2(15.0, 15.0)));
+
+        cx.clear();
+        cx.set_fill_rule(FillRule::NonZero);
+        cx.rect(Rect::xywh(0.0, 0.0, 30.0, 30.0));
+        cx.rect(Rect::xywh(10.0, 10.0, 10.0, 10.0));
+        cx.fill(Color::WHITE);
+        assert!(cx.contains_point(vec2(15.0, 15.0)));
+    }
 }
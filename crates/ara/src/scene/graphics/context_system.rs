@@ -26,6 +26,8 @@ impl System for GraphicsContextSystem {
 pub struct GpuGraphicsContext {
     pub(crate) geometry_handle: GeometryHandle,
     pub(crate) commands: Vec<RenderCommand>,
+    /// pixels per unit of the context the geometry was built for
+    pub(crate) pixel_scale: f32,
 }
 
 impl GpuGraphicsContext {
@@ -43,6 +45,7 @@ impl GpuGraphicsContext {
         Self {
             geometry_handle,
             commands: Default::default(),
+            pixel_scale: 1.0,
         }
     }
 }
@@ -1,8 +1,8 @@
 use std::ops::Range;
 
-use ara_math::{Corners, Point, Rect};
+use ara_math::{Corners, Mat3, Point, Rect};
 
-use crate::{PathBuilder, PathEvent};
+use crate::{get_path_bounds, PathBuilder, PathEvent, PathEventsIter, PathGeometryBuilder};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum GfxPathInstruction {
@@ -23,6 +23,24 @@ pub enum GfxPathInstruction {
     },
 }
 
+impl GfxPathInstruction {
+    /// Bounds of the instruction before any transform is applied
+    pub fn bounds(&self, builder: &PathBuilder) -> Rect<f32> {
+        match self {
+            GfxPathInstruction::Rect { bounds } | GfxPathInstruction::RoundRect { bounds, .. } => {
+                bounds.clone()
+            }
+            GfxPathInstruction::Circle { center, radius } => Rect::from_corners(
+                *center - Point::new(*radius, *radius),
+                *center + Point::new(*radius, *radius),
+            ),
+            GfxPathInstruction::Path { points, .. } => {
+                get_path_bounds(&builder.points[points.clone()])
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub struct GfxPathEntry {
     pub(crate) start: usize,
@@ -48,6 +66,56 @@ impl GraphicsPath {
         &self.instructions[entry.start..entry.end]
     }
 
+    /// Flattens the entry into polygons with the transform applied.
+    /// Each range in `contours` indexes into `points`
+    pub(crate) fn flatten(
+        &self,
+        entry: GfxPathEntry,
+        transform: &Mat3,
+        points: &mut Vec<Point>,
+        contours: &mut Vec<Range<usize>>,
+    ) {
+        let start = points.len();
+        let mut scratch = PathBuilder::default();
+
+        for instruction in self.get_entry(entry) {
+            let events = match instruction {
+                GfxPathInstruction::Rect { bounds } => {
+                    scratch.clear();
+                    scratch.rect(bounds);
+                    scratch.path_events()
+                }
+                GfxPathInstruction::RoundRect { bounds, corners } => {
+                    scratch.clear();
+                    scratch.round_rect(bounds, corners);
+                    scratch.path_events()
+                }
+                GfxPathInstruction::Circle { center, radius } => {
+                    scratch.clear();
+                    scratch.circle(*center, *radius);
+                    scratch.path_events()
+                }
+                GfxPathInstruction::Path {
+                    points: point_range,
+                    verbs,
+                } => PathEventsIter::new(
+                    &self.builder.points[point_range.clone()],
+                    &self.builder.verbs[verbs.clone()],
+                ),
+            };
+
+            contours.extend(
+                <PathGeometryBuilder<PathEventsIter>>::new(events, points).map(|(_, range)| range),
+            );
+        }
+
+        if !transform.is_identity() {
+            for point in &mut points[start..] {
+                *point = *transform * *point;
+            }
+        }
+    }
+
     pub fn rect(&mut self, rect: Rect<f32>) {
         self.instructions
             .push(GfxPathInstruction::Rect { bounds: rect });
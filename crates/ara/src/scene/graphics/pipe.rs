@@ -1,23 +1,41 @@
-use ara_math::{Rect, Size};
+use ara_math::{vec2, Mat3, Rect, Size};
 
 use crate::{
     paint::Vertex,
     render::{
         pipes::RenderPipe,
-        systems::{GeometryBuilder, GeometrySystem, GlobalUniformSystem},
-        Item, ItemContext, RenderCommand, RenderContext,
+        render_target::TargetFormat,
+        systems::{
+            GeometryBuilder, GeometryHandle, GeometrySystem, GlobalUniformSystem, PipelineKey,
+            PipelineLayoutId, PipelineSystem, RenderBufferRange, ShaderId, TextureKey,
+            TextureSystem,
+        },
+        BlendMode, Item, ItemContext, PaintContext, RenderCommand, RenderContext,
+        RenderStatsSystem, StencilMode,
     },
     scene::{
-        context::{BatchedGraphicsContextIter, BatchedGraphicsInstruction, GraphicsContext},
+        context::{
+            BatchedGraphicsContextIter, BatchedGraphicsInstruction, GraphicsClip, GraphicsContext,
+        },
         path::GfxPathInstruction,
+        SceneNodeId, WorldTransformSystem,
     },
-    Circle, PathBrush, PathEventsIter, Quad,
+    Circle, Color, FillStyle, PaintShader, PathBrush, PathEventsIter, Quad,
 };
 
 use super::{GpuGraphicsContext, GraphicsContextSystem};
 
+const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+    wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+const VERTEX_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &VERTEX_ATTRIBUTES,
+};
+
 pub(crate) struct GraphicsPipe {
-    pipeline: Option<wgpu::RenderPipeline>,
+    resources: Option<(ShaderId, PipelineLayoutId)>,
     #[allow(unused)]
     this: Item<Self>,
 }
@@ -27,80 +45,36 @@ impl RenderPipe for GraphicsPipe {
     where
         Self: Sized,
     {
-        let device = &cx.gpu.device;
-
-        let layout = cx.read_system(|sys: &GlobalUniformSystem, _| {
-            device.create_pipeline_layout(
-                &(wgpu::PipelineLayoutDescriptor {
-                    label: Some("Global Uniform Bind Group Layout"),
-                    bind_group_layouts: &[sys.get_bind_group_layout()],
-                    push_constant_ranges: &[],
-                }),
+        let globals = cx.read_system(|globals: &GlobalUniformSystem, _| {
+            globals.get_bind_group_layout().clone()
+        });
+        let world =
+            cx.read_system(|world: &WorldTransformSystem, _| world.get_bind_group_layout().clone());
+        let (paint, texture) = cx.read_system(|textures: &TextureSystem, _| {
+            (
+                textures.paint_layout().clone(),
+                textures.texture_layout().clone(),
             )
         });
 
-        let vbo_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4],
-        };
-
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Graphics Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../../render/shaders/ara.wgsl").into()),
+        let layout = cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.add_layout(
+                "Graphics Pipeline Layout",
+                &[&globals, &world, &paint, &texture],
+            )
         });
 
-        let blend = Some(wgpu::BlendState {
-            color: wgpu::BlendComponent {
-                src_factor: wgpu::BlendFactor::SrcAlpha,
-                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                operation: wgpu::BlendOperation::Add,
-            },
-            alpha: wgpu::BlendComponent::OVER,
+        let shader = cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.add_shader(
+                "Graphics Shader",
+                concat!(
+                    include_str!("../../render/shaders/paint.wgsl"),
+                    include_str!("../../render/shaders/ara.wgsl")
+                ),
+            )
         });
 
-        // todo move pipeline to pipeline system
-        let pipeline = device.create_render_pipeline(
-            &(wgpu::RenderPipelineDescriptor {
-                label: Some("Graphics Pipeline"),
-                layout: Some(&layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: Some("vs"),
-                    buffers: &[vbo_layout],
-                    compilation_options: Default::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: Some("fs_main"),
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: wgpu::TextureFormat::Rgba8Unorm,
-                        blend,
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::default(),
-                    cull_mode: None,
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
-            }),
-        );
-
-        self.pipeline.replace(pipeline);
+        self.resources = Some((shader, layout));
     }
 }
 
@@ -108,21 +82,72 @@ impl GraphicsPipe {
     pub fn new(cx: &mut ItemContext<Self>) -> Self {
         Self {
             this: cx.item(),
-            pipeline: None,
+            resources: None,
         }
     }
 
-    pub fn prepare(&self, cx: &mut RenderContext, context: &GraphicsContext) {
-        if !context.dirty.get() {
+    fn pipeline_key(
+        &self,
+        target: TargetFormat,
+        blend: wgpu::BlendState,
+        stencil: StencilMode,
+    ) -> Option<PipelineKey> {
+        let (shader, layout) = self.resources?;
+        Some(PipelineKey {
+            label: "Graphics Pipeline",
+            shader,
+            layout,
+            vertex_entry: "vs",
+            fragment_entry: if stencil.is_mask() {
+                "fs_mask"
+            } else {
+                "fs_main"
+            },
+            vertex_layouts: &[VERTEX_LAYOUT],
+            target,
+            blend: Some(blend),
+            color_writes: stencil.color_writes(),
+            depth_stencil: Some(stencil.depth_stencil_state()),
+        })
+    }
+
+    /// The pipeline drawing into `target` with a fixed function blend state
+    fn pipeline(
+        &self,
+        cx: &mut RenderContext,
+        target: TargetFormat,
+        blend: wgpu::BlendState,
+        stencil: StencilMode,
+    ) -> Option<wgpu::RenderPipeline> {
+        let key = self.pipeline_key(target, blend, stencil)?;
+        Some(cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.get_or_create(&key).clone()
+        }))
+    }
+
+    /// Builds the geometry of the context when it changed,
+    /// or when the pixel scale changed how its strokes are drawn
+    pub fn prepare(&self, cx: &mut RenderContext, context: &GraphicsContext, pixel_scale: f32) {
+        let rescaled = cx.read_system(|graphics_context_system: &GraphicsContextSystem, _| {
+            graphics_context_system
+                .get_cx(context)
+                .is_some_and(|gpu_context| {
+                    context.strokes_differ_at(gpu_context.pixel_scale, pixel_scale)
+                })
+        });
+
+        if !context.dirty.get() && !rescaled {
             log::debug!(
                 "Graphics context is not dirty, skipping rebuild for: {:?}",
                 context.id()
             );
+            cx.update_system(|stats: &mut RenderStatsSystem, _| stats.stats.contexts_reused += 1);
             return; // no need to rebuild reuse the old one
         }
+        cx.update_system(|stats: &mut RenderStatsSystem, _| stats.stats.contexts_rebuilt += 1);
 
         cx.update_system(|geometry_system: &mut GeometrySystem, cx| {
-            cx.update_system(|graphics_context_system: &mut GraphicsContextSystem, _| {
+            cx.update_system(|graphics_context_system: &mut GraphicsContextSystem, cx| {
                 context.dirty.set(false);
                 let gpu_context = graphics_context_system.get_or_init_cx(context, || {
                     GpuGraphicsContext::new(geometry_system.reserve())
@@ -137,21 +162,117 @@ impl GraphicsPipe {
                 let mut builder = GraphicsBuilder {
                     context,
                     batch: None,
+                    uv: None,
+                    pixel_scale,
                 };
 
                 gpu_context.clear();
+                gpu_context.pixel_scale = pixel_scale;
+
+                let mut blend_mode = BlendMode::Normal;
+                // shader of the previous draw and the transform it was tessellated with
+                let mut paint: Option<(&PaintShader, Mat3)> = None;
+                // texture of the previous draw, none when it was not textured
+                let mut texture: Option<TextureKey> = None;
+                // clips currently pushed, outermost first, with their mask geometry
+                let mut masks: Vec<(usize, RenderBufferRange)> = Vec::new();
+                // clip rect of the previous draw with its mask geometry
+                let mut clip: Option<(Rect<f32>, Option<RenderBufferRange>)> = None;
 
                 for batch in batched_graphics_iter {
                     let clip_rect = batch.clip_rect.clone();
+                    let clip_chain = context.clip_chain(batch.clip_path);
+                    let batch_blend_mode = batch.blend_mode;
+                    let batch_paint = batch.shader().map(|shader| (shader, *batch.transform));
+                    let batch_texture = match batch.texture {
+                        Some((source, _)) => {
+                            let bound = cx.update_system(|textures: &mut TextureSystem, _| {
+                                source.bind(textures)
+                            });
+                            if bound.is_none() {
+                                log::debug!("Texture is not in its atlas skipping: {:?}", source);
+                                continue;
+                            }
+                            bound
+                        }
+                        None => None,
+                    };
 
-                    builder.set_batch(batch);
+                    builder.set_batch(batch, batch_texture.as_ref().map(|bound| bound.uv.clone()));
 
                     let slice = geometry_system.append_data(handle, &mut builder);
 
-                    if !slice.is_empty() {
-                        gpu_context.add_command(RenderCommand::SetScissor { rect: clip_rect });
-                        gpu_context.add_command(RenderCommand::draw_indexed(handle, slice));
+                    if slice.is_empty() {
+                        continue;
                     }
+
+                    let shared = masks
+                        .iter()
+                        .zip(&clip_chain)
+                        .take_while(|((pushed, _), clip)| pushed == *clip)
+                        .count();
+
+                    while masks.len() > shared {
+                        let (_, mask) = masks.pop().expect("checked the length");
+                        gpu_context.add_command(RenderCommand::pop_mask(handle, mask));
+                    }
+
+                    for &clip in &clip_chain[shared..] {
+                        let mask = geometry_system.append_data(
+                            handle,
+                            &mut MaskBuilder {
+                                context,
+                                clip: &context.clips[clip],
+                            },
+                        );
+                        gpu_context.add_command(RenderCommand::push_mask(handle, mask.clone()));
+                        masks.push((clip, mask));
+                    }
+
+                    if batch_blend_mode != blend_mode {
+                        blend_mode = batch_blend_mode;
+                        gpu_context.add_command(RenderCommand::set_blend_mode(blend_mode));
+                    }
+                    if batch_paint != paint {
+                        paint = batch_paint;
+                        let bind_group =
+                            cx.update_system(|textures: &mut TextureSystem, _| match paint {
+                                Some((shader, transform)) => textures.paint(shader, &transform),
+                                None => textures.solid_paint().clone(),
+                            });
+                        gpu_context.add_command(RenderCommand::set_paint(bind_group));
+                    }
+                    // draws are split where the texture changes
+                    if batch_texture.as_ref().map(|bound| bound.key) != texture {
+                        texture = batch_texture.as_ref().map(|bound| bound.key);
+                        let bind_group = match batch_texture {
+                            Some(bound) => bound.bind_group,
+                            None => cx.read_system(|textures: &TextureSystem, _| {
+                                textures.white_texture().clone()
+                            }),
+                        };
+                        gpu_context.add_command(RenderCommand::set_texture(bind_group));
+                    }
+                    let clip_mask = match &clip {
+                        Some((rect, mask)) if *rect == clip_rect => mask.clone(),
+                        _ => {
+                            let mask = (clip_rect != Rect::EVERYTHING).then(|| {
+                                geometry_system
+                                    .append_data(handle, &mut RectMaskBuilder { rect: &clip_rect })
+                            });
+                            clip = Some((clip_rect.clone(), mask.clone()));
+                            mask
+                        }
+                    };
+                    gpu_context.add_command(RenderCommand::SetScissor {
+                        rect: clip_rect,
+                        mask: clip_mask.map(|mask| (handle, mask)),
+                    });
+                    gpu_context.add_command(RenderCommand::draw_indexed(handle, slice));
+                }
+
+                while let Some((_, mask)) = masks.pop() {
+                    gpu_context.add_command(RenderCommand::pop_mask(handle, mask));
                 }
 
                 geometry_system.sync(handle);
@@ -161,75 +282,223 @@ impl GraphicsPipe {
 
     pub fn execute(
         &self,
-        pass: &mut wgpu::RenderPass,
-        viewport: Size<u32>,
+        paint: &mut PaintContext,
         cx: &mut RenderContext,
+        node: SceneNodeId,
         context: &GraphicsContext,
     ) {
-        let Some(pipeline) = self.pipeline.as_ref() else {
-            log::warn!("GraphicsPipe not initialized");
+        let Some(commands) =
+            cx.read_system(|graphics_context_system: &GraphicsContextSystem, _| {
+                graphics_context_system
+                    .get_cx(context)
+                    .map(|gpu_context| gpu_context.commands.clone())
+            })
+        else {
+            log::debug!("Empty context skipping: {:?}", context.id());
+            return;
+        };
+
+        let Some(offset) = cx.read_system(|world: &WorldTransformSystem, _| world.get_offset(node))
+        else {
+            log::debug!("Node was not prepared skipping: {}", node);
+            return;
+        };
+
+        let Some(world) = cx.read_system(|world: &WorldTransformSystem, _| world.get(node)) else {
+            log::debug!("Node was not prepared skipping: {}", node);
             return;
         };
 
-        cx.read_system(|graphics_context_system: &GraphicsContextSystem, cx| {
-            let Some(gpu_context) = graphics_context_system.get_cx(context) else {
-                log::debug!("Empty context skipping: {:?}", context.id());
+        let viewport = paint.viewport();
+        let target = paint.target_format();
+        // clip rects are in the space of the node
+        let resolution = paint.resolution();
+        let to_pixels = world.transform * Mat3::from_scale(resolution, resolution);
+        let mut blend_mode = BlendMode::Normal;
+        let (solid_paint, white_texture) = cx.read_system(|textures: &TextureSystem, _| {
+            (
+                textures.solid_paint().clone(),
+                textures.white_texture().clone(),
+            )
+        });
+        let mut paint_group = solid_paint.clone();
+        let mut texture_group = white_texture.clone();
+        let mut scissor = ScissorRect::new(&Rect::EVERYTHING, &to_pixels, &viewport);
+        let normal = BlendMode::Normal.blend_state().expect("normal is fixed");
+
+        let draw = |paint: &mut PaintContext,
+                    cx: &mut RenderContext,
+                    blend: wgpu::BlendState,
+                    paint_group: &wgpu::BindGroup,
+                    texture_group: &wgpu::BindGroup,
+                    scissor: &ScissorRect,
+                    geometry_handle: GeometryHandle,
+                    slice: &RenderBufferRange| {
+            let Some(pipeline) = self.pipeline(cx, target, blend, paint.stencil_mode()) else {
+                log::warn!("GraphicsPipe is not initialized");
                 return;
             };
 
-            pass.set_pipeline(pipeline);
+            let pass = paint.pass();
+            pass.set_pipeline(&pipeline);
             cx.read_system(|sys: &GlobalUniformSystem, _| {
                 pass.set_bind_group(0, sys.get_bind_group(), &[]);
             });
+            cx.read_system(|world: &WorldTransformSystem, _| {
+                pass.set_bind_group(1, world.get_bind_group(), &[offset]);
+            });
+            pass.set_bind_group(2, paint_group, &[]);
+            pass.set_bind_group(3, texture_group, &[]);
+            pass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
 
             cx.read_system(|geometry_system: &GeometrySystem, _| {
-                /* End Read geometry system */
-                for command in &gpu_context.commands {
-                    match command {
-                        RenderCommand::SetScissor { rect } => {
-                            let scissor = ScissorRect::new(rect, &viewport);
-                            pass.set_scissor_rect(
-                                scissor.x,
-                                scissor.y,
-                                scissor.width,
-                                scissor.height,
-                            );
-                        }
-                        RenderCommand::DrawIndexed {
-                            geometry_handle,
-                            render_buffer_slice,
-                        } => {
-                            if let Some(buffer) =
-                                geometry_system.get(*geometry_handle, render_buffer_slice)
-                            {
-                                pass.set_vertex_buffer(0, buffer.vertex_buffer);
-
-                                pass.set_index_buffer(
-                                    buffer.index_buffer,
-                                    wgpu::IndexFormat::Uint32,
-                                );
-
-                                pass.draw_indexed(0..buffer.index_count, 0, 0..1);
-                            }
+                if let Some(buffer) = geometry_system.get(geometry_handle, slice) {
+                    pass.set_vertex_buffer(0, buffer.vertex_buffer);
+                    pass.set_index_buffer(buffer.index_buffer, wgpu::IndexFormat::Uint32);
+                    pass.draw_indexed(0..buffer.index_count, 0, 0..1);
+                }
+            });
+        };
+
+        // a graphics drawn as a mask only contributes its coverage
+        let masking = paint.stencil_mode().is_mask();
+        let unclipped = ScissorRect::new(&Rect::EVERYTHING, &to_pixels, &viewport);
+        // a clip rect the node does not keep axis aligned is masked instead of scissored,
+        // its mask is pushed before the next draw and popped before any other mask changes
+        let scissor_clips = masking || to_pixels.is_axis_aligned();
+        let mut clip_mask: Option<&(GeometryHandle, RenderBufferRange)> = None;
+        let mut clip_mask_pushed = false;
+        // modes reading the backdrop draw into a layer which is blended afterwards,
+        // consecutive draws with the same mode share one until a mask changes
+        let mut layer: Option<BlendMode> = None;
+        let end_layer =
+            |paint: &mut PaintContext, cx: &mut RenderContext, layer: &mut Option<BlendMode>| {
+                if let Some(mode) = layer.take() {
+                    paint.pop_layer(cx, mode, 1.0);
+                }
+            };
+
+        let mask_draw = |paint: &mut PaintContext,
+                         cx: &mut RenderContext,
+                         mask: &(GeometryHandle, RenderBufferRange)| {
+            draw(
+                paint,
+                cx,
+                normal,
+                &solid_paint,
+                &white_texture,
+                &unclipped,
+                mask.0,
+                &mask.1,
+            )
+        };
+        let pop_clip_mask = |paint: &mut PaintContext,
+                             cx: &mut RenderContext,
+                             clip_mask: Option<&(GeometryHandle, RenderBufferRange)>,
+                             pushed: &mut bool| {
+            if let Some(mask) = clip_mask.filter(|_| *pushed) {
+                paint.pop_mask(|paint| mask_draw(paint, cx, mask));
+                *pushed = false;
+            }
+        };
+
+        for command in &commands {
+            match command {
+                RenderCommand::SetBlendMode { mode } => blend_mode = *mode,
+                RenderCommand::SetPaint { bind_group } => paint_group = bind_group.clone(),
+                RenderCommand::SetTexture { bind_group } => texture_group = bind_group.clone(),
+                RenderCommand::SetScissor { rect, mask } => {
+                    let mask = mask.as_ref().filter(|_| !scissor_clips);
+                    if mask != clip_mask {
+                        if clip_mask_pushed {
+                            end_layer(paint, cx, &mut layer);
                         }
+                        pop_clip_mask(paint, cx, clip_mask, &mut clip_mask_pushed);
+                        clip_mask = mask;
                     }
+                    scissor = match mask {
+                        Some(_) => ScissorRect::new(&Rect::EVERYTHING, &to_pixels, &viewport),
+                        None => ScissorRect::new(rect, &to_pixels, &viewport),
+                    };
                 }
-                /* END Read Geometry System */
-            });
+                RenderCommand::PushMask {
+                    geometry_handle,
+                    render_buffer_slice,
+                } if !masking => {
+                    end_layer(paint, cx, &mut layer);
+                    pop_clip_mask(paint, cx, clip_mask, &mut clip_mask_pushed);
+                    paint.push_mask(|paint| {
+                        mask_draw(paint, cx, &(*geometry_handle, render_buffer_slice.clone()))
+                    })
+                }
+                RenderCommand::PopMask {
+                    geometry_handle,
+                    render_buffer_slice,
+                } if !masking => {
+                    end_layer(paint, cx, &mut layer);
+                    pop_clip_mask(paint, cx, clip_mask, &mut clip_mask_pushed);
+                    paint.pop_mask(|paint| {
+                        mask_draw(paint, cx, &(*geometry_handle, render_buffer_slice.clone()))
+                    })
+                }
+                RenderCommand::PushMask { .. } | RenderCommand::PopMask { .. } => {}
+                RenderCommand::DrawIndexed {
+                    geometry_handle,
+                    render_buffer_slice,
+                } => {
+                    let (layer_mode, blend) = match blend_mode.blend_state() {
+                        _ if masking => (None, normal),
+                        Some(blend) => (None, blend),
+                        None => (Some(blend_mode), normal),
+                    };
+                    let push_clip_mask = clip_mask.filter(|_| !clip_mask_pushed);
+                    if layer != layer_mode || push_clip_mask.is_some() {
+                        end_layer(paint, cx, &mut layer);
+                    }
 
-            pass.set_scissor_rect(0, 0, viewport.width, viewport.height);
-        });
+                    if let Some(mask) = push_clip_mask {
+                        paint.push_mask(|paint| mask_draw(paint, cx, mask));
+                        clip_mask_pushed = true;
+                    }
+                    if layer.is_none() && layer_mode.is_some() {
+                        paint.push_layer(cx);
+                        layer = layer_mode;
+                    }
+
+                    draw(
+                        paint,
+                        cx,
+                        blend,
+                        &paint_group,
+                        &texture_group,
+                        &scissor,
+                        *geometry_handle,
+                        render_buffer_slice,
+                    );
+                }
+            }
+        }
+        end_layer(paint, cx, &mut layer);
+        pop_clip_mask(paint, cx, clip_mask, &mut clip_mask_pushed);
+
+        paint
+            .pass()
+            .set_scissor_rect(0, 0, viewport.width, viewport.height);
     }
 }
 
 struct GraphicsBuilder<'a> {
     batch: Option<BatchedGraphicsInstruction<'a>>,
     context: &'a GraphicsContext,
+    /// area of the texture in uv space a textured batch is drawn with
+    uv: Option<Rect<f32>>,
+    pixel_scale: f32,
 }
 
 impl<'a> GraphicsBuilder<'a> {
-    fn set_batch(&mut self, batch: BatchedGraphicsInstruction<'a>) {
+    fn set_batch(&mut self, batch: BatchedGraphicsInstruction<'a>, uv: Option<Rect<f32>>) {
         self.batch = Some(batch);
+        self.uv = uv;
     }
 }
 
@@ -241,17 +510,55 @@ impl GeometryBuilder for GraphicsBuilder<'_> {
         let mut brush = PathBrush::default();
 
         drawlist.feathering(2.0);
+        drawlist.pixel_scale(self.pixel_scale);
         brush.default.antialias = true;
 
         let transform = *batch.transform;
 
-        if let Some(fill) = batch.fill {
-            brush.default.fill_style = *fill;
-        }
+        // a path brush fills white by default, strokes alone must not fill
+        brush.default.fill_style = batch.fill.cloned().unwrap_or_default();
 
         if let Some(stroke) = batch.stroke {
-            brush.default.stroke_style = *stroke;
+            brush.default.stroke_style = stroke.clone();
+        }
+
+        if let Some((_, tint)) = batch.texture {
+            brush.default.fill_style = FillStyle::default().color(tint);
+        }
+
+        let range = drawlist.capture_range(|drawlist| self.add_paths(drawlist, &brush, &transform));
+
+        // the texture is stretched over the bounds of the path before it was transformed
+        if let (Some(uv), Some(bounds)) = (&self.uv, self.local_bounds()) {
+            if transform.det() == 0.0 {
+                return;
+            }
+            let inverse = transform.inverse();
+            let (min, size) = (bounds.min(), bounds.size());
+            drawlist.map_range(range, |vertex| {
+                let local = inverse * vec2(vertex.position[0], vertex.position[1]);
+                vertex.uv = [
+                    uv.x() + (local.x - min.x) / size.width.max(f32::EPSILON) * uv.width(),
+                    uv.y() + (local.y - min.y) / size.height.max(f32::EPSILON) * uv.height(),
+                ];
+            });
         }
+    }
+}
+
+impl GraphicsBuilder<'_> {
+    fn local_bounds(&self) -> Option<Rect<f32>> {
+        let batch = self.batch.as_ref()?;
+        batch
+            .path_instructions
+            .iter()
+            .map(|instruction| instruction.bounds(&self.context.path.builder))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    fn add_paths(&self, drawlist: &mut crate::DrawList, brush: &PathBrush, transform: &Mat3) {
+        let batch = self.batch.as_ref().expect("Expected a batch");
+        let transform = *transform;
 
         for instruction in batch.path_instructions {
             match instruction {
@@ -289,13 +596,59 @@ impl GeometryBuilder for GraphicsBuilder<'_> {
 
                     let iter = PathEventsIter::new(points, verbs);
 
-                    drawlist.add_path(iter, &brush, Some(transform));
+                    drawlist.add_path(iter, brush, Some(transform));
                 }
             }
         }
     }
 }
 
+/// Fills a clip path without antialiasing, the stencil only records coverage
+struct MaskBuilder<'a> {
+    context: &'a GraphicsContext,
+    clip: &'a GraphicsClip,
+}
+
+impl GeometryBuilder for MaskBuilder<'_> {
+    fn build(&mut self, drawlist: &mut crate::DrawList) {
+        let mut brush = PathBrush::default();
+        brush.default.fill_style = FillStyle::default().color(Color::WHITE);
+        drawlist.feathering(0.0);
+        drawlist.pixel_scale(1.0);
+
+        let clip_paths = &self.context.clip_paths;
+        for instruction in clip_paths.get_entry(self.clip.path) {
+            if let GfxPathInstruction::Path { points, verbs } = instruction {
+                let iter = PathEventsIter::new(
+                    &clip_paths.builder.points[points.clone()],
+                    &clip_paths.builder.verbs[verbs.clone()],
+                );
+                drawlist.add_path(iter, &brush, Some(self.clip.transform));
+            }
+        }
+    }
+}
+
+/// Fills a clip rect in the space of the node, for nodes which do not keep it axis aligned
+struct RectMaskBuilder<'a> {
+    rect: &'a Rect<f32>,
+}
+
+impl GeometryBuilder for RectMaskBuilder<'_> {
+    fn build(&mut self, drawlist: &mut crate::DrawList) {
+        let mut brush = PathBrush::default();
+        brush.default.fill_style = FillStyle::default().color(Color::WHITE);
+        drawlist.feathering(0.0);
+        drawlist.pixel_scale(1.0);
+        drawlist.add_quad(
+            &Quad::default().rect(self.rect.clone()),
+            &brush.default,
+            false,
+            None,
+        );
+    }
+}
+
 struct ScissorRect {
     x: u32,
     y: u32,
@@ -304,7 +657,19 @@ struct ScissorRect {
 }
 
 impl ScissorRect {
-    fn new(clip_rect: &Rect<f32>, screen_size: &Size<u32>) -> Self {
+    /// Scissor covering the clip rect after `transform`,
+    /// exact when the transform keeps the rect axis aligned
+    fn new(clip_rect: &Rect<f32>, transform: &Mat3, screen_size: &Size<u32>) -> Self {
+        if *clip_rect == Rect::EVERYTHING {
+            return Self {
+                x: 0,
+                y: 0,
+                width: screen_size.width,
+                height: screen_size.height,
+            };
+        }
+
+        let clip_rect = &transform.transform_rect(clip_rect);
         let clip_min = clip_rect.min().round().map(|v| v as u32);
         let clip_max = clip_rect.max().round().map(|v| v as u32);
 
@@ -1,21 +1,29 @@
 pub mod container;
 pub mod graphics;
 pub mod node;
+pub mod sprite;
+pub mod text;
 
 pub use container::*;
 pub use graphics::*;
 pub use node::*;
+pub use sprite::*;
+pub use text::*;
 
 use crate::render::Plugin;
 
-use self::pipe::GraphicsPipe;
+use self::{graphics::pipe::GraphicsPipe, sprite::pipe::SpritePipe};
 
-/// This plugin allows to render Graphics, Containers etc... This is registred by default
+/// This plugin allows to render Graphics, Sprites, Text, Containers etc... This is registred by default
 pub struct ScenePlugin;
 
 impl Plugin for ScenePlugin {
     fn setup(&self, renderer: &mut crate::render::Renderer) {
-        renderer.add_system(GraphicsContextSystem::new);
-        renderer.add_pipe(GraphicsPipe::new);
+        renderer
+            .add_system(GraphicsContextSystem::new)
+            .add_system(WorldTransformSystem::new);
+        renderer
+            .add_pipe(GraphicsPipe::new)
+            .add_pipe(SpritePipe::new);
     }
 }
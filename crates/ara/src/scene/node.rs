@@ -1,20 +1,173 @@
 pub mod id;
 
-use ara_math::Size;
+use ara_math::{Mat3, Rect};
 pub use id::*;
+use std::sync::Arc;
+mod display;
+pub use display::*;
 mod parent;
 pub use parent::*;
+mod state;
+pub use state::*;
+mod tree;
+pub use tree::*;
+mod world;
+pub use world::*;
 
-use crate::render::RenderContext;
+use crate::{
+    render::{PaintContext, RenderContext, RenderStatsSystem},
+    Point,
+};
 
 pub trait SceneNode: SceneNodeIdentifier + IntoSceneNode + 'static {
     fn prepare(&self, render_context: &mut RenderContext);
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: Size<u32>,
-        render_context: &mut RenderContext,
-    );
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut RenderContext);
+
+    fn display(&self) -> DisplayState {
+        DisplayState::default()
+    }
+
+    /// Bounds of the geometry drawn by this node itself in its local space
+    fn content_bounds(&self) -> Option<Rect<f32>> {
+        None
+    }
+
+    /// Exact test against the geometry drawn by this node itself, the point is in local space
+    fn content_contains_point(&self, _point: Point) -> bool {
+        false
+    }
+
+    fn with_children(&self, _f: &mut dyn FnMut(&ChildrenStore)) {}
+
+    fn update_children(&self, _f: &mut dyn FnMut(&mut ChildrenStore)) {}
+
+    /// Parent link and change tracking of the node, nodes without state are prepared every frame
+    fn state(&self) -> Option<&NodeState> {
+        None
+    }
+
+    /// The node this node is a child of, nodes without state are never linked to a parent
+    fn parent(&self) -> Option<AnyNode> {
+        self.state()?.parent()?.upgrade()
+    }
+
+    /// Removes the node from its parent, returns false if it has none
+    fn remove_from_parent(&self) -> bool {
+        let Some(parent) = self.parent() else {
+            return false;
+        };
+        let mut removed = None;
+        parent.update_children(&mut |store| removed = store.remove_child(self.id()));
+        removed.is_some()
+    }
+
+    /// Called instead of `prepare` when nothing changed in the subtree since it was last prepared
+    fn reuse(&self, render_context: &mut RenderContext) {
+        reuse_subtree(self, render_context);
+    }
+
+    /// Bounds of the node and its descendants in the local space of the node
+    fn local_bounds(&self) -> Option<Rect<f32>> {
+        let mut bounds = self.content_bounds();
+        self.with_children(&mut |children| {
+            for child in &children.nodes {
+                bounds = union_bounds(bounds.take(), child.bounds(&Mat3::IDENTITY));
+            }
+        });
+        bounds
+    }
+
+    /// Bounds of the node and its descendants with `parent_transform` applied after the transform of the node
+    fn bounds(&self, parent_transform: &Mat3) -> Option<Rect<f32>> {
+        let display = self.display();
+        if !display.visible || !display.renderable {
+            return None;
+        }
+
+        let transform = display.local_transform() * *parent_transform;
+
+        let mut bounds = self
+            .content_bounds()
+            .map(|content| transform.transform_rect(&content));
+
+        self.with_children(&mut |children| {
+            for child in &children.nodes {
+                bounds = union_bounds(bounds.take(), child.bounds(&transform));
+            }
+        });
+
+        bounds
+    }
+
+    /// World bounds of the descendant with the given id
+    fn find_bounds(&self, id: SceneNodeId, parent_transform: &Mat3) -> Option<Rect<f32>> {
+        if self.id() == id {
+            return self.bounds(parent_transform);
+        }
+
+        let display = self.display();
+        if !display.visible || !display.renderable {
+            return None;
+        }
+
+        let transform = display.local_transform() * *parent_transform;
+
+        let mut bounds = None;
+        self.with_children(&mut |children| {
+            bounds = children
+                .iter()
+                .find_map(|child| child.find_bounds(id, &transform));
+        });
+        bounds
+    }
+
+    /// Returns the top most node under the point, the point is in the space of the parent.
+    /// Children are tested before the node itself as they are drawn on top of it
+    fn hit_test(&self, point: Point) -> Option<SceneNodeId> {
+        let display = self.display();
+        if !display.visible || !display.renderable {
+            return None;
+        }
+
+        let transform = display.local_transform();
+        if transform.det() == 0.0 {
+            return None;
+        }
+        let local_point = transform.inverse() * point;
+
+        let mut hit = None;
+        self.with_children(&mut |children| {
+            hit = children
+                .iter()
+                .rev()
+                .find_map(|child| child.hit_test(local_point));
+        });
+
+        hit.or_else(|| self.content_contains_point(local_point).then(|| self.id()))
+    }
+}
+
+/// Keeps everything prepared for the node and its visible descendants in the previous frames
+pub(crate) fn reuse_subtree<N: SceneNode>(node: &N, render_context: &mut RenderContext) {
+    render_context.update_system(|world: &mut WorldTransformSystem, _| world.retain(node.id()));
+    render_context.update_system(|stats: &mut RenderStatsSystem, _| stats.stats.nodes_reused += 1);
+
+    if !node.display().visible {
+        return;
+    }
+
+    node.with_children(&mut |children| {
+        for child in children {
+            child.reuse(render_context);
+        }
+    });
+}
+
+fn union_bounds(a: Option<Rect<f32>>, b: Option<Rect<f32>>) -> Option<Rect<f32>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(&b)),
+        (a, b) => a.or(b),
+    }
 }
 
 pub trait ParentNode: 'static {
@@ -36,6 +189,35 @@ pub trait ParentNode: 'static {
 pub trait RenderRoot {
     type Node: SceneNode;
     fn node(&self) -> &Self::Node;
+
+    fn parent(&self) -> Option<AnyNode> {
+        self.node().parent()
+    }
+
+    /// Removes the node from its parent, returns false if it has none
+    fn remove_from_parent(&self) -> bool {
+        self.node().remove_from_parent()
+    }
+
+    /// Bounds of the node and its descendants in the local space of the node
+    fn local_bounds(&self) -> Rect<f32> {
+        self.node().local_bounds().unwrap_or_default()
+    }
+
+    /// Returns the top most node under the point, either this node or one of its descendants
+    fn hit_test(&self, point: Point) -> Option<SceneNodeId> {
+        self.node().hit_test(point)
+    }
+}
+
+/// Returns the top most node under the point, the point is in the space the root is rendered to
+pub fn hit_test<R: RenderRoot>(root: &R, point: Point) -> Option<SceneNodeId> {
+    root.hit_test(point)
+}
+
+/// Bounds of the node with the given id in the space the root is rendered to
+pub fn world_bounds<R: RenderRoot>(root: &R, id: SceneNodeId) -> Option<Rect<f32>> {
+    root.node().find_bounds(id, &Mat3::IDENTITY)
 }
 
 pub trait IntoSceneNode: Sized {
@@ -60,13 +242,40 @@ impl<T: SceneNode> SceneNodeLike for NodeWrapper<T> {
         self.0.prepare(render_context);
     }
 
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: Size<u32>,
-        render_context: &mut RenderContext,
-    ) {
-        self.0.paint(pass, viewport, render_context);
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut RenderContext) {
+        self.0.paint(paint, render_context);
+    }
+
+    fn bounds(&self, parent_transform: &Mat3) -> Option<Rect<f32>> {
+        self.0.bounds(parent_transform)
+    }
+
+    fn find_bounds(&self, id: SceneNodeId, parent_transform: &Mat3) -> Option<Rect<f32>> {
+        self.0.find_bounds(id, parent_transform)
+    }
+
+    fn hit_test(&self, point: Point) -> Option<SceneNodeId> {
+        self.0.hit_test(point)
+    }
+
+    fn display(&self) -> DisplayState {
+        self.0.display()
+    }
+
+    fn with_children(&self, f: &mut dyn FnMut(&ChildrenStore)) {
+        self.0.with_children(f)
+    }
+
+    fn update_children(&self, f: &mut dyn FnMut(&mut ChildrenStore)) {
+        self.0.update_children(f)
+    }
+
+    fn state(&self) -> Option<&NodeState> {
+        self.0.state()
+    }
+
+    fn reuse(&self, render_context: &mut RenderContext) {
+        self.0.reuse(render_context);
     }
 }
 
@@ -74,16 +283,20 @@ impl<T: SceneNode> SceneNodeLike for NodeWrapper<T> {
 pub trait SceneNodeLike {
     fn id(&self) -> SceneNodeId;
     fn prepare(&self, render_context: &mut RenderContext);
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: Size<u32>,
-        render_context: &mut RenderContext,
-    );
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut RenderContext);
+    fn bounds(&self, parent_transform: &Mat3) -> Option<Rect<f32>>;
+    fn find_bounds(&self, id: SceneNodeId, parent_transform: &Mat3) -> Option<Rect<f32>>;
+    fn hit_test(&self, point: Point) -> Option<SceneNodeId>;
+    fn display(&self) -> DisplayState;
+    fn with_children(&self, f: &mut dyn FnMut(&ChildrenStore));
+    fn update_children(&self, f: &mut dyn FnMut(&mut ChildrenStore));
+    fn state(&self) -> Option<&NodeState>;
+    fn reuse(&self, render_context: &mut RenderContext);
 }
 
-// type erased node
-pub struct AnyNode(pub(crate) Box<dyn SceneNodeLike>);
+// type erased node, cloning it gives another handle to the same node
+#[derive(Clone)]
+pub struct AnyNode(pub(crate) Arc<dyn SceneNodeLike>);
 
 impl std::fmt::Debug for AnyNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -93,7 +306,23 @@ impl std::fmt::Debug for AnyNode {
 
 impl AnyNode {
     pub fn new<T: SceneNode>(node: T) -> Self {
-        Self(Box::new(NodeWrapper(node)))
+        Self(Arc::new(NodeWrapper(node)))
+    }
+
+    pub fn id(&self) -> SceneNodeId {
+        self.0.id()
+    }
+}
+
+impl SceneNodeIdentifier for AnyNode {
+    fn id(&self) -> SceneNodeId {
+        self.0.id()
+    }
+}
+
+impl SceneNodeIdentifier for &AnyNode {
+    fn id(&self) -> SceneNodeId {
+        self.0.id()
     }
 }
 
@@ -106,12 +335,86 @@ impl SceneNodeLike for AnyNode {
         self.0.prepare(render_context);
     }
 
-    fn paint<'encoder>(
-        &self,
-        pass: &mut wgpu::RenderPass<'encoder>,
-        viewport: Size<u32>,
-        render_context: &mut RenderContext,
-    ) {
-        self.0.paint(pass, viewport, render_context);
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut RenderContext) {
+        self.0.paint(paint, render_context);
+    }
+
+    fn bounds(&self, parent_transform: &Mat3) -> Option<Rect<f32>> {
+        self.0.bounds(parent_transform)
+    }
+
+    fn find_bounds(&self, id: SceneNodeId, parent_transform: &Mat3) -> Option<Rect<f32>> {
+        self.0.find_bounds(id, parent_transform)
+    }
+
+    fn hit_test(&self, point: Point) -> Option<SceneNodeId> {
+        self.0.hit_test(point)
+    }
+
+    fn display(&self) -> DisplayState {
+        self.0.display()
+    }
+
+    fn with_children(&self, f: &mut dyn FnMut(&ChildrenStore)) {
+        self.0.with_children(f)
+    }
+
+    fn update_children(&self, f: &mut dyn FnMut(&mut ChildrenStore)) {
+        self.0.update_children(f)
+    }
+
+    fn state(&self) -> Option<&NodeState> {
+        self.0.state()
+    }
+
+    fn reuse(&self, render_context: &mut RenderContext) {
+        self.0.reuse(render_context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        render::renderable::{DisplayObjectMut, View},
+        scene::{world_bounds, Container, Graphics, ParentNode, RenderRoot},
+        vec2, Color, Rect,
+    };
+
+    use super::hit_test;
+
+    #[test]
+    fn hit_test_respects_transforms_and_order() {
+        let mut stage = Container::new();
+        let mut group = Container::new();
+
+        let mut back = Graphics::new();
+        back.rect((0.0, 0.0, 100.0, 100.0)).fill(Color::RED);
+
+        let mut front = Graphics::new();
+        front.circle((50.0, 50.0), 25.0).fill(Color::WHITE);
+
+        group.child(&back).child(&front);
+        stage.child(&group);
+        group.set_position(vec2(100.0, 100.0));
+        group.set_scale(vec2(2.0, 2.0));
+
+        assert_eq!(hit_test(&stage, vec2(200.0, 200.0)), Some(front.node.id));
+        assert_eq!(hit_test(&stage, vec2(110.0, 110.0)), Some(back.node.id));
+        assert_eq!(hit_test(&stage, vec2(50.0, 50.0)), None);
+
+        assert_eq!(
+            world_bounds(&stage, front.node.id),
+            Some(Rect::xywh(150.0, 150.0, 100.0, 100.0))
+        );
+        assert_eq!(group.bounds(), Rect::xywh(100.0, 100.0, 200.0, 200.0));
+        assert_eq!(group.local_bounds(), Rect::xywh(0.0, 0.0, 100.0, 100.0));
+        assert!(stage.contains_point(vec2(290.0, 290.0)));
+
+        front.set_visible(false);
+        assert_eq!(hit_test(&stage, vec2(200.0, 200.0)), Some(back.node.id));
+
+        group.set_visible(false);
+        assert_eq!(hit_test(&stage, vec2(200.0, 200.0)), None);
+        assert_eq!(world_bounds(&stage, back.node.id), None);
     }
 }
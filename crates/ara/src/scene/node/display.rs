@@ -0,0 +1,162 @@
+use crate::{render::BlendMode, Mat3, Point};
+
+/// Local display state of a scene node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayState {
+    pub position: Point,
+    pub scale: Point,
+    /// rotation in radians
+    pub rotation: f32,
+    /// point in local space that is placed at `position`, scale and rotation happen around it
+    pub pivot: Point,
+    /// when false the node and its children are skipped entirely
+    pub visible: bool,
+    /// when false the node and its children are not drawn but still prepared
+    pub renderable: bool,
+    pub alpha: f32,
+    /// draw order among siblings, only used when the parent has sortable children
+    pub z_index: i32,
+    /// how the node is blended with what is behind it, other than `Normal` the node and its
+    /// children are drawn into a layer first
+    pub blend_mode: BlendMode,
+    /// draw the children into a layer which is composited with the alpha of the node,
+    /// so overlapping children do not show through each other
+    pub isolated: bool,
+}
+
+impl Default for DisplayState {
+    fn default() -> Self {
+        Self {
+            position: Point::new(0.0, 0.0),
+            scale: Point::new(1.0, 1.0),
+            rotation: 0.0,
+            pivot: Point::new(0.0, 0.0),
+            visible: true,
+            renderable: true,
+            alpha: 1.0,
+            z_index: 0,
+            blend_mode: BlendMode::Normal,
+            isolated: false,
+        }
+    }
+}
+
+impl DisplayState {
+    /// Transform from the local space of the node into the space of its parent
+    pub fn local_transform(&self) -> Mat3 {
+        Mat3::from_translation(-self.pivot.x, -self.pivot.y)
+            * Mat3::from_scale(self.scale.x, self.scale.y)
+            * Mat3::from_rotation(self.rotation)
+            * Mat3::from_translation(self.position.x, self.position.y)
+    }
+
+    /// Whether the children are drawn into a layer
+    pub fn needs_layer(&self) -> bool {
+        self.isolated || self.blend_mode != BlendMode::Normal
+    }
+}
+
+/// Accumulated state of a node after composing it with all of its ancestors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldState {
+    pub transform: Mat3,
+    /// alpha applied when drawing, relative to the closest layer
+    pub alpha: f32,
+    /// alpha the layer of the node is composited with, 1 for nodes without a layer
+    pub layer_alpha: f32,
+}
+
+impl Default for WorldState {
+    fn default() -> Self {
+        Self {
+            transform: Mat3::IDENTITY,
+            alpha: 1.0,
+            layer_alpha: 1.0,
+        }
+    }
+}
+
+impl WorldState {
+    pub fn child(&self, display: &DisplayState) -> Self {
+        let transform = display.local_transform() * self.transform;
+        let alpha = self.alpha * display.alpha.clamp(0.0, 1.0);
+
+        // the alpha of a layered node is applied once to the whole layer
+        if display.needs_layer() {
+            Self {
+                transform,
+                alpha: 1.0,
+                layer_alpha: alpha,
+            }
+        } else {
+            Self {
+                transform,
+                alpha,
+                layer_alpha: 1.0,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2;
+
+    #[test]
+    fn local_transform_applies_pivot_first() {
+        let display = DisplayState {
+            position: vec2(100.0, 100.0),
+            scale: vec2(2.0, 2.0),
+            pivot: vec2(10.0, 10.0),
+            ..Default::default()
+        };
+
+        let transform = display.local_transform();
+
+        assert_eq!(transform * vec2(10.0, 10.0), vec2(100.0, 100.0));
+        assert_eq!(transform * vec2(20.0, 10.0), vec2(120.0, 100.0));
+    }
+
+    #[test]
+    fn world_state_composes_with_parent() {
+        let parent = WorldState::default().child(&DisplayState {
+            position: vec2(50.0, 0.0),
+            alpha: 0.5,
+            ..Default::default()
+        });
+
+        let child = parent.child(&DisplayState {
+            position: vec2(0.0, 25.0),
+            scale: vec2(2.0, 2.0),
+            alpha: 0.5,
+            ..Default::default()
+        });
+
+        assert_eq!(child.transform * vec2(1.0, 1.0), vec2(52.0, 27.0));
+        assert_eq!(child.alpha, 0.25);
+    }
+
+    #[test]
+    fn isolated_nodes_move_alpha_to_their_layer() {
+        let parent = WorldState::default().child(&DisplayState {
+            alpha: 0.5,
+            ..Default::default()
+        });
+
+        let group = parent.child(&DisplayState {
+            alpha: 0.5,
+            isolated: true,
+            ..Default::default()
+        });
+        assert_eq!(group.alpha, 1.0);
+        assert_eq!(group.layer_alpha, 0.25);
+
+        let child = group.child(&DisplayState {
+            alpha: 0.5,
+            ..Default::default()
+        });
+        assert_eq!(child.alpha, 0.5);
+        assert_eq!(child.layer_alpha, 1.0);
+    }
+}
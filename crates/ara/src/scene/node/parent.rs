@@ -1,30 +1,267 @@
 use core::fmt;
 
-use super::{AnyNode, IntoSceneNode, ParentNode, SceneNodeIdentifier, SceneNodeLike};
+use super::{
+    AnyNode, IntoSceneNode, ParentNode, SceneNodeId, SceneNodeIdentifier, SceneNodeLike,
+    SceneTreeError, WeakNode,
+};
 
 #[derive(Default)]
-pub struct ChildrenStore(pub(crate) Vec<AnyNode>);
+pub struct ChildrenStore {
+    pub(crate) nodes: Vec<AnyNode>,
+    /// when enabled children are drawn in the order of their z_index
+    pub(crate) sortable: bool,
+    /// the node this store belongs to, set as the parent of every attached child
+    owner: Option<WeakNode>,
+}
+
 impl ChildrenStore {
+    pub(crate) fn with_owner(owner: WeakNode) -> Self {
+        Self {
+            owner: Some(owner),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn owner(&self) -> Option<&WeakNode> {
+        self.owner.as_ref()
+    }
+
+    pub fn owner_id(&self) -> Option<SceneNodeId> {
+        self.owner.as_ref().map(|owner| owner.id())
+    }
+
+    /// Fails if the node is the owner of this store or one of its ancestors
+    pub fn check_child(&self, node: impl SceneNodeIdentifier) -> Result<(), SceneTreeError> {
+        let Some(owner) = self.owner.as_ref().and_then(|owner| owner.upgrade()) else {
+            return Ok(());
+        };
+        let child = node.id();
+
+        if std::iter::once(owner.clone())
+            .chain(owner.ancestors())
+            .any(|node| node.id() == child)
+        {
+            return Err(SceneTreeError::Cycle {
+                parent: owner.id(),
+                child,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Links the node to the owner, removing it from its previous parent first
+    fn attach(&mut self, node: &AnyNode) -> Result<(), SceneTreeError> {
+        self.check_child(node)?;
+
+        let id = node.id();
+        if let Some(previous) = node.0.state().and_then(|state| state.parent()) {
+            if Some(previous.id()) == self.owner_id() {
+                self.nodes.retain(|child| child.id() != id);
+            } else if let Some(previous) = previous.upgrade() {
+                previous.update_children(&mut |store| store.nodes.retain(|child| child.id() != id));
+                if let Some(state) = previous.0.state() {
+                    state.mark_subtree_dirty();
+                }
+            }
+        }
+
+        if let Some(state) = node.0.state() {
+            state.set_parent(self.owner.clone());
+        }
+        Ok(())
+    }
+
+    /// Unlinks a node that was removed from this store
+    fn release(&self, node: &AnyNode) {
+        let Some(state) = node.0.state() else {
+            return;
+        };
+        if self.owner.is_some() && state.parent().map(|parent| parent.id()) == self.owner_id() {
+            state.set_parent(None);
+        }
+        self.mark_changed();
+    }
+
+    /// Marks the owner as having changed children
+    fn mark_changed(&self) {
+        let Some(owner) = self.owner.as_ref().and_then(|owner| owner.upgrade()) else {
+            return;
+        };
+        if let Some(state) = owner.0.state() {
+            state.mark_subtree_dirty();
+        }
+    }
+
+    /// Appends the node, fails without modifying the tree if that would create a cycle
+    pub fn try_add_child(&mut self, node: impl IntoSceneNode) -> Result<(), SceneTreeError> {
+        self.push(node.into_any_node())
+    }
+
+    fn push(&mut self, node: AnyNode) -> Result<(), SceneTreeError> {
+        self.attach(&node)?;
+        self.nodes.push(node);
+        Ok(())
+    }
+
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.nodes.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.nodes.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, AnyNode> {
+        self.nodes.iter()
+    }
+
+    pub fn get_child_at(&self, index: usize) -> Option<&AnyNode> {
+        self.nodes.get(index)
+    }
+
+    pub fn get_child_by_id(&self, node: impl SceneNodeIdentifier) -> Option<&AnyNode> {
+        let id = node.id();
+        self.nodes.iter().find(|child| child.id() == id)
+    }
+
+    pub fn get_child_index(&self, node: impl SceneNodeIdentifier) -> Option<usize> {
+        let id = node.id();
+        self.nodes.iter().position(|child| child.id() == id)
+    }
+
+    /// Inserts the node at `index`, shifting all children after it.
+    /// Panics if `index > len`
+    /// Nodes that would create a cycle are not inserted
+    pub fn insert_child_at(&mut self, index: usize, node: impl IntoSceneNode) {
+        assert!(index <= self.nodes.len(), "insertion index out of bounds");
+        let node = node.into_any_node();
+        if let Err(err) = self.attach(&node) {
+            log::error!("{err}");
+            return;
+        }
+        // the node may have been a child already, in which case the store shrunk
+        let index = index.min(self.nodes.len());
+        self.nodes.insert(index, node);
+    }
+
+    pub fn remove_child(&mut self, node: impl SceneNodeIdentifier) -> Option<AnyNode> {
+        let index = self.get_child_index(node)?;
+        self.remove_child_at(index)
+    }
+
+    pub fn remove_child_at(&mut self, index: usize) -> Option<AnyNode> {
+        let node = (index < self.nodes.len()).then(|| self.nodes.remove(index))?;
+        self.release(&node);
+        Some(node)
+    }
+
+    pub fn clear(&mut self) {
+        for node in std::mem::take(&mut self.nodes) {
+            self.release(&node);
+        }
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&AnyNode) -> bool) {
+        let (kept, removed) = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .partition(|node| f(node));
+        self.nodes = kept;
+        for node in removed {
+            self.release(&node);
+        }
+    }
+
+    /// Swaps the position of two children, returns false if either of them is not a child
+    pub fn swap_children(
+        &mut self,
+        a: impl SceneNodeIdentifier,
+        b: impl SceneNodeIdentifier,
+    ) -> bool {
+        let (Some(a), Some(b)) = (self.get_child_index(a), self.get_child_index(b)) else {
+            return false;
+        };
+        self.nodes.swap(a, b);
+        true
+    }
+
+    /// Moves the child to `index`, the index is clamped to the last position.
+    /// Returns false if the node is not a child
+    pub fn set_child_index(&mut self, node: impl SceneNodeIdentifier, index: usize) -> bool {
+        let Some(current) = self.get_child_index(node) else {
+            return false;
+        };
+        let child = self.nodes.remove(current);
+        let index = index.min(self.nodes.len());
+        self.nodes.insert(index, child);
+        true
+    }
+
+    /// Removes the node from this store or from the store of any descendant and returns it,
+    /// so it can be attached to another parent
+    pub fn detach_from_parent(&mut self, node: impl SceneNodeIdentifier) -> Option<AnyNode> {
+        let id = node.id();
+
+        if let Some(child) = self.remove_child(id) {
+            return Some(child);
+        }
+
+        self.nodes.iter().find_map(|child| {
+            let mut detached = None;
+            child.update_children(&mut |store| detached = store.detach_from_parent(id));
+            detached
+        })
+    }
+
+    pub fn is_sortable(&self) -> bool {
+        self.sortable
+    }
+
+    pub fn set_sortable(&mut self, sortable: bool) {
+        self.sortable = sortable;
+    }
+
+    /// Stable sort of the children by their z_index, children with the same z_index keep their order
+    pub fn sort_children(&mut self) {
+        if self.nodes.is_sorted_by_key(|child| child.display().z_index) {
+            return;
+        }
+        self.nodes
+            .sort_by_cached_key(|child| child.display().z_index);
+    }
+
+    pub(crate) fn sort_if_needed(&mut self) {
+        if self.sortable {
+            self.sort_children();
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a ChildrenStore {
+    type Item = &'a AnyNode;
+    type IntoIter = std::slice::Iter<'a, AnyNode>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.iter()
     }
 }
 
 impl fmt::Debug for ChildrenStore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("ChildrenContainer")
-            .field(&self.0.len())
+            .field(&self.nodes.len())
             .finish()
     }
 }
 
 impl ParentNode for ChildrenStore {
+    /// Nodes that would create a cycle are skipped
     fn extend(&mut self, nodes: impl Iterator<Item = AnyNode>) {
-        self.0.extend(nodes)
+        for node in nodes {
+            if let Err(err) = self.push(node) {
+                log::error!("{err}");
+            }
+        }
     }
 }
 
@@ -47,14 +284,14 @@ impl ChildrenAccessMut for ChildrenStore {
 // Default implementation for ParentNode providing child manipulation methods
 pub trait ChildrenController: ChildrenAccessMut + ParentNode {
     fn clear_children(&mut self) {
-        self.with_children_mut(|store| store.0.clear());
+        self.with_children_mut(|store| store.clear());
     }
 
     fn replace_children(&mut self, nodes: impl IntoIterator<Item = impl IntoSceneNode>) {
         self.with_children_mut(|store| {
-            store.0.clear();
+            store.clear();
             let nodes = nodes.into_iter().map(|node| node.into_any_node());
-            store.0.extend(nodes);
+            store.extend(nodes);
         });
     }
 
@@ -66,16 +303,135 @@ pub trait ChildrenController: ChildrenAccessMut + ParentNode {
         self.with_children_mut(|store| {
             let ids: ahash::HashSet<_> = nodes.into_iter().map(|n| n.id()).collect();
 
-            store.0.retain(|c| !ids.contains(&c.id()));
+            store.retain(|c| !ids.contains(&c.id()));
         });
     }
 
-    fn remove_child(&mut self, node: impl SceneNodeIdentifier) {
-        let id = node.id();
-        self.with_children_mut(|store| {
-            store.0.retain(|child| child.id() != id);
-        });
+    fn remove_child(&mut self, node: impl SceneNodeIdentifier) -> Option<AnyNode> {
+        self.with_children_mut(|store| store.remove_child(node))
+    }
+
+    /// Like `child` but reports an error instead of skipping nodes that would create a cycle
+    fn try_add_child(&mut self, node: impl IntoSceneNode) -> Result<&mut Self, SceneTreeError> {
+        self.with_children_mut(|store| store.try_add_child(node))?;
+        Ok(self)
+    }
+
+    fn insert_child_at(&mut self, index: usize, node: impl IntoSceneNode) -> &mut Self {
+        self.with_children_mut(|store| store.insert_child_at(index, node));
+        self
+    }
+
+    fn swap_children(&mut self, a: impl SceneNodeIdentifier, b: impl SceneNodeIdentifier) -> bool {
+        self.with_children_mut(|store| store.swap_children(a, b))
+    }
+
+    fn set_child_index(&mut self, node: impl SceneNodeIdentifier, index: usize) -> bool {
+        self.with_children_mut(|store| store.set_child_index(node, index))
+    }
+
+    fn get_child_index(&mut self, node: impl SceneNodeIdentifier) -> Option<usize> {
+        self.with_children_mut(|store| ChildrenStore::get_child_index(store, node))
+    }
+
+    /// Returns a handle to the child with the given id
+    fn get_child_by_id(&mut self, node: impl SceneNodeIdentifier) -> Option<AnyNode> {
+        self.with_children_mut(|store| ChildrenStore::get_child_by_id(store, node).cloned())
+    }
+
+    fn detach_from_parent(&mut self, node: impl SceneNodeIdentifier) -> Option<AnyNode> {
+        self.with_children_mut(|store| store.detach_from_parent(node))
+    }
+
+    fn children_count(&mut self) -> usize {
+        self.with_children_mut(|store| store.len())
+    }
+
+    /// Draw children in the order of their z_index instead of the insertion order.
+    /// The children are sorted before they are prepared for rendering
+    fn sortable_children(&mut self, sortable: bool) -> &mut Self {
+        self.with_children_mut(|store| store.set_sortable(sortable));
+        self
+    }
+
+    fn sort_children(&mut self) {
+        self.with_children_mut(|store| store.sort_children());
     }
 }
 // Blanket implementation for any type that implements ChildrenAccessMut and ParentNode
 impl<T: ChildrenAccessMut + ParentNode> ChildrenController for T {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        render::renderable::DisplayObjectMut,
+        scene::{Container, Graphics, ParentNode, SceneNodeId, SceneNodeIdentifier},
+    };
+
+    use super::{ChildrenAccessMut, ChildrenController};
+
+    fn child_ids(container: &mut Container) -> Vec<SceneNodeId> {
+        container.with_children_mut(|store| store.iter().map(|c| c.id()).collect())
+    }
+
+    #[test]
+    fn reorder_children() {
+        let mut stage = Container::new();
+        let a = Graphics::new();
+        let b = Graphics::new();
+        let c = Graphics::new();
+
+        stage.child(&a).child(&b);
+        stage.insert_child_at(0, &c);
+        assert_eq!(child_ids(&mut stage), [c.id(), a.id(), b.id()]);
+
+        assert!(stage.swap_children(&c, &b));
+        assert_eq!(child_ids(&mut stage), [b.id(), a.id(), c.id()]);
+
+        assert!(stage.set_child_index(&b, 10));
+        assert_eq!(child_ids(&mut stage), [a.id(), c.id(), b.id()]);
+        assert_eq!(stage.get_child_index(&c), Some(1));
+
+        assert!(stage.get_child_by_id(&a).is_some());
+        assert!(stage.remove_child(&a).is_some());
+        assert!(stage.get_child_by_id(&a).is_none());
+        assert!(!stage.swap_children(&a, &b));
+        assert_eq!(stage.children_count(), 2);
+    }
+
+    #[test]
+    fn detach_nested_child() {
+        let mut stage = Container::new();
+        let mut group = Container::new();
+        let leaf = Graphics::new();
+
+        group.child(&leaf);
+        stage.child(&group);
+
+        let detached = stage
+            .detach_from_parent(&leaf)
+            .expect("leaf is a descendant");
+        assert_eq!(detached.id(), leaf.id());
+        assert_eq!(group.children_count(), 0);
+        assert!(stage.detach_from_parent(&leaf).is_none());
+
+        stage.extend(std::iter::once(detached));
+        assert_eq!(child_ids(&mut stage), [group.id(), leaf.id()]);
+    }
+
+    #[test]
+    fn stable_sort_by_z_index() {
+        let mut stage = Container::new();
+        let mut a = Graphics::new();
+        let b = Graphics::new();
+        let mut c = Graphics::new();
+        let d = Graphics::new();
+
+        stage.sortable_children(true).children([&a, &b, &c, &d]);
+        a.set_z_index(1);
+        c.set_z_index(-1);
+
+        stage.sort_children();
+        assert_eq!(child_ids(&mut stage), [c.id(), b.id(), d.id(), a.id()]);
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::RwLock;
+
+use super::WeakNode;
+
+static VERSION: AtomicU64 = AtomicU64::new(0);
+
+fn next_version() -> u64 {
+    VERSION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Parent link and change tracking of a scene node.
+/// Kept outside of the node's lock so ancestors can be marked while a node is being mutated
+#[derive(Debug)]
+pub struct NodeState {
+    parent: RwLock<Option<WeakNode>>,
+    /// version of the last change to the node itself
+    changed: AtomicU64,
+    /// version of the last change to the node or anything below it
+    subtree_changed: AtomicU64,
+}
+
+impl Default for NodeState {
+    fn default() -> Self {
+        let version = next_version();
+        Self {
+            parent: RwLock::new(None),
+            changed: AtomicU64::new(version),
+            subtree_changed: AtomicU64::new(version),
+        }
+    }
+}
+
+impl NodeState {
+    /// Changes made after this version have not been observed yet
+    pub fn current_version() -> u64 {
+        VERSION.load(Ordering::Relaxed)
+    }
+
+    pub fn parent(&self) -> Option<WeakNode> {
+        self.parent.read().clone()
+    }
+
+    pub(crate) fn set_parent(&self, parent: Option<WeakNode>) {
+        *self.parent.write() = parent;
+        self.mark_dirty();
+    }
+
+    /// True if the node itself changed after `version`
+    pub fn is_dirty_since(&self, version: u64) -> bool {
+        self.changed.load(Ordering::Relaxed) > version
+    }
+
+    /// True if the node or any of its descendants changed after `version`
+    pub fn is_subtree_dirty_since(&self, version: u64) -> bool {
+        self.subtree_changed.load(Ordering::Relaxed) > version
+    }
+
+    /// Marks a change to the node itself (display state, content), ancestors are marked as well
+    pub fn mark_dirty(&self) {
+        let version = next_version();
+        self.changed.store(version, Ordering::Relaxed);
+        self.subtree_changed.store(version, Ordering::Relaxed);
+        self.mark_ancestors(version);
+    }
+
+    /// Marks a change below the node, eg: a child was added or removed
+    pub fn mark_subtree_dirty(&self) {
+        let version = next_version();
+        self.subtree_changed.store(version, Ordering::Relaxed);
+        self.mark_ancestors(version);
+    }
+
+    fn mark_ancestors(&self, version: u64) {
+        let mut next = self.parent().and_then(|parent| parent.upgrade());
+        while let Some(node) = next {
+            let Some(state) = node.0.state() else {
+                break;
+            };
+            state.subtree_changed.fetch_max(version, Ordering::Relaxed);
+            next = state.parent().and_then(|parent| parent.upgrade());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NodeState;
+    use crate::{
+        render::renderable::DisplayObjectMut,
+        scene::{ChildrenController, Container, Graphics, ParentNode},
+        vec2, Color,
+    };
+
+    #[test]
+    fn changes_propagate_to_ancestors() {
+        let mut stage = Container::new();
+        let mut group = Container::new();
+        let mut leaf = Graphics::new();
+        let sibling = Graphics::new();
+
+        group.child(&leaf);
+        stage.child(&group).child(&sibling);
+
+        let version = NodeState::current_version();
+        assert!(!stage.node.state.is_subtree_dirty_since(version));
+
+        leaf.set_position(vec2(1.0, 1.0));
+        assert!(leaf.node.state.is_dirty_since(version));
+        assert!(group.node.state.is_subtree_dirty_since(version));
+        assert!(stage.node.state.is_subtree_dirty_since(version));
+        assert!(!stage.node.state.is_dirty_since(version));
+        assert!(!sibling.node.state.is_subtree_dirty_since(version));
+
+        let version = NodeState::current_version();
+        leaf.rect((0.0, 0.0, 10.0, 10.0));
+        assert!(!stage.node.state.is_subtree_dirty_since(version));
+        leaf.fill(Color::RED);
+        assert!(stage.node.state.is_subtree_dirty_since(version));
+    }
+
+    #[test]
+    fn structure_changes_mark_parents() {
+        let mut stage = Container::new();
+        let mut group = Container::new();
+        let leaf = Graphics::new();
+        stage.child(&group);
+
+        let version = NodeState::current_version();
+        group.child(&leaf);
+        assert!(leaf.node.state.is_dirty_since(version));
+        assert!(stage.node.state.is_subtree_dirty_since(version));
+
+        let version = NodeState::current_version();
+        stage.child(&leaf);
+        assert!(group.node.state.is_subtree_dirty_since(version));
+
+        let version = NodeState::current_version();
+        stage.remove_child(&leaf);
+        assert!(stage.node.state.is_subtree_dirty_since(version));
+        assert!(!group.node.state.is_subtree_dirty_since(version));
+    }
+}
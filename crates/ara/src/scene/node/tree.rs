@@ -0,0 +1,312 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use ara_math::Mat3;
+use thiserror::Error;
+
+use super::{AnyNode, IntoSceneNode, SceneNodeId};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SceneTreeError {
+    #[error("Cannot add node {child:?} to {parent:?}: the node is the parent itself or one of its ancestors")]
+    Cycle {
+        parent: SceneNodeId,
+        child: SceneNodeId,
+    },
+}
+
+/// Weak handle to a scene node, parents are referenced through it so children never keep them alive
+#[derive(Clone)]
+pub struct WeakNode {
+    id: SceneNodeId,
+    upgrade: Arc<dyn Fn() -> Option<AnyNode> + Send + Sync>,
+}
+
+impl WeakNode {
+    pub(crate) fn new(
+        id: SceneNodeId,
+        upgrade: impl Fn() -> Option<AnyNode> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            id,
+            upgrade: Arc::new(upgrade),
+        }
+    }
+
+    pub fn id(&self) -> SceneNodeId {
+        self.id
+    }
+
+    /// Returns a handle to the node if it is still alive
+    pub fn upgrade(&self) -> Option<AnyNode> {
+        (self.upgrade)()
+    }
+}
+
+impl std::fmt::Debug for WeakNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WeakNode").field(&self.id).finish()
+    }
+}
+
+impl AnyNode {
+    pub fn parent(&self) -> Option<AnyNode> {
+        self.0.state()?.parent()?.upgrade()
+    }
+
+    /// Handles to the direct children of the node
+    pub fn children(&self) -> Vec<AnyNode> {
+        let mut children = Vec::new();
+        self.0
+            .with_children(&mut |store| children.extend(store.iter().cloned()));
+        children
+    }
+
+    /// Iterates the parent, grand parent and so on up to the root
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors {
+            next: self.parent(),
+        }
+    }
+
+    /// Pre-order traversal of the node and its descendants, yielding each node with its depth
+    pub fn depth_first(&self) -> DepthFirst {
+        DepthFirst {
+            stack: vec![(self.clone(), 0)],
+        }
+    }
+
+    /// Level-order traversal of the node and its descendants, yielding each node with its depth
+    pub fn breadth_first(&self) -> BreadthFirst {
+        BreadthFirst {
+            queue: VecDeque::from([(self.clone(), 0)]),
+        }
+    }
+
+    /// First node in depth first order, starting with this node, that matches the predicate
+    pub fn find(&self, mut predicate: impl FnMut(&AnyNode) -> bool) -> Option<AnyNode> {
+        self.depth_first()
+            .map(|(node, _)| node)
+            .find(|node| predicate(node))
+    }
+
+    /// Returns true if this node is the parent of `node` or one of its ancestors
+    pub fn is_ancestor_of(&self, node: &AnyNode) -> bool {
+        let id = self.id();
+        node.ancestors().any(|ancestor| ancestor.id() == id)
+    }
+
+    /// Transform from the local space of the node into the space of the root of its tree
+    pub fn world_transform(&self) -> Mat3 {
+        let mut transform = self.0.display().local_transform();
+        for ancestor in self.ancestors() {
+            transform = transform * ancestor.0.display().local_transform();
+        }
+        transform
+    }
+}
+
+pub struct Ancestors {
+    next: Option<AnyNode>,
+}
+
+impl Iterator for Ancestors {
+    type Item = AnyNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next.take()?;
+        self.next = node.parent();
+        Some(node)
+    }
+}
+
+pub struct DepthFirst {
+    stack: Vec<(AnyNode, usize)>,
+}
+
+impl Iterator for DepthFirst {
+    type Item = (AnyNode, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, depth) = self.stack.pop()?;
+        node.0.with_children(&mut |store| {
+            self.stack
+                .extend(store.iter().rev().map(|child| (child.clone(), depth + 1)));
+        });
+        Some((node, depth))
+    }
+}
+
+pub struct BreadthFirst {
+    queue: VecDeque<(AnyNode, usize)>,
+}
+
+impl Iterator for BreadthFirst {
+    type Item = (AnyNode, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, depth) = self.queue.pop_front()?;
+        node.0.with_children(&mut |store| {
+            self.queue
+                .extend(store.iter().map(|child| (child.clone(), depth + 1)));
+        });
+        Some((node, depth))
+    }
+}
+
+pub fn depth_first(root: impl IntoSceneNode) -> DepthFirst {
+    root.into_any_node().depth_first()
+}
+
+pub fn breadth_first(root: impl IntoSceneNode) -> BreadthFirst {
+    root.into_any_node().breadth_first()
+}
+
+/// First node in depth first order, starting with the root, that matches the predicate
+pub fn find(root: impl IntoSceneNode, predicate: impl FnMut(&AnyNode) -> bool) -> Option<AnyNode> {
+    root.into_any_node().find(predicate)
+}
+
+/// Ancestors of the node with the given id, nearest first.
+/// Returns None if the node is not part of the tree under `root`
+pub fn ancestors(root: impl IntoSceneNode, id: SceneNodeId) -> Option<Vec<AnyNode>> {
+    let root = root.into_any_node();
+    let node = root.find(|node| node.id() == id)?;
+
+    let root_id = root.id();
+    let mut ancestors = Vec::new();
+    if node.id() != root_id {
+        for ancestor in node.ancestors() {
+            let is_root = ancestor.id() == root_id;
+            ancestors.push(ancestor);
+            if is_root {
+                break;
+            }
+        }
+    }
+    Some(ancestors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        render::renderable::DisplayObjectMut,
+        scene::{
+            ChildrenController, Container, Graphics, ParentNode, RenderRoot, SceneNodeIdentifier,
+        },
+        vec2,
+    };
+
+    fn ids(nodes: impl IntoIterator<Item = (AnyNode, usize)>) -> Vec<(SceneNodeId, usize)> {
+        nodes
+            .into_iter()
+            .map(|(node, depth)| (node.id(), depth))
+            .collect()
+    }
+
+    #[test]
+    fn parent_links_follow_the_tree() {
+        let mut stage = Container::new();
+        let mut other = Container::new();
+        let leaf = Graphics::new();
+
+        assert!(leaf.parent().is_none());
+        stage.child(&leaf);
+        assert_eq!(leaf.parent().map(|p| p.id()), Some(stage.id()));
+
+        // adding to another parent moves the node
+        other.child(&leaf);
+        assert_eq!(leaf.parent().map(|p| p.id()), Some(other.id()));
+        assert_eq!(stage.children_count(), 0);
+
+        assert!(leaf.remove_from_parent());
+        assert!(leaf.parent().is_none());
+        assert!(!leaf.remove_from_parent());
+
+        let mut group = Container::new();
+        group.child(&leaf);
+        drop(group);
+        assert!(leaf.parent().is_none());
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let mut stage = Container::new();
+        let mut group = Container::new();
+        let mut leaf = Graphics::new();
+
+        stage.child(&group);
+        group.child(&leaf);
+
+        assert_eq!(
+            leaf.try_add_child(&stage).err(),
+            Some(SceneTreeError::Cycle {
+                parent: leaf.id(),
+                child: stage.id()
+            })
+        );
+        assert!(group.try_add_child(&group.clone()).is_err());
+
+        // skipped without touching the tree
+        leaf.child(&group);
+        assert_eq!(leaf.children_count(), 0);
+        assert_eq!(group.parent().map(|p| p.id()), Some(stage.id()));
+    }
+
+    #[test]
+    fn traversal_order_and_queries() {
+        let mut stage = Container::new();
+        let mut a = Container::new();
+        let a1 = Graphics::new();
+        let mut b = Graphics::new();
+        let b1 = Graphics::new();
+
+        a.child(&a1);
+        b.child(&b1);
+        stage.child(&a).child(&b);
+
+        assert_eq!(
+            ids(depth_first(&stage)),
+            [
+                (stage.id(), 0),
+                (a.id(), 1),
+                (a1.id(), 2),
+                (b.id(), 1),
+                (b1.id(), 2)
+            ]
+        );
+        assert_eq!(
+            ids(breadth_first(&stage)),
+            [
+                (stage.id(), 0),
+                (a.id(), 1),
+                (b.id(), 1),
+                (a1.id(), 2),
+                (b1.id(), 2)
+            ]
+        );
+
+        let found = find(&stage, |node| {
+            node.children().len() == 1 && node.id() != a.id()
+        });
+        assert_eq!(found.map(|node| node.id()), Some(b.id()));
+
+        let path: Vec<_> = ancestors(&stage, b1.id())
+            .unwrap()
+            .iter()
+            .map(|node| node.id())
+            .collect();
+        assert_eq!(path, [b.id(), stage.id()]);
+        // stops at the given root
+        assert_eq!(ancestors(&a, a1.id()).unwrap().len(), 1);
+        assert!(ancestors(&a, b1.id()).is_none());
+
+        let node = (&b1).into_any_node();
+        assert!(node.parent().unwrap().is_ancestor_of(&node));
+        assert!((&stage).into_any_node().is_ancestor_of(&node));
+
+        stage.set_position(vec2(10.0, 0.0));
+        b.set_scale(vec2(2.0, 2.0));
+        assert_eq!(node.world_transform() * vec2(1.0, 1.0), vec2(12.0, 2.0));
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{
+    render::{systems::System, ItemContext, RenderRunner},
+    Subscription,
+};
+
+use std::ops::Range;
+
+use super::{DisplayState, NodeState, SceneNodeId, WorldState};
+
+static INITIAL_NODE_CAPACITY: u64 = 64;
+
+/// Composes the display state of scene nodes down the tree during prepare
+/// and uploads the resulting world transform and alpha of each node to the gpu.
+/// Nodes bind their entry with a dynamic offset when painting,
+/// so moving a parent never requires rebuilding the geometry of its children.
+/// Entries are kept across frames, subtrees that did not change since they were prepared reuse them
+pub struct WorldTransformSystem {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    stack: Vec<StackEntry>,
+    slots: ahash::HashMap<SceneNodeId, NodeSlot>,
+    free: Vec<u32>,
+    frame: u64,
+    /// physical pixels per scene unit of the view being rendered
+    resolution: f32,
+    /// the resolution differs from the previous frame, nothing prepared before can be reused
+    rescaled: bool,
+    data: Vec<u8>,
+    /// bytes of `data` that changed since the last sync
+    dirty_range: Option<Range<usize>>,
+    stride: u64,
+    buffer: NodeUniformsBuffer,
+    _sub: Option<Subscription>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StackEntry {
+    id: SceneNodeId,
+    world: WorldState,
+    /// the world state differs from the one prepared previously, descendants have to be prepared as well
+    changed: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NodeSlot {
+    offset: u32,
+    world: WorldState,
+    /// node the world state was composed with, None for the root
+    parent: Option<SceneNodeId>,
+    /// `NodeState` version at the time the node was prepared
+    version: u64,
+    /// last frame the node was part of the rendered tree
+    frame: u64,
+}
+
+impl WorldTransformSystem {
+    pub fn new(cx: &mut ItemContext<Self>) -> Self {
+        let device = cx.gpu.device.clone();
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = wgpu::util::align_to(std::mem::size_of::<NodeUniformData>() as u64, alignment);
+
+        let buffer = NodeUniformsBuffer::new(&device, stride * INITIAL_NODE_CAPACITY);
+
+        let sub = cx
+            .add_runner(RenderRunner::Start, |runner| {
+                let resolution = runner.view.resolution();
+                runner.update_system(|this: &mut Self, _| this.begin(resolution));
+                Ok(())
+            })
+            // runs after the RenderableSystem has prepared the scene
+            .join(cx.add_runner(RenderRunner::PreRender, |runner| {
+                runner.update_system(|this: &mut Self, _| this.sync());
+                Ok(())
+            }));
+
+        Self {
+            queue: cx.gpu.queue.clone(),
+            device,
+            stack: Default::default(),
+            slots: Default::default(),
+            free: Default::default(),
+            frame: 0,
+            resolution: 1.0,
+            rescaled: false,
+            data: Default::default(),
+            dirty_range: None,
+            stride,
+            buffer,
+            _sub: Some(sub),
+        }
+    }
+}
+
+impl System for WorldTransformSystem {
+    fn init(&mut self, _cx: &mut crate::render::RenderContext) {}
+}
+
+impl WorldTransformSystem {
+    fn begin(&mut self, resolution: f32) {
+        self.stack.clear();
+        self.frame += 1;
+        self.rescaled = resolution != self.resolution;
+        self.resolution = resolution;
+    }
+
+    /// Physical pixels per scene unit of the view being rendered,
+    /// strokes are tessellated for the pixel scale of the world transform times this
+    pub fn resolution(&self) -> f32 {
+        self.resolution
+    }
+
+    /// The state of the node currently on top of the stack
+    pub fn current(&self) -> WorldState {
+        self.stack
+            .last()
+            .map(|entry| entry.world)
+            .unwrap_or_default()
+    }
+
+    /// True if the node and its subtree can be drawn with what was prepared in a previous frame
+    pub fn can_reuse(&self, id: SceneNodeId, state: &NodeState) -> bool {
+        let parent = self.stack.last();
+        if self.rescaled || parent.is_some_and(|parent| parent.changed) {
+            return false;
+        }
+
+        self.slots.get(&id).is_some_and(|slot| {
+            slot.parent == parent.map(|parent| parent.id)
+                && !state.is_subtree_dirty_since(slot.version)
+        })
+    }
+
+    /// Keeps the entry of a node that was not prepared this frame
+    pub fn retain(&mut self, id: SceneNodeId) {
+        if let Some(slot) = self.slots.get_mut(&id) {
+            slot.frame = self.frame;
+        }
+    }
+
+    /// Composes the display state of the node with its parent and records it for painting.
+    /// Every push must be followed by a pop after the children of the node are prepared
+    pub fn push(&mut self, id: SceneNodeId, display: &DisplayState) -> WorldState {
+        let world = self.current().child(display);
+        let version = NodeState::current_version();
+        let frame = self.frame;
+        let parent = self.stack.last().map(|parent| parent.id);
+
+        let (slot, changed) = match self.slots.get_mut(&id) {
+            Some(slot) => {
+                let changed = slot.world != world;
+                slot.world = world;
+                slot.parent = parent;
+                slot.version = version;
+                slot.frame = frame;
+                (*slot, changed)
+            }
+            None => {
+                let offset = self.allocate();
+                let slot = NodeSlot {
+                    offset,
+                    world,
+                    parent,
+                    version,
+                    frame,
+                };
+                self.slots.insert(id, slot);
+                (slot, true)
+            }
+        };
+
+        if changed {
+            let start = slot.offset as usize;
+            let uniform = NodeUniformData::new(&world);
+            let bytes = bytemuck::bytes_of(&uniform);
+            self.data[start..start + bytes.len()].copy_from_slice(bytes);
+            self.mark_dirty(start..start + self.stride as usize);
+        }
+
+        self.stack.push(StackEntry { id, world, changed });
+        world
+    }
+
+    pub fn pop(&mut self) -> Option<WorldState> {
+        self.stack.pop().map(|entry| entry.world)
+    }
+
+    /// World state the node was last prepared with
+    pub fn get(&self, id: SceneNodeId) -> Option<WorldState> {
+        self.slots.get(&id).map(|slot| slot.world)
+    }
+
+    /// Dynamic offset of the node in the uniform buffer
+    pub fn get_offset(&self, id: SceneNodeId) -> Option<u32> {
+        self.slots.get(&id).map(|slot| slot.offset)
+    }
+
+    pub fn get_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.buffer.bind_group_layout
+    }
+
+    pub fn get_bind_group(&self) -> &wgpu::BindGroup {
+        &self.buffer.bind_group
+    }
+
+    fn allocate(&mut self) -> u32 {
+        if let Some(offset) = self.free.pop() {
+            return offset;
+        }
+        let offset = self.data.len();
+        self.data.resize(offset + self.stride as usize, 0);
+        offset as u32
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty_range = Some(match self.dirty_range.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+
+    fn sync(&mut self) {
+        debug_assert!(self.stack.is_empty(), "Unbalanced world transform stack");
+
+        // nodes that were not part of this frame are gone from the tree
+        let frame = self.frame;
+        let free = &mut self.free;
+        self.slots.retain(|_, slot| {
+            let keep = slot.frame == frame;
+            if !keep {
+                free.push(slot.offset);
+            }
+            keep
+        });
+
+        let size = self.data.len() as u64;
+        if size > self.buffer.capacity {
+            self.buffer
+                .resize(&self.device, size.next_power_of_two().max(self.stride));
+            self.dirty_range = Some(0..self.data.len());
+        }
+
+        let Some(range) = self.dirty_range.take() else {
+            return;
+        };
+
+        self.queue.write_buffer(
+            &self.buffer.gpu_buffer,
+            range.start as u64,
+            &self.data[range],
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod, PartialEq)]
+#[repr(C)]
+pub struct NodeUniformData {
+    transform: [[f32; 4]; 3],
+    alpha: f32,
+    _pad: [f32; 3],
+}
+
+impl NodeUniformData {
+    pub fn new(world: &WorldState) -> Self {
+        Self {
+            transform: world.transform.to_cols_array_padded(),
+            alpha: world.alpha,
+            _pad: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NodeUniformsBuffer {
+    gpu_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    bind_group_layout: wgpu::BindGroupLayout,
+    capacity: u64,
+}
+
+impl NodeUniformsBuffer {
+    fn new(device: &wgpu::Device, capacity: u64) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(
+            &(wgpu::BindGroupLayoutDescriptor {
+                label: Some("Node uniform bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<NodeUniformData>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            }),
+        );
+
+        let (gpu_buffer, bind_group) = Self::create_buffer(device, &bind_group_layout, capacity);
+
+        Self {
+            gpu_buffer,
+            bind_group,
+            bind_group_layout,
+            capacity,
+        }
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, capacity: u64) {
+        let (gpu_buffer, bind_group) =
+            Self::create_buffer(device, &self.bind_group_layout, capacity);
+        self.gpu_buffer = gpu_buffer;
+        self.bind_group = bind_group;
+        self.capacity = capacity;
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        capacity: u64,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let gpu_buffer = device.create_buffer(
+            &(wgpu::BufferDescriptor {
+                label: Some("Node uniform buffer"),
+                size: capacity,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        );
+
+        let bind_group = device.create_bind_group(
+            &(wgpu::BindGroupDescriptor {
+                label: Some("Node uniform bind group"),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &gpu_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<NodeUniformData>() as u64),
+                    }),
+                }],
+            }),
+        );
+
+        (gpu_buffer, bind_group)
+    }
+}
@@ -0,0 +1,716 @@
+use crate::{
+    math::{Rect, Size},
+    paint::Vertex,
+    render::{
+        renderable::{DisplayObject, DisplayObjectMut, View},
+        systems::{BoundTexture, TextureSystem},
+        PaintContext, RenderStatsSystem,
+    },
+    vec2, AraAtlas, AtlasKey, Color, Image, Point, Rgba,
+};
+use ara_math::Mat3;
+use parking_lot::RwLock;
+use std::{
+    hash::{Hash, Hasher},
+    sync::{Arc, Weak},
+};
+
+use super::{
+    AnyNode, ChildrenAccessMut, ChildrenStore, DisplayState, IntoSceneNode, NodeState, ParentNode,
+    RenderRoot, SceneNode, SceneNodeId, SceneNodeIdentifier, SceneNodeLike, WeakNode,
+    WorldTransformSystem,
+};
+
+pub(crate) mod pipe;
+
+use pipe::SpritePipe;
+
+/// What a sprite draws, paths of a `Graphics` can be filled with it as well
+#[derive(Debug, Clone)]
+pub enum SpriteTexture {
+    /// a tile of an atlas, sprites on the same page of an atlas are drawn together
+    Atlas { atlas: Arc<AraAtlas>, key: AtlasKey },
+    /// an image uploaded on its own
+    Image(Image),
+}
+
+impl SpriteTexture {
+    pub fn atlas(atlas: Arc<AraAtlas>, key: impl Into<AtlasKey>) -> Self {
+        Self::Atlas {
+            atlas,
+            key: key.into(),
+        }
+    }
+
+    /// Size in pixels, none when the key is not in the atlas
+    pub fn size(&self) -> Option<Size<f32>> {
+        match self {
+            SpriteTexture::Atlas { atlas, key } => {
+                let size = atlas.get_texture_info(key)?.tile.bounds.size;
+                Some(Size::new(size.width as f32, size.height as f32))
+            }
+            SpriteTexture::Image(image) => {
+                let size = image.size();
+                Some(Size::new(size.width as f32, size.height as f32))
+            }
+        }
+    }
+
+    pub(crate) fn bind(&self, textures: &mut TextureSystem) -> Option<BoundTexture> {
+        match self {
+            SpriteTexture::Atlas { atlas, key } => textures.atlas_tile(atlas, key),
+            SpriteTexture::Image(image) => Some(textures.image(image)),
+        }
+    }
+}
+
+/// Atlases are compared by address
+impl PartialEq for SpriteTexture {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                SpriteTexture::Atlas { atlas, key },
+                SpriteTexture::Atlas {
+                    atlas: other_atlas,
+                    key: other_key,
+                },
+            ) => Arc::ptr_eq(atlas, other_atlas) && key == other_key,
+            (SpriteTexture::Image(image), SpriteTexture::Image(other)) => image == other,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for SpriteTexture {}
+
+impl Hash for SpriteTexture {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            SpriteTexture::Atlas { atlas, key } => {
+                Arc::as_ptr(atlas).hash(state);
+                key.hash(state);
+            }
+            SpriteTexture::Image(image) => image.hash(state),
+        }
+    }
+}
+
+impl From<Image> for SpriteTexture {
+    fn from(image: Image) -> Self {
+        Self::Image(image)
+    }
+}
+
+/// Insets in pixels of the source which keep their size when the sprite is resized,
+/// the edges between them are stretched and the center fills the rest
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NineSlice {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl NineSlice {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+}
+
+/// Draws a texture, the area drawn is placed around the origin by the anchor
+#[derive(Clone)]
+pub struct Sprite {
+    pub(crate) node: SpriteNode,
+}
+
+unsafe impl Send for Sprite {}
+unsafe impl Sync for Sprite {}
+
+impl Sprite {
+    pub fn new(texture: impl Into<SpriteTexture>) -> Self {
+        let id = SceneNodeId::new();
+        let texture = texture.into();
+
+        let state = Arc::new(NodeState::default());
+        let weak_state = Arc::downgrade(&state);
+        let inner = Arc::new_cyclic(|weak: &Weak<RwLock<SpriteInner>>| {
+            let weak = weak.clone();
+            let owner = WeakNode::new(id, move || {
+                let node = SpriteNode {
+                    id,
+                    inner: weak.upgrade()?,
+                    state: weak_state.upgrade()?,
+                };
+                Some(node.into_any_node())
+            });
+            RwLock::new(SpriteInner {
+                display: Default::default(),
+                children: ChildrenStore::with_owner(owner),
+                texture,
+                anchor: Point::new(0.0, 0.0),
+                tint: Color::WHITE,
+                flip_x: false,
+                flip_y: false,
+                source_rect: None,
+                nine_slice: None,
+                size: None,
+            })
+        });
+
+        Self {
+            node: SpriteNode { id, inner, state },
+        }
+    }
+
+    /// A sprite drawing the tile of the key in the atlas
+    pub fn from_atlas(atlas: Arc<AraAtlas>, key: impl Into<AtlasKey>) -> Self {
+        Self::new(SpriteTexture::atlas(atlas, key))
+    }
+
+    pub fn get_texture(&self) -> SpriteTexture {
+        self.node.inner.read().texture.clone()
+    }
+
+    pub fn texture(&mut self, texture: impl Into<SpriteTexture>) -> &mut Self {
+        self.node.inner.write().texture = texture.into();
+        self
+    }
+
+    pub fn get_anchor(&self) -> Point {
+        self.node.inner.read().anchor
+    }
+
+    /// Point of the sprite placed at its origin, (0, 0) is the top left and (1, 1) the bottom right
+    pub fn anchor(&mut self, anchor: impl Into<Point>) -> &mut Self {
+        self.node.inner.write().anchor = anchor.into();
+        self
+    }
+
+    pub fn get_tint(&self) -> Color {
+        self.node.inner.read().tint
+    }
+
+    /// Color the texture is multiplied with
+    pub fn tint(&mut self, tint: impl Into<Color>) -> &mut Self {
+        self.node.inner.write().tint = tint.into();
+        self
+    }
+
+    pub fn get_flip_x(&self) -> bool {
+        self.node.inner.read().flip_x
+    }
+
+    /// Mirrors the texture horizontally, the sprite keeps its place
+    pub fn flip_x(&mut self, flip: bool) -> &mut Self {
+        self.node.inner.write().flip_x = flip;
+        self
+    }
+
+    pub fn get_flip_y(&self) -> bool {
+        self.node.inner.read().flip_y
+    }
+
+    /// Mirrors the texture vertically, the sprite keeps its place
+    pub fn flip_y(&mut self, flip: bool) -> &mut Self {
+        self.node.inner.write().flip_y = flip;
+        self
+    }
+
+    pub fn get_source_rect(&self) -> Option<Rect<f32>> {
+        self.node.inner.read().source_rect.clone()
+    }
+
+    /// Area of the texture drawn in pixels, the whole texture when none
+    pub fn source_rect(&mut self, rect: impl Into<Option<Rect<f32>>>) -> &mut Self {
+        self.node.inner.write().source_rect = rect.into();
+        self
+    }
+
+    pub fn get_nine_slice(&self) -> Option<NineSlice> {
+        self.node.inner.read().nine_slice
+    }
+
+    /// Keeps the corners of the source from stretching when the size of the sprite changes
+    pub fn nine_slice(&mut self, nine_slice: impl Into<Option<NineSlice>>) -> &mut Self {
+        self.node.inner.write().nine_slice = nine_slice.into();
+        self
+    }
+
+    /// Size the sprite is drawn at, zero while the texture is not in its atlas
+    pub fn get_size(&self) -> Size<f32> {
+        self.node.inner.read().size().unwrap_or_default()
+    }
+
+    /// Stretches the source to the size, the size of the source when none
+    pub fn size(&mut self, size: impl Into<Option<Size<f32>>>) -> &mut Self {
+        self.node.inner.write().size = size.into();
+        self
+    }
+}
+
+impl ParentNode for Sprite {
+    fn extend(&mut self, nodes: impl Iterator<Item = AnyNode>) {
+        self.node.inner.write().extend(nodes)
+    }
+}
+
+impl ChildrenAccessMut for Sprite {
+    fn with_children_mut<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut ChildrenStore) -> R,
+    {
+        self.node.inner.write().with_children_mut(f)
+    }
+}
+
+impl SceneNodeIdentifier for Sprite {
+    fn id(&self) -> SceneNodeId {
+        self.node.id
+    }
+}
+
+impl SceneNodeIdentifier for &Sprite {
+    fn id(&self) -> SceneNodeId {
+        self.node.id
+    }
+}
+
+impl RenderRoot for Sprite {
+    type Node = SpriteNode;
+
+    fn node(&self) -> &Self::Node {
+        &self.node
+    }
+}
+
+impl View for Sprite {
+    fn bounds(&self) -> Rect<f32> {
+        self.node.bounds(&Mat3::IDENTITY).unwrap_or_default()
+    }
+
+    fn contains_point(&self, point: Point) -> bool {
+        self.node.hit_test(point).is_some()
+    }
+}
+
+impl DisplayObject for Sprite {
+    fn get_position(&self) -> Point {
+        self.node.inner.read().display.position
+    }
+
+    fn get_scale(&self) -> Point {
+        self.node.inner.read().display.scale
+    }
+
+    fn get_rotation(&self) -> f32 {
+        self.node.inner.read().display.rotation
+    }
+
+    fn get_pivot(&self) -> Point {
+        self.node.inner.read().display.pivot
+    }
+
+    fn renderable(&self) -> bool {
+        self.node.inner.read().display.renderable
+    }
+
+    fn visible(&self) -> bool {
+        self.node.inner.read().display.visible
+    }
+
+    fn alpha(&self) -> f32 {
+        self.node.inner.read().display.alpha
+    }
+
+    fn get_z_index(&self) -> i32 {
+        self.node.inner.read().display.z_index
+    }
+}
+
+impl DisplayObjectMut for Sprite {
+    fn set_position(&mut self, position: Point) {
+        self.node
+            .update_display(|display| display.position = position);
+    }
+
+    fn set_scale(&mut self, scale: Point) {
+        self.node.update_display(|display| display.scale = scale);
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.node
+            .update_display(|display| display.rotation = rotation);
+    }
+
+    fn set_pivot(&mut self, pivot: Point) {
+        self.node.update_display(|display| display.pivot = pivot);
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.node
+            .update_display(|display| display.visible = visible);
+    }
+
+    fn set_alpha(&mut self, alpha: f32) {
+        self.node.update_display(|display| display.alpha = alpha);
+    }
+
+    fn set_renderable(&mut self, renderable: bool) {
+        self.node
+            .update_display(|display| display.renderable = renderable);
+    }
+
+    fn set_z_index(&mut self, z_index: i32) {
+        self.node
+            .update_display(|display| display.z_index = z_index);
+    }
+}
+
+impl IntoSceneNode for &Sprite {
+    type Node = SpriteNode;
+
+    fn into_scene_node(self) -> Self::Node {
+        self.node.clone().into_scene_node()
+    }
+}
+
+pub(crate) struct SpriteInner {
+    pub(crate) display: DisplayState,
+    pub(crate) children: ChildrenStore,
+    pub(crate) texture: SpriteTexture,
+    pub(crate) anchor: Point,
+    pub(crate) tint: Color,
+    pub(crate) flip_x: bool,
+    pub(crate) flip_y: bool,
+    pub(crate) source_rect: Option<Rect<f32>>,
+    pub(crate) nine_slice: Option<NineSlice>,
+    pub(crate) size: Option<Size<f32>>,
+}
+
+unsafe impl Send for SpriteInner {}
+unsafe impl Sync for SpriteInner {}
+
+impl ParentNode for SpriteInner {
+    fn extend(&mut self, nodes: impl Iterator<Item = AnyNode>) {
+        self.children.extend(nodes);
+    }
+}
+
+impl ChildrenAccessMut for SpriteInner {
+    fn with_children_mut<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut ChildrenStore) -> R,
+    {
+        f(&mut self.children)
+    }
+}
+
+impl SpriteInner {
+    fn size(&self) -> Option<Size<f32>> {
+        self.size.or_else(|| match &self.source_rect {
+            Some(source) => Some(source.size()),
+            None => self.texture.size(),
+        })
+    }
+
+    /// Area covered by the sprite in its local space
+    fn local_rect(&self) -> Option<Rect<f32>> {
+        let size = self.size()?;
+        Some(Rect::xywh(
+            -self.anchor.x * size.width,
+            -self.anchor.y * size.height,
+            size.width,
+            size.height,
+        ))
+    }
+
+    /// Appends the quads of the sprite after `transform`, nine sliced sprites are drawn with nine quads.
+    /// `uv` is the area of the texture holding an image of `size` pixels
+    pub(crate) fn build(
+        &self,
+        uv: &Rect<f32>,
+        size: Size<f32>,
+        transform: &Mat3,
+        color: Rgba,
+        vertices: &mut Vec<Vertex>,
+        indices: &mut Vec<u32>,
+    ) {
+        let Some(rect) = self.local_rect() else {
+            return;
+        };
+
+        let source = self
+            .source_rect
+            .clone()
+            .unwrap_or_else(|| Rect::xywh(0.0, 0.0, size.width, size.height));
+        let u = |x: f32| uv.x() + x / size.width.max(1.0) * uv.width();
+        let v = |y: f32| uv.y() + y / size.height.max(1.0) * uv.height();
+
+        let nine_slice = self.nine_slice.as_ref();
+        let (columns, column_count) = slice_axis(
+            (rect.x(), rect.width()),
+            (u(source.x()), u(source.x() + source.width())),
+            source.width(),
+            nine_slice.map(|slice| (slice.left, slice.right)),
+            self.flip_x,
+        );
+        let (rows, row_count) = slice_axis(
+            (rect.y(), rect.height()),
+            (v(source.y()), v(source.y() + source.height())),
+            source.height(),
+            nine_slice.map(|slice| (slice.top, slice.bottom)),
+            self.flip_y,
+        );
+
+        let base = vertices.len() as u32;
+        for &(y, v) in &rows[..row_count] {
+            for &(x, u) in &columns[..column_count] {
+                vertices.push(Vertex::new(*transform * vec2(x, y), color, (u, v)));
+            }
+        }
+
+        let stride = column_count as u32;
+        for row in 0..row_count as u32 - 1 {
+            for column in 0..stride - 1 {
+                let i = base + row * stride + column;
+                indices.extend([i, i + 1, i + stride + 1, i, i + stride + 1, i + stride]);
+            }
+        }
+    }
+}
+
+/// Positions and texture coordinates of the edges between slices along one axis
+fn slice_axis(
+    (start, len): (f32, f32),
+    (t0, t1): (f32, f32),
+    source_len: f32,
+    insets: Option<(f32, f32)>,
+    flip: bool,
+) -> ([(f32, f32); 4], usize) {
+    let (t0, t1) = if flip { (t1, t0) } else { (t0, t1) };
+    let end = start + len;
+
+    let Some((a, b)) = insets else {
+        return ([(start, t0), (end, t1), (0.0, 0.0), (0.0, 0.0)], 2);
+    };
+
+    // the first slice shows the end of the source when flipped
+    let (a, b) = if flip { (b, a) } else { (a, b) };
+    // insets which do not fit shrink together
+    let scale = if a + b > len.abs() && a + b > 0.0 {
+        len.abs() / (a + b)
+    } else {
+        1.0
+    };
+    let texel = (t1 - t0) / source_len.max(f32::EPSILON);
+
+    (
+        [
+            (start, t0),
+            (start + a * scale, t0 + a * texel),
+            (end - b * scale, t1 - b * texel),
+            (end, t1),
+        ],
+        4,
+    )
+}
+
+// ---------------------
+// Node
+// ---------------------
+#[derive(Clone)]
+pub struct SpriteNode {
+    pub(crate) id: SceneNodeId,
+    pub(crate) inner: Arc<RwLock<SpriteInner>>,
+    pub(crate) state: Arc<NodeState>,
+}
+
+impl SpriteNode {
+    fn update_display(&self, f: impl FnOnce(&mut DisplayState)) {
+        f(&mut self.inner.write().display);
+        self.state.mark_dirty();
+    }
+}
+
+unsafe impl Send for SpriteNode {}
+unsafe impl Sync for SpriteNode {}
+
+impl SceneNodeIdentifier for SpriteNode {
+    fn id(&self) -> SceneNodeId {
+        self.id
+    }
+}
+
+impl SceneNode for SpriteNode {
+    fn prepare(&self, render_context: &mut crate::render::RenderContext) {
+        if render_context
+            .read_system(|world: &WorldTransformSystem, _| world.can_reuse(self.id, &self.state))
+        {
+            self.reuse(render_context);
+            return;
+        }
+
+        self.inner.write().children.sort_if_needed();
+
+        let inner = self.inner.read();
+        render_context.update_system(|world: &mut WorldTransformSystem, _| {
+            world.push(self.id, &inner.display);
+        });
+        render_context
+            .update_system(|stats: &mut RenderStatsSystem, _| stats.stats.nodes_prepared += 1);
+
+        // hidden nodes keep their own entry so they are not prepared every frame
+        if inner.display.visible {
+            for child in &inner.children.nodes {
+                child.prepare(render_context);
+            }
+        }
+
+        render_context.update_system(|world: &mut WorldTransformSystem, _| {
+            world.pop();
+        });
+    }
+
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut crate::render::RenderContext) {
+        let inner = self.inner.read();
+        if !inner.display.visible || !inner.display.renderable {
+            return;
+        }
+
+        render_context.update_pipe(|pipe: &mut SpritePipe, cx| {
+            pipe.execute(paint, cx, self.id, &inner);
+        });
+
+        for child in &inner.children.nodes {
+            child.paint(paint, render_context);
+        }
+    }
+
+    fn display(&self) -> DisplayState {
+        self.inner.read().display
+    }
+
+    fn content_bounds(&self) -> Option<Rect<f32>> {
+        self.inner.read().local_rect()
+    }
+
+    fn content_contains_point(&self, point: Point) -> bool {
+        self.inner
+            .read()
+            .local_rect()
+            .is_some_and(|rect| rect.contains_point(&point))
+    }
+
+    fn with_children(&self, f: &mut dyn FnMut(&ChildrenStore)) {
+        f(&self.inner.read().children)
+    }
+
+    fn update_children(&self, f: &mut dyn FnMut(&mut ChildrenStore)) {
+        f(&mut self.inner.write().children)
+    }
+
+    fn state(&self) -> Option<&NodeState> {
+        Some(&self.state)
+    }
+}
+
+impl IntoSceneNode for SpriteNode {
+    type Node = Self;
+
+    fn into_scene_node(self) -> Self::Node {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{hit_test, Container};
+
+    fn image(width: u32, height: u32) -> Image {
+        Image::from_rgba((width, height), vec![255; (width * height * 4) as usize]).unwrap()
+    }
+
+    fn build(sprite: &Sprite) -> (Vec<Vertex>, Vec<u32>) {
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        let size = sprite.get_texture().size().unwrap();
+        sprite.node.inner.read().build(
+            &Rect::xywh(0.0, 0.0, 1.0, 1.0),
+            size,
+            &Mat3::IDENTITY,
+            Color::WHITE.into(),
+            &mut vertices,
+            &mut indices,
+        );
+        (vertices, indices)
+    }
+
+    #[test]
+    fn sprite_bounds_and_hit_test_follow_the_anchor() {
+        let mut stage = Container::new();
+        let mut sprite = Sprite::new(image(40, 20));
+        sprite.anchor((0.5, 1.0));
+        sprite.set_position(vec2(100.0, 100.0));
+        stage.child(&sprite);
+
+        assert_eq!(sprite.local_bounds(), Rect::xywh(-20.0, -20.0, 40.0, 20.0));
+        assert_eq!(sprite.bounds(), Rect::xywh(80.0, 80.0, 40.0, 20.0));
+        assert_eq!(hit_test(&stage, vec2(90.0, 95.0)), Some(sprite.node.id));
+        assert_eq!(hit_test(&stage, vec2(90.0, 105.0)), None);
+
+        sprite.source_rect(Rect::xywh(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(sprite.get_size(), Size::new(10.0, 10.0));
+        sprite.size(Size::new(60.0, 30.0));
+        assert_eq!(sprite.bounds(), Rect::xywh(70.0, 70.0, 60.0, 30.0));
+    }
+
+    #[test]
+    fn nine_slice_keeps_the_corners_and_flips() {
+        let mut sprite = Sprite::new(image(8, 8));
+        sprite
+            .nine_slice(NineSlice::uniform(2.0))
+            .size(Size::new(20.0, 10.0));
+
+        let (vertices, indices) = build(&sprite);
+        assert_eq!((vertices.len(), indices.len()), (16, 54));
+        let columns: Vec<_> = vertices[..4]
+            .iter()
+            .map(|vertex| (vertex.position[0], vertex.uv[0]))
+            .collect();
+        assert_eq!(
+            columns,
+            [(0.0, 0.0), (2.0, 0.25), (18.0, 0.75), (20.0, 1.0)]
+        );
+
+        // a flipped sprite shows the right inset on the left
+        sprite
+            .nine_slice(NineSlice::new(1.0, 2.0, 3.0, 2.0))
+            .flip_x(true);
+        let (vertices, _) = build(&sprite);
+        let columns: Vec<_> = vertices[..4]
+            .iter()
+            .map(|vertex| (vertex.position[0], vertex.uv[0]))
+            .collect();
+        assert_eq!(
+            columns,
+            [(0.0, 1.0), (3.0, 0.625), (19.0, 0.125), (20.0, 0.0)]
+        );
+
+        // without slices the sprite is a single quad
+        sprite
+            .nine_slice(None)
+            .source_rect(Rect::xywh(4.0, 0.0, 4.0, 8.0));
+        let (vertices, indices) = build(&sprite);
+        assert_eq!((vertices.len(), indices.len()), (4, 6));
+        assert_eq!((vertices[0].uv, vertices[1].uv), ([1.0, 0.0], [0.5, 0.0]));
+    }
+}
@@ -0,0 +1,233 @@
+use std::any::Any;
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    paint::Vertex,
+    render::{
+        pipes::RenderPipe,
+        render_target::TargetFormat,
+        systems::{
+            BoundTexture, GlobalUniformSystem, PipelineKey, PipelineLayoutId, PipelineSystem,
+            ShaderId, TextureKey, TextureSystem,
+        },
+        BlendMode, Item, ItemContext, PaintContext, PendingDraw, RenderContext, RenderStatsSystem,
+        StencilMode,
+    },
+    scene::{SceneNodeId, WorldTransformSystem},
+    Rgba,
+};
+
+use super::SpriteInner;
+
+const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+    wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+const VERTEX_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &VERTEX_ATTRIBUTES,
+};
+
+/// Draws sprites in the space of the scene,
+/// so consecutive sprites sharing a texture are merged into one draw call
+pub(crate) struct SpritePipe {
+    resources: Option<(ShaderId, PipelineLayoutId)>,
+    #[allow(unused)]
+    this: Item<Self>,
+}
+
+impl RenderPipe for SpritePipe {
+    fn init(&mut self, cx: &mut RenderContext)
+    where
+        Self: Sized,
+    {
+        let globals = cx.read_system(|globals: &GlobalUniformSystem, _| {
+            globals.get_bind_group_layout().clone()
+        });
+        let texture =
+            cx.read_system(|textures: &TextureSystem, _| textures.texture_layout().clone());
+
+        let layout = cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.add_layout("Sprite Pipeline Layout", &[&globals, &texture])
+        });
+
+        let shader = cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.add_shader(
+                "Sprite Shader",
+                include_str!("../../render/shaders/sprite.wgsl"),
+            )
+        });
+
+        self.resources = Some((shader, layout));
+    }
+}
+
+impl SpritePipe {
+    pub fn new(cx: &mut ItemContext<Self>) -> Self {
+        Self {
+            this: cx.item(),
+            resources: None,
+        }
+    }
+
+    fn pipeline(
+        &self,
+        cx: &mut RenderContext,
+        target: TargetFormat,
+        stencil: StencilMode,
+    ) -> Option<wgpu::RenderPipeline> {
+        let (shader, layout) = self.resources?;
+        let key = PipelineKey {
+            label: "Sprite Pipeline",
+            shader,
+            layout,
+            vertex_entry: "vs",
+            fragment_entry: if stencil.is_mask() {
+                "fs_mask"
+            } else {
+                "fs_main"
+            },
+            vertex_layouts: &[VERTEX_LAYOUT],
+            target,
+            blend: BlendMode::Normal.blend_state(),
+            color_writes: stencil.color_writes(),
+            depth_stencil: Some(stencil.depth_stencil_state()),
+        };
+
+        Some(cx.update_system(|pipelines: &mut PipelineSystem, _| {
+            pipelines.get_or_create(&key).clone()
+        }))
+    }
+
+    pub fn execute(
+        &self,
+        paint: &mut PaintContext,
+        cx: &mut RenderContext,
+        node: SceneNodeId,
+        sprite: &SpriteInner,
+    ) {
+        let Some(world) = cx.read_system(|world: &WorldTransformSystem, _| world.get(node)) else {
+            log::debug!("Node was not prepared skipping: {}", node);
+            return;
+        };
+
+        // a sprite drawn as a mask only contributes its coverage
+        let stencil = paint.stencil_mode();
+        let mut color: Rgba = sprite.tint.into();
+        color.a *= world.alpha;
+        if color.a <= 0.0 && !stencil.is_mask() {
+            return;
+        }
+
+        let Some(texture) =
+            cx.update_system(|textures: &mut TextureSystem, _| sprite.texture.bind(textures))
+        else {
+            log::debug!("Sprite texture is not in its atlas skipping: {}", node);
+            return;
+        };
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        sprite.build(
+            &texture.uv,
+            texture.size,
+            &world.transform,
+            color,
+            &mut vertices,
+            &mut indices,
+        );
+
+        self.draw_quads(paint, cx, &texture, vertices, indices);
+    }
+
+    /// Draws quads in the space of the scene sampling the texture,
+    /// merged with the previous draw when it used the same texture
+    pub(crate) fn draw_quads(
+        &self,
+        paint: &mut PaintContext,
+        cx: &mut RenderContext,
+        texture: &BoundTexture,
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+    ) {
+        if indices.is_empty() {
+            return;
+        }
+
+        let stencil = paint.stencil_mode();
+        let Some(pipeline) = self.pipeline(cx, paint.target_format(), stencil) else {
+            log::warn!("SpritePipe is not initialized");
+            return;
+        };
+
+        let batch = SpriteBatch {
+            texture: texture.key,
+            stencil,
+            pipeline,
+            globals: cx
+                .read_system(|globals: &GlobalUniformSystem, _| globals.get_bind_group().clone()),
+            bind_group: texture.bind_group.clone(),
+            device: cx.gpu.device.clone(),
+            vertices,
+            indices,
+        };
+
+        if !paint.defer(batch) {
+            cx.update_system(|stats: &mut RenderStatsSystem, _| stats.stats.sprite_batches += 1);
+        }
+    }
+}
+
+/// Quads of consecutive sprites sharing a texture, uploaded when they are drawn
+struct SpriteBatch {
+    texture: TextureKey,
+    stencil: StencilMode,
+    pipeline: wgpu::RenderPipeline,
+    globals: wgpu::BindGroup,
+    bind_group: wgpu::BindGroup,
+    device: wgpu::Device,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl PendingDraw for SpriteBatch {
+    fn merge(&mut self, next: &mut dyn Any) -> bool {
+        let Some(next) = next.downcast_mut::<Self>() else {
+            return false;
+        };
+        if next.texture != self.texture || next.stencil != self.stencil {
+            return false;
+        }
+
+        let base = self.vertices.len() as u32;
+        self.vertices.append(&mut next.vertices);
+        self.indices
+            .extend(next.indices.iter().map(|index| index + base));
+        true
+    }
+
+    fn draw(self: Box<Self>, pass: &mut wgpu::RenderPass<'static>) {
+        let vertex_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("ara::Sprite::VertexBuffer"),
+                contents: bytemuck::cast_slice(&self.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let index_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("ara::Sprite::IndexBuffer"),
+                contents: bytemuck::cast_slice(&self.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.globals, &[]);
+        pass.set_bind_group(1, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        pass.draw_indexed(0..self.indices.len() as u32, 0, 0..1);
+    }
+}
@@ -0,0 +1,627 @@
+use crate::{
+    arc_string::ArcString,
+    math::Rect,
+    paint::Vertex,
+    render::{
+        renderable::{DisplayObject, DisplayObjectMut, View},
+        systems::{BoundTexture, GlyphSystem, TextureSystem},
+        PaintContext, RenderContext, RenderStatsSystem,
+    },
+    vec2, AtlasKeySource, Color, Point, Rgba, Text, TextAlign, TextBaseline, TextSystem, Vec2,
+};
+use ara_math::Mat3;
+use cosmic_text::{Align, Attrs, Buffer, Family, LayoutGlyph, Metrics, Shaping};
+use parking_lot::RwLock;
+use std::sync::{Arc, Weak};
+
+use super::{
+    sprite::pipe::SpritePipe, AnyNode, ChildrenAccessMut, ChildrenStore, DisplayState,
+    IntoSceneNode, NodeState, ParentNode, RenderRoot, SceneNode, SceneNodeId, SceneNodeIdentifier,
+    SceneNodeLike, WeakNode, WorldTransformSystem,
+};
+
+/// Height of a line relative to the size of the font, same as `Canvas::fill_text`
+const LINE_HEIGHT_EM: f32 = 1.4;
+
+/// Draws a text, it is shaped when first rendered and again only after its content, style or width changes.
+/// `Text::pos` is placed at the origin of the node following the align and baseline of the text.
+/// Its bounds are empty until it is first rendered
+#[derive(Clone)]
+pub struct TextNode {
+    pub(crate) node: TextSceneNode,
+}
+
+unsafe impl Send for TextNode {}
+unsafe impl Sync for TextNode {}
+
+impl TextNode {
+    pub fn new(text: Text) -> Self {
+        let id = SceneNodeId::new();
+
+        let state = Arc::new(NodeState::default());
+        let weak_state = Arc::downgrade(&state);
+        let inner = Arc::new_cyclic(|weak: &Weak<RwLock<TextInner>>| {
+            let weak = weak.clone();
+            let owner = WeakNode::new(id, move || {
+                let node = TextSceneNode {
+                    id,
+                    inner: weak.upgrade()?,
+                    state: weak_state.upgrade()?,
+                };
+                Some(node.into_any_node())
+            });
+            RwLock::new(TextInner {
+                display: Default::default(),
+                children: ChildrenStore::with_owner(owner),
+                text,
+                fill: Color::BLACK,
+                max_width: None,
+                layout: None,
+            })
+        });
+
+        Self {
+            node: TextSceneNode { id, inner, state },
+        }
+    }
+
+    pub fn get_text(&self) -> Text {
+        self.node.inner.read().text.clone()
+    }
+
+    pub fn text(&mut self, text: Text) -> &mut Self {
+        self.node.update_layout(|inner| inner.text = text);
+        self
+    }
+
+    /// Replaces what is written keeping the style of the text
+    pub fn content(&mut self, content: impl Into<ArcString>) -> &mut Self {
+        let content = content.into();
+        self.node.update_layout(|inner| inner.text.text = content);
+        self
+    }
+
+    pub fn get_fill(&self) -> Color {
+        self.node.inner.read().fill
+    }
+
+    /// Color of the glyphs, color glyphs like emojis only take its alpha
+    pub fn fill(&mut self, fill: impl Into<Color>) -> &mut Self {
+        self.node.inner.write().fill = fill.into();
+        self
+    }
+
+    pub fn get_max_width(&self) -> Option<f32> {
+        self.node.inner.read().max_width
+    }
+
+    /// Wraps lines longer than the width, lines are aligned in this width when set
+    pub fn max_width(&mut self, width: impl Into<Option<f32>>) -> &mut Self {
+        let width = width.into();
+        self.node.update_layout(|inner| inner.max_width = width);
+        self
+    }
+}
+
+impl ParentNode for TextNode {
+    fn extend(&mut self, nodes: impl Iterator<Item = AnyNode>) {
+        self.node.inner.write().extend(nodes)
+    }
+}
+
+impl ChildrenAccessMut for TextNode {
+    fn with_children_mut<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut ChildrenStore) -> R,
+    {
+        self.node.inner.write().with_children_mut(f)
+    }
+}
+
+impl SceneNodeIdentifier for TextNode {
+    fn id(&self) -> SceneNodeId {
+        self.node.id
+    }
+}
+
+impl SceneNodeIdentifier for &TextNode {
+    fn id(&self) -> SceneNodeId {
+        self.node.id
+    }
+}
+
+impl RenderRoot for TextNode {
+    type Node = TextSceneNode;
+
+    fn node(&self) -> &Self::Node {
+        &self.node
+    }
+}
+
+impl View for TextNode {
+    fn bounds(&self) -> Rect<f32> {
+        self.node.bounds(&Mat3::IDENTITY).unwrap_or_default()
+    }
+
+    fn contains_point(&self, point: Point) -> bool {
+        self.node.hit_test(point).is_some()
+    }
+}
+
+impl DisplayObject for TextNode {
+    fn get_position(&self) -> Point {
+        self.node.inner.read().display.position
+    }
+
+    fn get_scale(&self) -> Point {
+        self.node.inner.read().display.scale
+    }
+
+    fn get_rotation(&self) -> f32 {
+        self.node.inner.read().display.rotation
+    }
+
+    fn get_pivot(&self) -> Point {
+        self.node.inner.read().display.pivot
+    }
+
+    fn renderable(&self) -> bool {
+        self.node.inner.read().display.renderable
+    }
+
+    fn visible(&self) -> bool {
+        self.node.inner.read().display.visible
+    }
+
+    fn alpha(&self) -> f32 {
+        self.node.inner.read().display.alpha
+    }
+
+    fn get_z_index(&self) -> i32 {
+        self.node.inner.read().display.z_index
+    }
+}
+
+impl DisplayObjectMut for TextNode {
+    fn set_position(&mut self, position: Point) {
+        self.node
+            .update_display(|display| display.position = position);
+    }
+
+    fn set_scale(&mut self, scale: Point) {
+        self.node.update_display(|display| display.scale = scale);
+    }
+
+    fn set_rotation(&mut self, rotation: f32) {
+        self.node
+            .update_display(|display| display.rotation = rotation);
+    }
+
+    fn set_pivot(&mut self, pivot: Point) {
+        self.node.update_display(|display| display.pivot = pivot);
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.node
+            .update_display(|display| display.visible = visible);
+    }
+
+    fn set_alpha(&mut self, alpha: f32) {
+        self.node.update_display(|display| display.alpha = alpha);
+    }
+
+    fn set_renderable(&mut self, renderable: bool) {
+        self.node
+            .update_display(|display| display.renderable = renderable);
+    }
+
+    fn set_z_index(&mut self, z_index: i32) {
+        self.node
+            .update_display(|display| display.z_index = z_index);
+    }
+}
+
+impl IntoSceneNode for &TextNode {
+    type Node = TextSceneNode;
+
+    fn into_scene_node(self) -> Self::Node {
+        self.node.clone().into_scene_node()
+    }
+}
+
+pub(crate) struct TextInner {
+    pub(crate) display: DisplayState,
+    pub(crate) children: ChildrenStore,
+    pub(crate) text: Text,
+    pub(crate) fill: Color,
+    pub(crate) max_width: Option<f32>,
+    /// none until the text is shaped
+    pub(crate) layout: Option<TextLayout>,
+}
+
+unsafe impl Send for TextInner {}
+unsafe impl Sync for TextInner {}
+
+impl ParentNode for TextInner {
+    fn extend(&mut self, nodes: impl Iterator<Item = AnyNode>) {
+        self.children.extend(nodes);
+    }
+}
+
+impl ChildrenAccessMut for TextInner {
+    fn with_children_mut<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut ChildrenStore) -> R,
+    {
+        f(&mut self.children)
+    }
+}
+
+impl TextInner {
+    fn paint_glyphs(&self, paint: &mut PaintContext, cx: &mut RenderContext, node: SceneNodeId) {
+        let Some(layout) = &self.layout else {
+            return;
+        };
+
+        let Some(world) = cx.read_system(|world: &WorldTransformSystem, _| world.get(node)) else {
+            log::debug!("Node was not prepared skipping: {}", node);
+            return;
+        };
+
+        // a text drawn as a mask only contributes its coverage
+        let mut color: Rgba = self.fill.into();
+        color.a *= world.alpha;
+        if color.a <= 0.0 && !paint.stencil_mode().is_mask() {
+            return;
+        }
+
+        // glyphs are rasterized for the pixels of the target
+        let scale = paint.resolution();
+        let atlas = cx.read_system(|glyphs: &GlyphSystem, _| glyphs.atlas().clone());
+
+        let mut quads: Option<(BoundTexture, Vec<Vertex>, Vec<u32>)> = None;
+        for shaped in &layout.glyphs {
+            let physical = shaped.glyph.physical(
+                (
+                    layout.origin.x * scale,
+                    (layout.origin.y + shaped.baseline) * scale,
+                ),
+                scale,
+            );
+
+            let Some(glyph) = cx
+                .update_system(|glyphs: &mut GlyphSystem, _| glyphs.rasterize(physical.cache_key))
+            else {
+                continue;
+            };
+            let Some(texture) = cx.update_system(|textures: &mut TextureSystem, _| {
+                textures.atlas_tile(&atlas, &glyph.key)
+            }) else {
+                continue;
+            };
+
+            if quads
+                .as_ref()
+                .is_some_and(|(current, ..)| current.key != texture.key)
+            {
+                draw_quads(paint, cx, quads.take());
+            }
+            let uv = texture.uv.clone();
+            let (_, vertices, indices) =
+                quads.get_or_insert_with(|| (texture, Vec::new(), Vec::new()));
+
+            let color = if glyph.key.texture_kind().is_color() {
+                Rgba {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                    a: color.a,
+                }
+            } else {
+                color
+            };
+
+            let x = (physical.x + glyph.left) as f32 / scale;
+            let y = (physical.y - glyph.top) as f32 / scale;
+            let width = glyph.size.width as f32 / scale;
+            let height = glyph.size.height as f32 / scale;
+
+            let base = vertices.len() as u32;
+            for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                vertices.push(Vertex::new(
+                    world.transform * vec2(x + dx * width, y + dy * height),
+                    color,
+                    (uv.x() + dx * uv.width(), uv.y() + dy * uv.height()),
+                ));
+            }
+            indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        draw_quads(paint, cx, quads);
+    }
+}
+
+fn draw_quads(
+    paint: &mut PaintContext,
+    cx: &mut RenderContext,
+    quads: Option<(BoundTexture, Vec<Vertex>, Vec<u32>)>,
+) {
+    let Some((texture, vertices, indices)) = quads else {
+        return;
+    };
+    cx.update_pipe(|pipe: &mut SpritePipe, cx| {
+        pipe.draw_quads(paint, cx, &texture, vertices, indices);
+    });
+}
+
+/// A glyph placed on its line
+#[derive(Debug, Clone)]
+struct ShapedGlyph {
+    glyph: LayoutGlyph,
+    /// offset of the baseline of the line from the top of the text
+    baseline: f32,
+}
+
+/// Shaped lines of a text
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TextLayout {
+    glyphs: Vec<ShapedGlyph>,
+    /// where the top left of the lines is placed in the local space of the node
+    origin: Vec2<f32>,
+    /// area covered by the glyphs in the local space of the node, none for empty texts
+    rect: Option<Rect<f32>>,
+}
+
+impl TextLayout {
+    pub(crate) fn shape(text_system: &TextSystem, text: &Text, max_width: Option<f32>) -> Self {
+        text_system.write(|state| {
+            let font_system = &mut state.font_system;
+
+            let metrics = Metrics::new(text.size, text.size * LINE_HEIGHT_EM);
+            let mut buffer = Buffer::new(font_system, metrics);
+            buffer.set_size(font_system, max_width, None);
+
+            let attrs = Attrs::new()
+                .family(Family::Name(&text.font.family))
+                .weight(text.font.weight.into())
+                .style(text.font.style.into());
+            buffer.set_text(font_system, &text.text, attrs, Shaping::Advanced);
+
+            let align = match text.align {
+                TextAlign::Left => Align::Left,
+                TextAlign::Center => Align::Center,
+                TextAlign::Right => Align::Right,
+            };
+            for line in &mut buffer.lines {
+                line.set_align(Some(align));
+            }
+            buffer.shape_until_scroll(font_system, false);
+
+            // lines are aligned within their paragraph, the widest line aligns them all
+            let mut width = max_width.unwrap_or_else(|| {
+                buffer
+                    .layout_runs()
+                    .map(|run| run.line_w)
+                    .fold(0.0, f32::max)
+            });
+            if max_width.is_none() && align != Align::Left {
+                width = width.ceil();
+                buffer.set_size(font_system, Some(width), None);
+                buffer.shape_until_scroll(font_system, false);
+            }
+
+            let mut glyphs = Vec::new();
+            let mut height: f32 = 0.0;
+            let mut first_baseline = None;
+            let mut extent: Option<(f32, f32)> = None;
+            for run in buffer.layout_runs() {
+                height = height.max(run.line_top + run.line_height);
+                first_baseline.get_or_insert(run.line_y);
+                for glyph in run.glyphs {
+                    let (start, end) = extent.unwrap_or((glyph.x, glyph.x + glyph.w));
+                    extent = Some((start.min(glyph.x), end.max(glyph.x + glyph.w)));
+                    glyphs.push(ShapedGlyph {
+                        glyph: glyph.clone(),
+                        baseline: run.line_y,
+                    });
+                }
+            }
+
+            let x = match text.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => -width / 2.0,
+                TextAlign::Right => -width,
+            };
+            let y = match text.baseline {
+                TextBaseline::Top | TextBaseline::Hanging => 0.0,
+                TextBaseline::Middle => -height / 2.0,
+                TextBaseline::Bottom => -height,
+                TextBaseline::Alphabetic => -first_baseline.unwrap_or_default(),
+            };
+            let origin = vec2(text.pos.x + x, text.pos.y + y);
+
+            Self {
+                glyphs,
+                origin,
+                rect: extent.map(|(start, end)| {
+                    Rect::xywh(origin.x + start, origin.y, end - start, height)
+                }),
+            }
+        })
+    }
+}
+
+// ---------------------
+// Node
+// ---------------------
+#[derive(Clone)]
+pub struct TextSceneNode {
+    pub(crate) id: SceneNodeId,
+    pub(crate) inner: Arc<RwLock<TextInner>>,
+    pub(crate) state: Arc<NodeState>,
+}
+
+impl TextSceneNode {
+    fn update_display(&self, f: impl FnOnce(&mut DisplayState)) {
+        f(&mut self.inner.write().display);
+        self.state.mark_dirty();
+    }
+
+    /// Drops the layout so the text is shaped again when it is next prepared
+    fn update_layout(&self, f: impl FnOnce(&mut TextInner)) {
+        {
+            let mut inner = self.inner.write();
+            f(&mut inner);
+            inner.layout = None;
+        }
+        self.state.mark_dirty();
+    }
+}
+
+unsafe impl Send for TextSceneNode {}
+unsafe impl Sync for TextSceneNode {}
+
+impl SceneNodeIdentifier for TextSceneNode {
+    fn id(&self) -> SceneNodeId {
+        self.id
+    }
+}
+
+impl SceneNode for TextSceneNode {
+    fn prepare(&self, render_context: &mut RenderContext) {
+        if render_context
+            .read_system(|world: &WorldTransformSystem, _| world.can_reuse(self.id, &self.state))
+        {
+            self.reuse(render_context);
+            return;
+        }
+
+        {
+            let mut inner = self.inner.write();
+            inner.children.sort_if_needed();
+
+            if inner.layout.is_none() {
+                let text_system = render_context
+                    .read_system(|glyphs: &GlyphSystem, _| glyphs.text_system().clone());
+                inner.layout = Some(TextLayout::shape(
+                    &text_system,
+                    &inner.text,
+                    inner.max_width,
+                ));
+                render_context.update_system(|stats: &mut RenderStatsSystem, _| {
+                    stats.stats.texts_shaped += 1
+                });
+            }
+        }
+
+        let inner = self.inner.read();
+        render_context.update_system(|world: &mut WorldTransformSystem, _| {
+            world.push(self.id, &inner.display);
+        });
+        render_context
+            .update_system(|stats: &mut RenderStatsSystem, _| stats.stats.nodes_prepared += 1);
+
+        // hidden nodes keep their own entry so they are not prepared every frame
+        if inner.display.visible {
+            for child in &inner.children.nodes {
+                child.prepare(render_context);
+            }
+        }
+
+        render_context.update_system(|world: &mut WorldTransformSystem, _| {
+            world.pop();
+        });
+    }
+
+    fn paint(&self, paint: &mut PaintContext, render_context: &mut RenderContext) {
+        let inner = self.inner.read();
+        if !inner.display.visible || !inner.display.renderable {
+            return;
+        }
+
+        inner.paint_glyphs(paint, render_context, self.id);
+
+        for child in &inner.children.nodes {
+            child.paint(paint, render_context);
+        }
+    }
+
+    fn display(&self) -> DisplayState {
+        self.inner.read().display
+    }
+
+    fn content_bounds(&self) -> Option<Rect<f32>> {
+        self.inner.read().layout.as_ref()?.rect.clone()
+    }
+
+    fn content_contains_point(&self, point: Point) -> bool {
+        self.content_bounds()
+            .is_some_and(|rect| rect.contains_point(&point))
+    }
+
+    fn with_children(&self, f: &mut dyn FnMut(&ChildrenStore)) {
+        f(&self.inner.read().children)
+    }
+
+    fn update_children(&self, f: &mut dyn FnMut(&mut ChildrenStore)) {
+        f(&mut self.inner.write().children)
+    }
+
+    fn state(&self) -> Option<&NodeState> {
+        Some(&self.state)
+    }
+}
+
+impl IntoSceneNode for TextSceneNode {
+    type Node = Self;
+
+    fn into_scene_node(self) -> Self::Node {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(text: Text, max_width: Option<f32>) -> Rect<f32> {
+        let text_system = TextSystem::default();
+        TextLayout::shape(&text_system, &text, max_width)
+            .rect
+            .expect("no glyphs were shaped")
+    }
+
+    #[test]
+    fn text_layout_is_placed_by_align_and_baseline() {
+        let text = Text::new("Hello").size_px(20.0);
+
+        let top_left = layout(text.clone().baseline(TextBaseline::Top), None);
+        assert!(top_left.x().abs() < 2.0 && top_left.y() == 0.0);
+        assert!(top_left.width() > 20.0);
+        assert_eq!(top_left.height(), 20.0 * LINE_HEIGHT_EM);
+
+        let centered = layout(text.clone().align(TextAlign::Center), None);
+        assert!((centered.x() + centered.width() / 2.0).abs() < 1.0);
+        // the origin is on the baseline of the first line
+        assert!(centered.y() < 0.0 && centered.y() + centered.height() > 0.0);
+
+        let right = layout(
+            text.align(TextAlign::Right)
+                .baseline(TextBaseline::Bottom)
+                .pos(100.0, 50.0),
+            None,
+        );
+        assert!((right.x() + right.width() - 100.0).abs() < 1.0);
+        assert_eq!(right.y() + right.height(), 50.0);
+    }
+
+    #[test]
+    fn text_layout_wraps_at_max_width() {
+        let text = Text::new("wrap these words").size_px(20.0);
+
+        let line = layout(text.clone(), None);
+        let wrapped = layout(text, Some(line.width() / 2.0));
+        assert!(wrapped.width() <= line.width() / 2.0);
+        assert!(wrapped.height() > line.height());
+    }
+}
@@ -0,0 +1,276 @@
+//! Headless rendering for tests.
+//! Renders a scene offscreen on a software adapter and compares the result against golden PNG files
+//!
+//! Goldens are read from `<golden_dir>/<name>.png`. Set `ARA_UPDATE_GOLDENS=1` to (re)write them from the
+//! current output. On a mismatch the actual image and a diff are written to the output directory
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use ara_math::Size;
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    gpu::{Backends, Context, ContextSpecification},
+    render::{
+        renderable::DisplayObject, RenderSnapshot, RenderTo, Renderer, RendererSpecification,
+        ViewConfig, ViewTarget,
+    },
+    scene::RenderRoot,
+    Color,
+};
+
+pub const UPDATE_GOLDENS_ENV: &str = "ARA_UPDATE_GOLDENS";
+
+/// Creates a context on a software adapter so tests can run on machines without a gpu.
+/// Backends can be narrowed with `WGPU_BACKEND`
+pub fn headless_context() -> Result<Context> {
+    futures::executor::block_on(Context::new(ContextSpecification {
+        backends: Backends::from_env().unwrap_or_default(),
+        force_fallback_adapter: true,
+        ..Default::default()
+    }))
+}
+
+#[derive(Debug, Clone)]
+pub struct GoldenConfig {
+    /// logical size of the rendered image
+    pub size: Size<u32>,
+    pub resolution: f32,
+    pub clear_color: Color,
+    /// max difference allowed in a single channel before a pixel counts as mismatched
+    pub tolerance: u8,
+    /// number of mismatched pixels allowed, antialiasing differs slightly between adapters
+    pub max_mismatched_pixels: usize,
+    pub golden_dir: PathBuf,
+    /// where actual and diff images are written on failure
+    pub output_dir: PathBuf,
+}
+
+impl Default for GoldenConfig {
+    fn default() -> Self {
+        Self {
+            size: Size::new(128, 128),
+            resolution: 1.0,
+            clear_color: Color::WHITE,
+            tolerance: 2,
+            max_mismatched_pixels: 0,
+            golden_dir: PathBuf::from("tests/golden"),
+            output_dir: std::env::temp_dir().join("ara-golden"),
+        }
+    }
+}
+
+/// A renderer drawing into an offscreen texture
+pub struct TestRenderer {
+    renderer: Renderer,
+    config: GoldenConfig,
+}
+
+impl TestRenderer {
+    pub fn new(config: GoldenConfig) -> Result<Self> {
+        let gpu = headless_context()?;
+        Ok(Self::with_context(&gpu, config))
+    }
+
+    pub fn with_context(gpu: &Context, config: GoldenConfig) -> Self {
+        let mut renderer = Renderer::new(
+            gpu,
+            RendererSpecification {
+                render_to: RenderTo {
+                    target: ViewTarget::Texture,
+                    config: ViewConfig {
+                        size: config.size,
+                        resolution: config.resolution,
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            },
+        );
+        renderer.init();
+
+        Self { renderer, config }
+    }
+
+    pub fn renderer(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+
+    pub fn config(&self) -> &GoldenConfig {
+        &self.config
+    }
+
+    pub fn render<R>(&mut self, root: &R) -> Result<RenderSnapshot>
+    where
+        R: RenderRoot + DisplayObject,
+    {
+        self.renderer.render(root, self.config.clear_color);
+        self.renderer.read_pixels()
+    }
+
+    /// Renders the root and compares it with the golden image `name`
+    pub fn check_golden<R>(&mut self, name: &str, root: &R) -> Result<()>
+    where
+        R: RenderRoot + DisplayObject,
+    {
+        let snapshot = self.render(root)?;
+        let actual = RgbaImage::from_raw(snapshot.width(), snapshot.height(), snapshot.data)
+            .context("snapshot size does not match its data")?;
+
+        let golden_path = self.config.golden_dir.join(format!("{name}.png"));
+
+        if std::env::var_os(UPDATE_GOLDENS_ENV).is_some() {
+            save(&actual, &golden_path)?;
+            return Ok(());
+        }
+
+        if !golden_path.exists() {
+            let actual_path = self.config.output_dir.join(format!("{name}.actual.png"));
+            save(&actual, &actual_path)?;
+            bail!(
+                "golden {} does not exist, output written to {}. Run with {UPDATE_GOLDENS_ENV}=1 to create it",
+                golden_path.display(),
+                actual_path.display()
+            );
+        }
+
+        let expected = image::open(&golden_path)
+            .with_context(|| format!("failed to read golden {}", golden_path.display()))?
+            .to_rgba8();
+
+        let comparison = compare(&expected, &actual, self.config.tolerance);
+        if comparison.mismatched <= self.config.max_mismatched_pixels {
+            return Ok(());
+        }
+
+        let actual_path = self.config.output_dir.join(format!("{name}.actual.png"));
+        save(&actual, &actual_path)?;
+
+        if let Some(diff) = &comparison.diff {
+            let diff_path = self.config.output_dir.join(format!("{name}.diff.png"));
+            save(diff, &diff_path)?;
+            bail!(
+                "{name}: {} pixels differ (max channel difference {}), see {} and {}",
+                comparison.mismatched,
+                comparison.max_difference,
+                actual_path.display(),
+                diff_path.display()
+            );
+        }
+
+        bail!(
+            "{name}: expected a {}x{} image got {}x{}, see {}",
+            expected.width(),
+            expected.height(),
+            actual.width(),
+            actual.height(),
+            actual_path.display()
+        );
+    }
+
+    /// Panicking version of `check_golden`
+    #[track_caller]
+    pub fn assert_golden<R>(&mut self, name: &str, root: &R)
+    where
+        R: RenderRoot + DisplayObject,
+    {
+        if let Err(err) = self.check_golden(name, root) {
+            panic!("{err:#}");
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    /// pixels with a channel differing by more than the tolerance
+    pub mismatched: usize,
+    pub max_difference: u8,
+    /// mismatched pixels in red over a faded copy of the expected image,
+    /// `None` if the sizes differ
+    pub diff: Option<RgbaImage>,
+}
+
+/// Compares two images channel by channel
+pub fn compare(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Comparison {
+    if expected.dimensions() != actual.dimensions() {
+        return Comparison {
+            mismatched: expected.len().max(actual.len()) / 4,
+            max_difference: u8::MAX,
+            diff: None,
+        };
+    }
+
+    let mut mismatched = 0;
+    let mut max_difference = 0;
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+
+    for ((expected, actual), out) in expected
+        .pixels()
+        .zip(actual.pixels())
+        .zip(diff.pixels_mut())
+    {
+        let difference = expected
+            .0
+            .iter()
+            .zip(actual.0)
+            .map(|(a, b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0);
+
+        max_difference = max_difference.max(difference);
+
+        *out = if difference > tolerance {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, b, _] = expected.0;
+            let luma = (r as u32 * 3 + g as u32 * 6 + b as u32) / 10;
+            let faded = (255 - (255 - luma) / 4) as u8;
+            Rgba([faded, faded, faded, 255])
+        };
+    }
+
+    Comparison {
+        mismatched,
+        max_difference,
+        diff: Some(diff),
+    }
+}
+
+fn save(image: &RgbaImage, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    image
+        .save(path)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::compare;
+
+    #[test]
+    fn compares_with_tolerance() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([10, 20, 30, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, Rgba([12, 20, 30, 255]));
+        actual.put_pixel(3, 3, Rgba([10, 20, 90, 255]));
+
+        let result = compare(&expected, &actual, 2);
+        assert_eq!(result.mismatched, 1);
+        assert_eq!(result.max_difference, 60);
+
+        let diff = result.diff.unwrap();
+        assert_eq!(diff.get_pixel(3, 3), &Rgba([255, 0, 0, 255]));
+        assert_ne!(diff.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+
+        let smaller = RgbaImage::new(2, 2);
+        let result = compare(&expected, &smaller, 2);
+        assert!(result.diff.is_none());
+        assert_eq!(result.mismatched, 16);
+    }
+}
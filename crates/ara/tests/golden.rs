@@ -0,0 +1,1073 @@
+use std::sync::Arc;
+
+use ara::{
+    paint::AtlasImage,
+    render::{
+        renderable::{DisplayObjectMut, View},
+        texture::TextureSourceDescriptor,
+        BlendMode, RenderOptions, RenderSnapshot,
+    },
+    scene::{
+        Container, Graphics, ParentNode, RenderRoot, SceneNodeIdentifier, Sprite, SpriteTexture,
+        TextNode,
+    },
+    stroke_to_path,
+    testing::{GoldenConfig, TestRenderer},
+    vec2, AraAtlas, AtlasKey, Color, FillRule, Gradient, Image, LineCap, LineDash, LineJoin, Mat3,
+    PathBuilder, Pattern, Rect, Size, SpreadMode, StrokeStyle, Text, TextBaseline,
+    TextureAddressMode, TextureFilterMode,
+};
+
+fn test_renderer(resolution: f32) -> TestRenderer {
+    TestRenderer::new(GoldenConfig {
+        size: (64, 64).into(),
+        resolution,
+        golden_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden").into(),
+        max_mismatched_pixels: 8,
+        ..Default::default()
+    })
+    .expect("failed to create a headless renderer")
+}
+
+fn scene() -> Container {
+    let mut stage = Container::new();
+
+    let mut shapes = Graphics::new();
+    shapes
+        .rect((4.0, 4.0, 24.0, 24.0))
+        .fill(Color::RED)
+        .circle((44.0, 16.0), 12.0)
+        .fill(Color::BLUE);
+
+    let mut lines = Graphics::new();
+    lines
+        .line_width(4.0)
+        .line_join(LineJoin::Round)
+        .rect((8.0, 8.0, 40.0, 16.0))
+        .stroke(Color::GREEN);
+    lines.set_position(vec2(0.0, 32.0));
+
+    stage.child(&shapes).child(&lines);
+    stage
+}
+
+#[test]
+fn graphics_fill_and_stroke() {
+    test_renderer(1.0).assert_golden("graphics_fill_and_stroke", &scene());
+}
+
+#[test]
+fn graphics_fill_and_stroke_hidpi() {
+    test_renderer(2.0).assert_golden("graphics_fill_and_stroke@2x", &scene());
+}
+
+#[test]
+fn renders_into_targets_of_different_formats() {
+    let mut test = test_renderer(1.0);
+    let renderer = test.renderer();
+
+    let target = renderer.create_texture_target(&TextureSourceDescriptor {
+        size: (64, 64).into(),
+        antialias: false,
+        format: ara::gpu::TextureFormat::Bgra8Unorm,
+        ..Default::default()
+    });
+
+    let stage = scene();
+    for _ in 0..2 {
+        renderer.render(&stage, Color::WHITE);
+        renderer.render(&stage, RenderOptions::default().view(&target));
+
+        let primary = renderer.read_pixels().unwrap();
+        let offscreen = renderer.read_view_pixels(&(&target).into()).unwrap();
+        assert_eq!(primary.get_pixel(10, 10), Some([255, 0, 0, 255]));
+        assert_eq!(offscreen.get_pixel(10, 10), Some([255, 0, 0, 255]));
+        assert_eq!(offscreen.get_pixel(60, 60), Some([255, 255, 255, 255]));
+
+        let read = futures::executor::block_on(renderer.read_view_pixels_async(&(&target).into()));
+        assert_eq!(read.unwrap(), offscreen);
+    }
+}
+
+#[track_caller]
+fn assert_pixels(snapshot: &RenderSnapshot, expected: &[((u32, u32), [u8; 4])]) {
+    for &((x, y), color) in expected {
+        let pixel = snapshot.get_pixel(x, y).unwrap();
+        let close = pixel.iter().zip(color).all(|(a, b)| a.abs_diff(b) <= 2);
+        assert!(close, "pixel at {x},{y} is {pixel:?}, expected {color:?}");
+    }
+}
+
+const GRAY: Color = Color::from_rgb(0x808080);
+
+/// A gray backdrop with a 16x16 square drawn in each cell,
+/// the top two rows blend single fills and the third row blends containers
+fn blend_scene() -> Container {
+    let mut stage = Container::new();
+
+    let mut backdrop = Graphics::new();
+    backdrop.rect((0.0, 0.0, 64.0, 64.0)).fill(GRAY);
+    stage.child(&backdrop);
+
+    let fills = [
+        (BlendMode::Multiply, Color::RED),
+        (BlendMode::Screen, Color::RED),
+        (BlendMode::Difference, Color::WHITE),
+        (BlendMode::Erase, Color::BLACK),
+        (BlendMode::Add, Color::from_rgb(0x404040)),
+        (BlendMode::Darken, Color::from_rgb(0x404040)),
+        (BlendMode::Lighten, Color::from_rgb(0xc0c0c0)),
+        (BlendMode::HardLight, Color::RED),
+    ];
+
+    let mut squares = Graphics::new();
+    for (i, (mode, color)) in fills.into_iter().enumerate() {
+        let (x, y) = ((i % 4) as f32 * 16.0, (i / 4) as f32 * 16.0);
+        squares
+            .blend_mode(mode)
+            .rect((x + 2.0, y + 2.0, 12.0, 12.0))
+            .fill(color);
+    }
+    stage.child(&squares);
+
+    // blue over red inside the group, the result is multiplied as a whole
+    let mut multiply = Container::new();
+    let mut group = Graphics::new();
+    group
+        .rect((2.0, 34.0, 12.0, 12.0))
+        .fill(Color::RED)
+        .rect((8.0, 34.0, 6.0, 12.0))
+        .fill(Color::BLUE);
+    multiply.set_blend_mode(BlendMode::Multiply);
+    multiply.child(&group);
+
+    let mut overlay = Container::new();
+    let mut group = Graphics::new();
+    group.rect((18.0, 34.0, 12.0, 12.0)).fill(Color::RED);
+    overlay.set_blend_mode(BlendMode::Overlay);
+    overlay.child(&group);
+
+    stage.child(&multiply).child(&overlay);
+    stage
+}
+
+#[test]
+fn blend_modes() {
+    let mut test = test_renderer(1.0);
+    let stage = blend_scene();
+
+    let snapshot = test.render(&stage).unwrap();
+    let expected = [
+        ((8, 8), [128, 0, 0, 255]),
+        ((24, 8), [255, 128, 128, 255]),
+        ((40, 8), [127, 127, 127, 255]),
+        ((56, 8), [0, 0, 0, 0]),
+        ((8, 24), [192, 192, 192, 255]),
+        ((24, 24), [64, 64, 64, 255]),
+        ((40, 24), [192, 192, 192, 255]),
+        ((56, 24), [255, 0, 0, 255]),
+        ((4, 40), [128, 0, 0, 255]),
+        ((12, 40), [0, 0, 128, 255]),
+        ((24, 40), [255, 1, 1, 255]),
+        ((40, 40), [128, 128, 128, 255]),
+    ];
+    assert_pixels(&snapshot, &expected);
+
+    test.assert_golden("blend_modes", &stage);
+}
+
+#[test]
+fn consecutive_blend_draws_share_a_layer() {
+    let mut test = test_renderer(1.0);
+
+    let mut stage = Container::new();
+    let mut backdrop = Graphics::new();
+    backdrop.rect((0.0, 0.0, 64.0, 64.0)).fill(GRAY);
+    stage.child(&backdrop);
+
+    let mut squares = Graphics::new();
+    squares.blend_mode(BlendMode::Overlay);
+    for i in 0..3 {
+        squares
+            .rect((i as f32 * 16.0 + 2.0, 2.0, 12.0, 12.0))
+            .fill(Color::RED);
+    }
+    squares
+        .blend_mode(BlendMode::Multiply)
+        .rect((2.0, 18.0, 12.0, 12.0))
+        .fill(Color::RED);
+    stage.child(&squares);
+
+    let stats = test.renderer().render(&stage, Color::WHITE);
+    assert_eq!(stats.layers_composited, 1);
+
+    let snapshot = test.render(&stage).unwrap();
+    let overlay = [255, 1, 1, 255];
+    assert_pixels(
+        &snapshot,
+        &[
+            ((8, 8), overlay),
+            ((24, 8), overlay),
+            ((40, 8), overlay),
+            ((56, 8), [128, 128, 128, 255]),
+            ((8, 24), [128, 0, 0, 255]),
+        ],
+    );
+}
+
+/// Two half transparent groups of a red and a blue square overlapping in the middle,
+/// the left one applies its alpha per child and the right one as a layer
+fn group_opacity_scene() -> Container {
+    let mut stage = Container::new();
+
+    for (i, isolated) in [false, true].into_iter().enumerate() {
+        let mut group = Container::new();
+        let mut squares = Graphics::new();
+        squares
+            .rect((4.0, 4.0, 16.0, 24.0))
+            .fill(Color::RED)
+            .rect((12.0, 4.0, 16.0, 24.0))
+            .fill(Color::BLUE);
+
+        group.set_alpha(0.5);
+        group.set_isolated(isolated);
+        group.set_position(vec2(i as f32 * 32.0, 16.0));
+        group.child(&squares);
+        stage.child(&group);
+    }
+
+    stage
+}
+
+#[test]
+fn group_opacity() {
+    let mut test = test_renderer(1.0);
+    let stage = group_opacity_scene();
+
+    let snapshot = test.render(&stage).unwrap();
+    let expected = [
+        // per child alpha, the red square shows through the blue one
+        ((8, 32), [255, 128, 128, 255]),
+        ((16, 32), [128, 64, 191, 255]),
+        // layer alpha, the overlap is blue only
+        ((40, 32), [255, 128, 128, 255]),
+        ((48, 32), [128, 128, 255, 255]),
+        ((56, 32), [128, 128, 255, 255]),
+    ];
+    assert_pixels(&snapshot, &expected);
+
+    test.assert_golden("group_opacity", &stage);
+}
+
+fn rect_path(rect: Rect<f32>) -> ara::Path {
+    let mut builder = PathBuilder::default();
+    builder.rect(&rect);
+    builder.build()
+}
+
+fn line_path(from: (f32, f32), to: (f32, f32)) -> ara::Path {
+    let mut builder = PathBuilder::default();
+    builder.begin(from.into());
+    builder.line_to(to.into());
+    builder.end(false);
+    builder.build()
+}
+
+#[test]
+fn dashed_strokes() {
+    let mut test = test_renderer(1.0);
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    graphics
+        .line_width(4.0)
+        .line_dash([8.0, 4.0])
+        .path(&line_path((4.0, 8.0), (60.0, 8.0)))
+        .stroke(Color::BLACK)
+        // dots are zero length dashes with round caps
+        .line_width(6.0)
+        .line_cap(LineCap::Round)
+        .line_dash([0.0, 10.0])
+        .path(&line_path((8.0, 20.0), (60.0, 20.0)))
+        .stroke(Color::RED)
+        // the pattern runs around the corners of a closed rect
+        .line_width(4.0)
+        .line_cap(LineCap::Butt)
+        .line_dash([6.0, 6.0])
+        .line_dash_offset(3.0)
+        .rect((8.0, 32.0, 24.0, 24.0))
+        .stroke(Color::BLUE)
+        .line_dash([4.0, 4.0])
+        .line_dash_offset(0.0)
+        .circle((48.0, 44.0), 10.0)
+        .stroke(Color::GREEN);
+    stage.child(&graphics);
+
+    let (black, red, blue, white) = (
+        [0, 0, 0, 255],
+        [255, 0, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 255, 255],
+    );
+
+    let snapshot = test.render(&stage).unwrap();
+    assert_pixels(
+        &snapshot,
+        &[
+            ((8, 8), black),
+            ((14, 8), white),
+            ((20, 8), black),
+            ((8, 20), red),
+            ((13, 20), white),
+            ((18, 20), red),
+            // the first and the last dash meet at the corner
+            ((8, 32), blue),
+            ((14, 32), white),
+            ((20, 32), blue),
+            ((20, 44), white),
+        ],
+    );
+
+    test.assert_golden("dashed_strokes", &stage);
+}
+
+#[test]
+fn fill_rules() {
+    let mut test = test_renderer(1.0);
+
+    let star = |x: f32, y: f32| {
+        let mut path = PathBuilder::default();
+        for i in 0..5 {
+            let angle = i as f32 * 4.0 * std::f32::consts::PI / 5.0;
+            let point = vec2(x + angle.sin() * 13.0, y - angle.cos() * 13.0);
+            if i == 0 {
+                path.begin(point);
+            } else {
+                path.line_to(point);
+            }
+        }
+        path.end(true);
+        path.build()
+    };
+
+    let mut squares = PathBuilder::default();
+    squares.rect(&Rect::xywh(4.0, 4.0, 24.0, 24.0));
+    squares.rect(&Rect::xywh(12.0, 12.0, 8.0, 8.0));
+
+    let mut donut = PathBuilder::default();
+    donut.circle(vec2(48.0, 48.0), 12.0);
+    donut.circle(vec2(48.0, 48.0), 6.0);
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    graphics
+        .fill_rule(FillRule::EvenOdd)
+        .path(&squares.build())
+        .fill(Color::RED)
+        .path(&star(16.0, 50.0))
+        .fill(Color::GREEN)
+        .path(&donut.build())
+        .fill(Color::BLACK)
+        // the center of the star is wound twice
+        .fill_rule(FillRule::NonZero)
+        .path(&star(48.0, 18.0))
+        .fill(Color::BLUE);
+    stage.child(&graphics);
+
+    let (red, green, blue, black, white) = (
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [0, 0, 0, 255],
+        [255, 255, 255, 255],
+    );
+
+    let snapshot = test.render(&stage).unwrap();
+    assert_pixels(
+        &snapshot,
+        &[
+            ((8, 8), red),
+            ((16, 16), white),
+            ((16, 41), green),
+            ((16, 50), white),
+            ((48, 8), blue),
+            ((48, 18), blue),
+            ((38, 48), black),
+            ((48, 48), white),
+        ],
+    );
+
+    test.assert_golden("fill_rules", &stage);
+}
+
+#[test]
+fn miter_joins() {
+    let mut test = test_renderer(1.0);
+
+    let corner = |x: f32| {
+        let mut path = PathBuilder::default();
+        path.begin(vec2(x - 8.0, 56.0));
+        path.line_to(vec2(x, 24.0));
+        path.line_to(vec2(x + 8.0, 56.0));
+        path.end(false);
+        path.build()
+    };
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    // the miters of these corners are about 4.1 times half the line width
+    graphics
+        .line_width(6.0)
+        .path(&corner(12.0))
+        .stroke(Color::BLACK)
+        .miter_limit(2.0)
+        .path(&corner(32.0))
+        .stroke(Color::RED)
+        .line_join(LineJoin::MiterClip)
+        .path(&corner(52.0))
+        .stroke(Color::BLUE);
+    stage.child(&graphics);
+
+    let (black, red, blue, white) = (
+        [0, 0, 0, 255],
+        [255, 0, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 255, 255],
+    );
+
+    let snapshot = test.render(&stage).unwrap();
+    assert_pixels(
+        &snapshot,
+        &[
+            // the full miter
+            ((12, 15), black),
+            // beveled past the limit
+            ((32, 22), white),
+            ((32, 24), red),
+            // clipped twice the half width above the corner
+            ((52, 17), white),
+            ((52, 18), blue),
+        ],
+    );
+
+    test.assert_golden("miter_joins", &stage);
+}
+
+#[test]
+fn hairline_strokes() {
+    let mut test = test_renderer(1.0);
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    // thinner than a pixel fades out instead of getting thinner
+    graphics
+        .line_width(0.5)
+        .path(&line_path((4.0, 8.5), (60.0, 8.5)))
+        .stroke(Color::BLACK)
+        .line_width(1.5)
+        .path(&line_path((4.0, 16.5), (60.0, 16.5)))
+        .stroke(Color::BLACK);
+    stage.child(&graphics);
+
+    // the same strokes in a node scaled by four
+    let mut scaled = Graphics::new();
+    scaled
+        .line_width(1.0)
+        .path(&line_path((1.0, 1.0), (15.0, 1.0)))
+        .stroke(Color::RED)
+        .non_scaling_stroke(true)
+        .path(&line_path((1.0, 5.0), (15.0, 5.0)))
+        .stroke(Color::BLUE)
+        // a quarter of a unit is a pixel once the node is scaled
+        .non_scaling_stroke(false)
+        .line_width(0.25)
+        .path(&line_path((1.0, 7.0), (15.0, 7.0)))
+        .stroke(Color::GREEN);
+    scaled.set_position(vec2(0.0, 24.5));
+    scaled.set_scale(vec2(4.0, 4.0));
+    stage.child(&scaled);
+
+    let snapshot = test.render(&stage).unwrap();
+    assert_pixels(
+        &snapshot,
+        &[
+            ((32, 7), [255, 255, 255, 255]),
+            ((32, 8), [127, 127, 127, 255]),
+            ((32, 16), [0, 0, 0, 255]),
+            ((32, 28), [255, 0, 0, 255]),
+            ((32, 31), [255, 255, 255, 255]),
+            ((32, 44), [0, 0, 255, 255]),
+            ((32, 45), [255, 255, 255, 255]),
+            ((32, 52), [0, 255, 0, 255]),
+        ],
+    );
+
+    test.assert_golden("hairline_strokes", &stage);
+}
+
+#[test]
+fn hairline_strokes_at_resolution() {
+    let mut test = test_renderer(2.0);
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    // half a unit is a whole physical pixel at twice the resolution
+    graphics
+        .line_width(0.5)
+        .path(&line_path((4.0, 8.25), (60.0, 8.25)))
+        .stroke(Color::BLACK)
+        .line_width(0.25)
+        .path(&line_path((4.0, 12.25), (60.0, 12.25)))
+        .stroke(Color::BLACK)
+        // non scaling strokes are measured in physical pixels
+        .line_width(1.0)
+        .non_scaling_stroke(true)
+        .path(&line_path((4.0, 20.25), (60.0, 20.25)))
+        .stroke(Color::BLUE);
+    stage.child(&graphics);
+
+    let snapshot = test.render(&stage).unwrap();
+    let white = [255, 255, 255, 255];
+    assert_pixels(
+        &snapshot,
+        &[
+            ((64, 15), white),
+            ((64, 16), [0, 0, 0, 255]),
+            ((64, 17), white),
+            ((64, 24), [127, 127, 127, 255]),
+            ((64, 25), white),
+            ((64, 39), white),
+            ((64, 40), [0, 0, 255, 255]),
+            ((64, 41), white),
+        ],
+    );
+
+    test.assert_golden("hairline_strokes_at_resolution", &stage);
+}
+
+/// Top left a square clipped to a diamond, top right a square clipped to the left half of a circle,
+/// the bottom half is masked by a circle with a half transparent group masked again to its right half
+fn clip_scene() -> Container {
+    let mut stage = Container::new();
+
+    let mut diamond = Graphics::new();
+    diamond
+        .translate(16.0, 16.0)
+        .rotate(std::f32::consts::FRAC_PI_4)
+        .clip_path(&rect_path(Rect::xywh(-10.0, -10.0, 20.0, 20.0)))
+        .reset_transform()
+        .rect((0.0, 0.0, 32.0, 32.0))
+        .fill(Color::RED);
+
+    let mut circle = PathBuilder::default();
+    circle.circle(vec2(48.0, 16.0), 12.0);
+    let mut half_circle = Graphics::new();
+    half_circle
+        .clip_path(&circle.build())
+        // stays a scissor rect
+        .clip_path(&rect_path(Rect::xywh(32.0, 0.0, 16.0, 32.0)))
+        .rect((32.0, 0.0, 32.0, 32.0))
+        .fill(Color::BLUE);
+
+    let mut masked = Container::new();
+    let mut mask = Graphics::new();
+    mask.circle((32.0, 48.0), 14.0).fill(Color::WHITE);
+    masked.set_mask(&mask);
+
+    let mut green = Graphics::new();
+    green.rect((0.0, 32.0, 64.0, 32.0)).fill(Color::GREEN);
+
+    let mut group = Container::new();
+    let mut right = Graphics::new();
+    right.rect((32.0, 32.0, 32.0, 32.0)).fill(Color::WHITE);
+    let mut blue = Graphics::new();
+    blue.rect((0.0, 32.0, 64.0, 32.0)).fill(Color::BLUE);
+    group.set_mask(&right);
+    group.set_alpha(0.5);
+    group.set_isolated(true);
+    group.child(&blue);
+
+    masked.child(&green).child(&group);
+    stage.child(&diamond).child(&half_circle).child(&masked);
+    stage
+}
+
+#[test]
+fn clip_and_mask() {
+    let mut test = test_renderer(1.0);
+    let stage = clip_scene();
+
+    let snapshot = test.render(&stage).unwrap();
+    let expected = [
+        ((16, 16), [255, 0, 0, 255]),
+        ((16, 4), [255, 0, 0, 255]),
+        ((4, 4), [255, 255, 255, 255]),
+        ((28, 28), [255, 255, 255, 255]),
+        ((42, 16), [0, 0, 255, 255]),
+        ((54, 16), [255, 255, 255, 255]),
+        ((36, 4), [255, 255, 255, 255]),
+        ((24, 48), [0, 255, 0, 255]),
+        ((40, 48), [0, 128, 128, 255]),
+        ((4, 36), [255, 255, 255, 255]),
+        ((60, 60), [255, 255, 255, 255]),
+    ];
+    assert_pixels(&snapshot, &expected);
+
+    test.assert_golden("clip_and_mask", &stage);
+}
+
+#[test]
+fn clip_rect_follows_node_transform() {
+    let mut test = test_renderer(2.0);
+
+    let mut stage = Container::new();
+    let mut clipped = Graphics::new();
+    clipped
+        .translate(4.0, 4.0)
+        .clip(Rect::xywh(0.0, 0.0, 8.0, 8.0))
+        .reset_transform()
+        .rect((0.0, 0.0, 32.0, 32.0))
+        .fill(Color::RED);
+    clipped.set_position(vec2(32.0, 32.0));
+    clipped.set_scale(vec2(2.0, 2.0));
+    stage.child(&clipped);
+
+    // the clip covers 40..56 in logical pixels, twice that in physical ones
+    let snapshot = test.render(&stage).unwrap();
+    let expected = [
+        ((78, 78), [255, 255, 255, 255]),
+        ((82, 82), [255, 0, 0, 255]),
+        ((110, 110), [255, 0, 0, 255]),
+        ((114, 114), [255, 255, 255, 255]),
+    ];
+    assert_pixels(&snapshot, &expected);
+}
+
+#[test]
+fn clip_rect_rotated_with_node() {
+    let mut test = test_renderer(1.0);
+
+    let mut circle = PathBuilder::default();
+    circle.circle(vec2(0.0, 0.0), 14.0);
+
+    let mut stage = Container::new();
+    let mut clipped = Graphics::new();
+    clipped
+        .clip(Rect::xywh(-12.0, -12.0, 24.0, 24.0))
+        .rect((-32.0, -32.0, 64.0, 64.0))
+        .fill(Color::RED)
+        .clip_path(&circle.build())
+        .rect((-32.0, -32.0, 64.0, 64.0))
+        .fill(Color::GREEN)
+        .reset_clip()
+        .rect((16.0, 0.0, 8.0, 8.0))
+        .fill(Color::BLUE);
+    clipped.set_position(vec2(32.0, 32.0));
+    clipped.set_rotation(std::f32::consts::FRAC_PI_4);
+    stage.child(&clipped);
+
+    // the clip is a diamond reaching 17 pixels from the center, not its bounding box
+    let snapshot = test.render(&stage).unwrap();
+    let (red, green, blue, white) = (
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 255, 255],
+    );
+    assert_pixels(
+        &snapshot,
+        &[
+            ((32, 32), green),
+            ((47, 32), red),
+            ((44, 20), white),
+            ((20, 20), white),
+            // drawn after the clip was reset
+            ((43, 49), blue),
+        ],
+    );
+
+    test.assert_golden("clip_rect_rotated_with_node", &stage);
+}
+
+/// A linear, a focal radial, a conic and a rotated repeating gradient, one in each quadrant,
+/// with the offset of the quadrant the context is translated by
+fn gradients() -> [(Gradient, (f32, f32)); 4] {
+    let linear = Gradient::linear((0.0, 0.0), (32.0, 0.0))
+        .add_stop(0.0, Color::RED)
+        .add_stop(1.0, Color::BLUE);
+
+    let radial = Gradient::two_point_radial((16.0, 16.0), 16.0, (10.0, 12.0))
+        .add_stop(0.0, Color::WHITE)
+        .add_stop(0.5, Color::GREEN)
+        .add_stop(1.0, Color::BLACK)
+        .spread(SpreadMode::Reflect);
+
+    let conic = Gradient::conic((16.0, 16.0), 0.0)
+        .add_stop(0.0, Color::RED)
+        .add_stop(0.5, Color::GREEN)
+        .add_stop(1.0, Color::RED);
+
+    let repeating = Gradient::linear((0.0, 0.0), (8.0, 0.0))
+        .add_stop(0.0, Color::BLUE)
+        .add_stop(1.0, Color::from_rgb(0xffff00))
+        .spread(SpreadMode::Repeat)
+        .transform(Mat3::from_rotation(std::f32::consts::FRAC_PI_4));
+
+    let mut quadrants = [linear, radial, conic, repeating].into_iter().enumerate();
+    std::array::from_fn(|_| {
+        let (i, gradient) = quadrants.next().expect("four quadrants");
+        (gradient, ((i % 2) as f32 * 32.0, (i / 2) as f32 * 32.0))
+    })
+}
+
+#[test]
+fn gradient_fills() {
+    let mut test = test_renderer(1.0);
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    for (gradient, (x, y)) in gradients() {
+        graphics
+            .reset_transform()
+            .translate(x, y)
+            .rect((0.0, 0.0, 32.0, 32.0))
+            .fill(gradient);
+    }
+    stage.child(&graphics);
+
+    // the shader matches the gradient evaluated on the cpu at pixel centers
+    let snapshot = test.render(&stage).unwrap();
+    let mut expected = Vec::new();
+    for (gradient, (dx, dy)) in gradients() {
+        let transform = Mat3::from_translation(dx, dy);
+        let to_gradient = transform.inverse() * gradient.transform.inverse();
+        for (x, y) in [(4, 4), (12, 20), (16, 16), (22, 9), (27, 27)] {
+            let pixel = transform * vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let color = gradient.color_at(to_gradient * pixel);
+            expected.push(((pixel.x as u32, pixel.y as u32), color.into()));
+        }
+    }
+    assert_pixels(&snapshot, &expected);
+
+    test.assert_golden("gradient_fills", &stage);
+}
+
+#[test]
+fn pattern_fills() {
+    let mut test = test_renderer(1.0);
+
+    // a 2x2 checker scaled to 8px cells
+    let (r, b) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+    let checker = Image::from_rgba((2, 2), [r, b, b, r].concat()).unwrap();
+    let pattern = |address_mode| {
+        Pattern::new(checker.clone())
+            .transform(Mat3::from_scale(8.0, 8.0))
+            .address_mode(address_mode)
+            .filter(TextureFilterMode::Nearest)
+    };
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    graphics
+        .rect((0.0, 0.0, 32.0, 32.0))
+        .fill(pattern(TextureAddressMode::Repeat))
+        .translate(32.0, 0.0)
+        .rect((0.0, 0.0, 32.0, 32.0))
+        .fill(pattern(TextureAddressMode::MirrorRepeat))
+        .reset_transform()
+        .translate(0.0, 32.0)
+        .rect((0.0, 0.0, 32.0, 32.0))
+        .fill(pattern(TextureAddressMode::ClampToEdge))
+        .reset_transform()
+        .circle((48.0, 48.0), 14.0)
+        .fill(pattern(TextureAddressMode::Repeat));
+    stage.child(&graphics);
+
+    let snapshot = test.render(&stage).unwrap();
+    assert_pixels(
+        &snapshot,
+        &[
+            // repeat, the pattern follows the transform of the path
+            ((4, 4), r),
+            ((12, 4), b),
+            ((20, 4), r),
+            ((20, 12), b),
+            // mirror repeat
+            ((36, 4), r),
+            ((44, 4), b),
+            ((52, 4), b),
+            ((60, 4), r),
+            // clamp to edge
+            ((28, 36), b),
+            ((28, 60), r),
+            ((4, 60), b),
+            // a circle is filled in the space of the stage
+            ((44, 44), r),
+            ((52, 44), b),
+        ],
+    );
+
+    test.assert_golden("pattern_fills", &stage);
+}
+
+/// A 16x16 image with a red, green, blue and white quadrant
+fn quadrant_image() -> Image {
+    let quadrants: Vec<u8> = (0..16)
+        .flat_map(|y| (0..16).map(move |x| (x, y)))
+        .flat_map(|(x, y)| match (x < 8, y < 8) {
+            (true, true) => [255, 0, 0, 255],
+            (false, true) => [0, 255, 0, 255],
+            (true, false) => [0, 0, 255, 255],
+            (false, false) => [255, 255, 255, 255],
+        })
+        .collect();
+    Image::from_rgba((16, 16), quadrants).unwrap()
+}
+
+#[test]
+fn texture_fills() {
+    let mut test = test_renderer(1.0);
+
+    let (r, g, b, w) = (
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 255, 255],
+    );
+
+    let image = quadrant_image();
+
+    // a red tile and a mask covering the left half of its tile
+    let atlas = Arc::new(AraAtlas::new(test.renderer().gpu().clone()));
+    let red = AtlasKey::Image(AtlasImage::new(1));
+    let mask = AtlasKey::Image(AtlasImage::new(2).greyscale());
+    atlas.create_texture_init(&red, Size::new(8, 8), &r.repeat(64));
+    let half: Vec<u8> = (0..64).map(|i| if i % 8 < 4 { 255 } else { 0 }).collect();
+    atlas.create_texture_init(&mask, Size::new(8, 8), &half);
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    graphics
+        .rect((0.0, 0.0, 32.0, 32.0))
+        .fill_texture(image.clone(), Color::WHITE)
+        .circle((48.0, 16.0), 14.0)
+        .fill_texture(SpriteTexture::atlas(atlas.clone(), red), Color::WHITE)
+        .rect((0.0, 32.0, 32.0, 32.0))
+        .fill_texture(SpriteTexture::atlas(atlas, mask), Color::BLUE)
+        .translate(32.0, 32.0)
+        .scale(2.0, 2.0)
+        .rect((0.0, 0.0, 16.0, 16.0))
+        .fill_texture(image, Color::WHITE);
+    stage.child(&graphics);
+
+    let snapshot = test.render(&stage).unwrap();
+    assert_pixels(
+        &snapshot,
+        &[
+            // stretched over the bounds of the path
+            ((8, 8), r),
+            ((24, 8), g),
+            ((8, 24), b),
+            ((24, 24), w),
+            ((48, 16), r),
+            // a mask takes the color of the tint
+            ((8, 48), b),
+            ((24, 48), w),
+            // the texture follows the transform of the path
+            ((40, 40), r),
+            ((56, 40), g),
+            ((40, 56), b),
+        ],
+    );
+
+    test.assert_golden("texture_fills", &stage);
+}
+
+#[test]
+fn sprites() {
+    let mut test = test_renderer(1.0);
+
+    let (r, g, b, w) = (
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 255, 255],
+    );
+
+    // two tiles on the same page of an atlas
+    let atlas = Arc::new(AraAtlas::new(test.renderer().gpu().clone()));
+    let red = AtlasKey::Image(AtlasImage::new(1));
+    let blue = AtlasKey::Image(AtlasImage::new(2));
+    atlas.create_texture_init(&red, Size::new(8, 8), &r.repeat(64));
+    atlas.create_texture_init(&blue, Size::new(8, 8), &b.repeat(64));
+
+    let image = quadrant_image();
+
+    let mut stage = Container::new();
+    for i in 0..4 {
+        let key = if i % 2 == 0 { &red } else { &blue };
+        let mut sprite = Sprite::from_atlas(atlas.clone(), key.clone());
+        sprite.size(Size::new(16.0, 16.0));
+        sprite.set_position(vec2(i as f32 * 16.0, 0.0));
+        match i {
+            2 => sprite.set_alpha(0.5),
+            3 => _ = sprite.tint(Color::from_rgb(0x808080)),
+            _ => {}
+        }
+        stage.child(&sprite);
+    }
+
+    let mut stretched = Sprite::new(image.clone());
+    stretched.size(Size::new(32.0, 32.0));
+    stretched.set_position(vec2(0.0, 16.0));
+    stage.child(&stretched);
+
+    let mut flipped = Sprite::new(image.clone());
+    flipped
+        .size(Size::new(32.0, 32.0))
+        .anchor((0.5, 0.5))
+        .flip_x(true);
+    flipped.set_position(vec2(48.0, 32.0));
+    stage.child(&flipped);
+
+    // the blue quadrant
+    let mut source = Sprite::new(image);
+    source.source_rect(Rect::xywh(0.0, 8.0, 8.0, 8.0));
+    source.set_position(vec2(0.0, 48.0));
+    stage.child(&source);
+
+    // consecutive sprites on the same atlas page or image share a draw call
+    let stats = test.renderer().render(&stage, Color::WHITE);
+    assert_eq!(stats.sprite_batches, 2);
+
+    let snapshot = test.render(&stage).unwrap();
+    assert_pixels(
+        &snapshot,
+        &[
+            ((8, 8), r),
+            ((24, 8), b),
+            ((40, 8), [255, 128, 128, 255]),
+            ((56, 8), [0, 0, 128, 255]),
+            ((8, 24), r),
+            ((24, 24), g),
+            ((8, 40), b),
+            ((24, 40), w),
+            // mirrored around its center
+            ((40, 24), g),
+            ((56, 24), r),
+            ((40, 40), w),
+            ((56, 40), b),
+            ((4, 52), b),
+        ],
+    );
+
+    test.assert_golden("sprites", &stage);
+}
+
+#[test]
+fn text_nodes() {
+    let mut test = test_renderer(1.0);
+
+    let mut stage = Container::new();
+    let mut text = TextNode::new(Text::new("Ara").size_px(12.0).baseline(TextBaseline::Top));
+    text.fill(Color::RED);
+    text.set_position(vec2(4.0, 4.0));
+    text.set_scale(vec2(2.0, 2.0));
+    stage.child(&text);
+
+    // the text is shaped once and its layout is kept while it does not change
+    let stats = test.renderer().render(&stage, Color::WHITE);
+    assert_eq!(stats.texts_shaped, 1);
+    assert!(stats.sprite_batches >= 1);
+    let stats = test.renderer().render(&stage, Color::WHITE);
+    assert_eq!(stats.texts_shaped, 0);
+
+    text.set_position(vec2(8.0, 8.0));
+    let stats = test.renderer().render(&stage, Color::WHITE);
+    assert_eq!(stats.texts_shaped, 0);
+
+    text.content("Ara!");
+    let snapshot = test.render(&stage).unwrap();
+
+    // glyphs are drawn with the transform of the node and stay inside its bounds
+    let bounds = text.bounds();
+    assert!(bounds.x() >= 8.0 && bounds.y() == 8.0);
+    assert!(bounds.width() > 24.0 && bounds.height() > 24.0);
+
+    let mut covered = 0;
+    for y in 0..snapshot.height() {
+        for x in 0..snapshot.width() {
+            let pixel = snapshot.get_pixel(x, y).unwrap();
+            if pixel == [255, 255, 255, 255] {
+                continue;
+            }
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            assert!(
+                px > bounds.x() - 1.0
+                    && px < bounds.x() + bounds.width() + 1.0
+                    && py > bounds.y() - 1.0
+                    && py < bounds.y() + bounds.height() + 1.0,
+                "pixel at {x},{y} is outside of the text"
+            );
+            // the glyphs are red with antialiased edges
+            assert!(pixel[0] == 255 && pixel[1] == pixel[2], "{pixel:?}");
+            covered += 1;
+        }
+    }
+    assert!(covered > 20);
+    assert_eq!(
+        text.hit_test(vec2(bounds.x() + 1.0, bounds.y() + 1.0)),
+        Some(text.id())
+    );
+}
+
+#[test]
+fn stroke_outlines() {
+    let mut test = test_renderer(1.0);
+
+    let zigzag = |x: f32| {
+        let mut path = PathBuilder::default();
+        path.begin(vec2(x, 12.0));
+        path.line_to(vec2(x + 20.0, 24.0));
+        path.line_to(vec2(x, 36.0));
+        path.line_to(vec2(x + 20.0, 48.0));
+        path.end(false);
+        path.build()
+    };
+    let stroke_style = StrokeStyle::default()
+        .line_width(6.0)
+        .line_cap(LineCap::Round)
+        .line_join(LineJoin::Round)
+        .line_dash(LineDash::new([12.0, 12.0]));
+
+    let mut stage = Container::new();
+    let mut graphics = Graphics::new();
+    // the stroke on the left and its outline filled on the right
+    graphics
+        .line_width(6.0)
+        .line_cap(LineCap::Round)
+        .line_join(LineJoin::Round)
+        .line_dash([12.0, 12.0])
+        .path(&zigzag(6.0))
+        .stroke(Color::BLACK)
+        .path(&stroke_to_path(&zigzag(38.0), &stroke_style))
+        .fill(Color::BLACK);
+    stage.child(&graphics);
+
+    let snapshot = test.render(&stage).unwrap();
+    let (black, white) = ([0, 0, 0, 255], [255, 255, 255, 255]);
+    for x in [0, 32] {
+        assert_pixels(
+            &snapshot,
+            &[
+                // inside the first dash and the one after it
+                ((x + 10, 15), black),
+                ((x + 20, 27), black),
+                // in the gap between them
+                ((x + 21, 21), white),
+            ],
+        );
+    }
+
+    test.assert_golden("stroke_outlines", &stage);
+}
@@ -19,7 +19,7 @@ pub fn run(gpu: Context) {
     canvas.draw_round_rect(
         &rect,
         &Corners::with_all(10.0),
-        Brush::filled(Color::TORCH_RED).stroke_color(Color::WHITE).line_width(5).antialias(true)
+        Brush::filled(Color::TORCH_RED).stroke_color(Color::WHITE).line_width(5.0).antialias(true)
     );
 
     let center = rect.center();
@@ -1,4 +1,4 @@
-/// this example is for the new rendering API.
+// this example is for the new rendering API.
 
 use ara::{
     render::{ RenderTo, ViewSystemExt },
@@ -11,7 +11,7 @@ use ara::{
 pub fn run(gpu: Context) {
     let mut renderer = ara::render::Renderer::new(&gpu, ara::render::RendererSpecification {
         render_to: RenderTo {
-            target: ara::render::ViewTarget::Empty, // todo change to image after we support offscreen rendering
+            target: ara::render::ViewTarget::Texture,
             config: ara::render::ViewConfig {
                 size: (800, 600).into(),
                 ..Default::default()
@@ -36,7 +36,7 @@ pub fn run(gpu: Context) {
         .fill(Color::KHAKI)
         .circle(center, 100.0)
         .fill(Color::KHAKI)
-        .line_width(10)
+        .line_width(10.0)
         .stroke(Color::RED);
 
     stage.child(&g);
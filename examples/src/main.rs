@@ -14,13 +14,16 @@ const EXAMPLES: &[Example] = &[
         name: "basic",
         run: basic::run,
     },
+    Example {
+        name: "graphics",
+        run: graphics::run,
+    },
 ];
 
 fn main() {
     let example_name = std::env
         ::args()
-        .skip(1)
-        .next()
+        .nth(1)
         .unwrap_or_else(|| {
             println!("Usage: cargo run <example_name>");
             println!("Available examples:");